@@ -0,0 +1,928 @@
+//! The YTS `list_movies` API: request parameters, the HTTP client, and page fetching.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use rayon::prelude::*;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{truncate_chars, Result, YtsError};
+
+pub const API_BASE: &str = "https://yts.bz/api/v2/list_movies.json";
+/// Where YTS is expected to publish v3 of its API.
+pub const API_BASE_V3: &str = "https://yts.bz/api/v3/list_movies.json";
+
+/// Largest page size the YTS API accepts, and the default.
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+pub const DEFAULT_MAX_RETRIES: u8 = 5;
+/// Defaults of `--timeout` and `--connect-timeout`, in seconds.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Validators and bodies of fetched pages, kept next to the database so
+/// re-fetches can send conditional requests.
+pub const PAGE_CACHE_FILE: &str = ".yts_page_cache.json";
+
+/// `sort_by` values accepted by the YTS `list_movies` endpoint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum ApiSortBy {
+    #[default]
+    DateAdded,
+    Title,
+    Year,
+    Rating,
+    Peers,
+    Seeds,
+    DownloadCount,
+    LikeCount,
+}
+
+impl ApiSortBy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiSortBy::DateAdded => "date_added",
+            ApiSortBy::Title => "title",
+            ApiSortBy::Year => "year",
+            ApiSortBy::Rating => "rating",
+            ApiSortBy::Peers => "peers",
+            ApiSortBy::Seeds => "seeds",
+            ApiSortBy::DownloadCount => "download_count",
+            ApiSortBy::LikeCount => "like_count",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+/// Query parameters for `list_movies` requests. The default lists every
+/// movie newest first, which is what syncing relies on.
+#[derive(Debug, Clone, Default)]
+pub struct FetchParams {
+    pub sort_by: ApiSortBy,
+    pub order_by: SortOrder,
+    pub query_term: Option<String>,
+    pub genre: Option<String>,
+    /// Minimum IMDb rating, 0-9.
+    pub minimum_rating: Option<u8>,
+}
+
+impl FetchParams {
+    /// Whether pages come newest first, so a fetch can stop at the first
+    /// movie it already has.
+    pub fn is_newest_first(&self) -> bool {
+        self.sort_by == ApiSortBy::DateAdded && self.order_by == SortOrder::Desc
+    }
+}
+
+impl FetchParams {
+    /// Appends the parameters to `url`'s query string, skipping unset ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use url::Url;
+    /// use yts_movie_scraper::{ApiSortBy, FetchParams, SortOrder};
+    ///
+    /// let params = FetchParams { query_term: Some("the matrix".into()), ..Default::default() };
+    /// let mut url = Url::parse("https://yts.bz/api/v2/list_movies.json").unwrap();
+    /// params.append_to(&mut url);
+    ///
+    /// assert_eq!(url.query(), Some("sort_by=date_added&order_by=desc&query_term=the+matrix"));
+    ///
+    /// let params = FetchParams { sort_by: ApiSortBy::LikeCount, order_by: SortOrder::Asc, ..Default::default() };
+    /// let mut url = Url::parse("https://yts.bz/api/v2/list_movies.json").unwrap();
+    /// params.append_to(&mut url);
+    ///
+    /// assert_eq!(url.query(), Some("sort_by=like_count&order_by=asc"));
+    ///
+    /// let params = FetchParams { genre: Some("action".into()), minimum_rating: Some(7), ..Default::default() };
+    /// let mut url = Url::parse("https://yts.bz/api/v2/list_movies.json").unwrap();
+    /// params.append_to(&mut url);
+    ///
+    /// assert_eq!(url.query(), Some("sort_by=date_added&order_by=desc&genre=action&minimum_rating=7"));
+    /// ```
+    pub fn append_to(&self, url: &mut Url) {
+        let mut pairs = url.query_pairs_mut();
+        pairs.append_pair("sort_by", self.sort_by.as_str()).append_pair("order_by", self.order_by.as_str());
+        if let Some(query_term) = &self.query_term {
+            pairs.append_pair("query_term", query_term);
+        }
+        if let Some(genre) = &self.genre {
+            pairs.append_pair("genre", genre);
+        }
+        if let Some(minimum_rating) = self.minimum_rating {
+            pairs.append_pair("minimum_rating", &minimum_rating.to_string());
+        }
+    }
+}
+
+/// Version of the YTS API, which decides the default endpoint and the
+/// [`ApiClient`] fetching its pages. Supporting a new version means adding a
+/// variant and a client rather than changing the fetch code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ApiVersion {
+    #[default]
+    V2,
+    /// Not published yet; every request fails until YTS releases it.
+    V3,
+}
+
+impl ApiVersion {
+    /// The official `list_movies` endpoint of this version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use yts_movie_scraper::{ApiVersion, Config, ConfigOverrides, API_BASE};
+    ///
+    /// assert_eq!(ApiVersion::V2.default_url(), API_BASE);
+    ///
+    /// let overrides = ConfigOverrides { api_version: ApiVersion::V2, ..Default::default() };
+    /// let config = Config::default().resolve(overrides, Path::new("yts_movies.json")).unwrap();
+    /// assert_eq!(config.api_url.as_str(), "https://yts.bz/api/v2/list_movies.json");
+    /// ```
+    pub fn default_url(self) -> &'static str {
+        match self {
+            ApiVersion::V2 => API_BASE,
+            ApiVersion::V3 => API_BASE_V3,
+        }
+    }
+
+    /// Parses a `list_movies` response body of this version.
+    pub fn parse_response(self, body: &str) -> Result<ApiResponse> {
+        match self {
+            ApiVersion::V2 => Ok(serde_json::from_str(body)?),
+            ApiVersion::V3 => Err(v3_unsupported()),
+        }
+    }
+}
+
+fn v3_unsupported() -> YtsError {
+    YtsError::ApiError("YTS API v3 is not supported yet; use --api-version v2".to_string())
+}
+
+/// Fetches `list_movies` pages from one version of the YTS API with fixed
+/// [`FetchParams`]. [`api_client`] picks the client for [`YtsApi::version`].
+pub trait ApiClient: Send + Sync {
+    /// The request URL of `page`.
+    fn page_url(&self, page: u32) -> Url;
+
+    fn fetch_page(&self, page: u32) -> BoxFuture<'_, Result<ApiResponse>>;
+}
+
+/// The v2 API, the one YTS serves today.
+pub struct V2Client<'a> {
+    pub api: &'a YtsApi,
+    pub params: &'a FetchParams,
+}
+
+impl ApiClient for V2Client<'_> {
+    fn page_url(&self, page: u32) -> Url {
+        let mut url = self.api.base_url.clone();
+        url.query_pairs_mut()
+            .append_pair("limit", &self.api.page_size.to_string())
+            .append_pair("page", &page.to_string());
+        self.params.append_to(&mut url);
+        url
+    }
+
+    fn fetch_page(&self, page: u32) -> BoxFuture<'_, Result<ApiResponse>> {
+        Box::pin(fetch_v2_page(self.api, self.page_url(page), page))
+    }
+}
+
+/// A placeholder for v3 until YTS publishes its schema. Requests fail without
+/// reaching the network.
+pub struct V3Client<'a> {
+    pub api: &'a YtsApi,
+    pub params: &'a FetchParams,
+}
+
+impl ApiClient for V3Client<'_> {
+    fn page_url(&self, page: u32) -> Url {
+        let mut url = self.api.base_url.clone();
+        url.query_pairs_mut()
+            .append_pair("page", &page.to_string())
+            .append_pair("page_size", &self.api.page_size.to_string());
+        self.params.append_to(&mut url);
+        url
+    }
+
+    fn fetch_page(&self, _page: u32) -> BoxFuture<'_, Result<ApiResponse>> {
+        Box::pin(async { Err(v3_unsupported()) })
+    }
+}
+
+/// The client for `api.version`.
+pub fn api_client<'a>(api: &'a YtsApi, params: &'a FetchParams) -> Box<dyn ApiClient + 'a> {
+    match api.version {
+        ApiVersion::V2 => Box::new(V2Client { api, params }),
+        ApiVersion::V3 => Box::new(V3Client { api, params }),
+    }
+}
+
+/// Connection settings shared by every request to the YTS API.
+pub struct YtsApi {
+    pub client: reqwest::Client,
+    pub base_url: Url,
+    /// Schema of the responses from `base_url`.
+    pub version: ApiVersion,
+    pub max_retries: u8,
+    /// Movies requested per page, at most [`MAX_PAGE_SIZE`].
+    pub page_size: u32,
+    /// Sent as the [`API_KEY_HEADER`] header, for mirrors that require a key.
+    pub api_key: Option<String>,
+    /// Makes [`fetch_page`] send conditional requests and reuse cached
+    /// bodies on 304 Not Modified.
+    pub page_cache: Option<Arc<Mutex<PageCache>>>,
+}
+
+/// A page response with the validators the server sent for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPage {
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+impl CachedPage {
+    /// Returns `None` if the response has neither an `ETag` nor a
+    /// `Last-Modified` header, as there would be no way to revalidate it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reqwest::header::{HeaderMap, HeaderValue, ETAG};
+    /// use yts_movie_scraper::CachedPage;
+    ///
+    /// assert!(CachedPage::from_headers(&HeaderMap::new(), "{}".into()).is_none());
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+    /// let page = CachedPage::from_headers(&headers, "{}".into()).unwrap();
+    /// assert_eq!(page.etag.as_deref(), Some("\"abc\""));
+    /// assert_eq!(page.last_modified, None);
+    /// ```
+    pub fn from_headers(headers: &reqwest::header::HeaderMap, body: String) -> Option<Self> {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(String::from);
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+
+        (etag.is_some() || last_modified.is_some()).then_some(CachedPage { etag, last_modified, body })
+    }
+}
+
+/// Cached page responses by request URL, saved in [`PAGE_CACHE_FILE`].
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{CachedPage, PageCache};
+///
+/// let mut cache = PageCache::default();
+/// let url = "https://yts.bz/api/v2/list_movies.json?page=2";
+/// cache.insert(url, CachedPage { etag: Some("\"v1\"".into()), last_modified: None, body: "{}".into() });
+/// assert!(cache.is_changed());
+///
+/// let restored: PageCache = serde_json::from_str(&serde_json::to_string(&cache).unwrap()).unwrap();
+/// assert_eq!(restored.get(url).unwrap().etag.as_deref(), Some("\"v1\""));
+/// assert!(restored.get("https://yts.bz/api/v2/list_movies.json?page=3").is_none());
+/// assert!(!restored.is_changed());
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PageCache {
+    pages: HashMap<String, CachedPage>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl PageCache {
+    /// Reads the cache, starting empty if it is missing or cannot be parsed.
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return PageCache::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("ignoring unreadable page cache {}: {}", path.display(), e);
+            PageCache::default()
+        })
+    }
+
+    /// Writes the cache if anything was inserted since it was loaded.
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        if self.changed {
+            let mut writer = BufWriter::new(fs::File::create(path)?);
+            serde_json::to_writer(&mut writer, self)?;
+            writer.flush()?;
+            self.changed = false;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, url: &str) -> Option<&CachedPage> {
+        self.pages.get(url)
+    }
+
+    pub fn insert(&mut self, url: &str, page: CachedPage) {
+        self.pages.insert(url.to_string(), page);
+        self.changed = true;
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiMovie {
+    pub id: u32,
+    pub title: String,
+    pub year: u32,
+    pub imdb_code: String,
+    #[serde(default)]
+    pub rating: f32,
+    #[serde(default)]
+    pub mpa_rating: String,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub large_cover_image: String,
+    #[serde(default)]
+    pub medium_cover_image: String,
+    #[serde(default)]
+    pub small_cover_image: String,
+    #[serde(default)]
+    pub date_uploaded_unix: i64,
+    pub torrents: Vec<ApiTorrent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiTorrent {
+    pub quality: String,
+    #[serde(rename = "type")]
+    pub torrent_type: String,
+    pub hash: String,
+    /// Missing from some mirrors, which only send `size`.
+    #[serde(default)]
+    pub size_bytes: u64,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub seeds: u32,
+    #[serde(default)]
+    pub peers: u32,
+    /// Human-readable size, e.g. "1.84 GB".
+    #[serde(default)]
+    pub size: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiData {
+    pub movie_count: u32,
+    pub movies: Option<Vec<ApiMovie>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiResponse {
+    pub data: ApiData,
+}
+
+/// Limits for each HTTP request made by a [`build_client`] client.
+#[derive(Clone, Copy, Debug)]
+pub struct HttpTimeouts {
+    /// From sending the request until the whole body has been read.
+    pub total: Duration,
+    /// For establishing the connection.
+    pub connect: Duration,
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        HttpTimeouts {
+            total: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            connect: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// URL schemes [`build_client`] accepts for a proxy.
+pub const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Builds the HTTP client used for all API requests. Without an explicit
+/// proxy the system proxy settings apply unless `no_proxy` is set. Requests
+/// exceeding `timeouts` fail with [`YtsError::Timeout`], and a proxy whose
+/// scheme is not one of [`PROXY_SCHEMES`] is a [`YtsError::ConfigError`].
+///
+/// Responses may be gzip or brotli compressed; the client advertises both in
+/// `Accept-Encoding` and decompresses transparently.
+pub fn build_client(proxy: Option<&Url>, no_proxy: bool, timeouts: HttpTimeouts) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(concat!("yts-movie-scraper/", env!("CARGO_PKG_VERSION")))
+        .timeout(timeouts.total)
+        .connect_timeout(timeouts.connect)
+        .gzip(true)
+        .brotli(true);
+
+    if let Some(proxy) = proxy {
+        if !PROXY_SCHEMES.contains(&proxy.scheme()) {
+            return Err(YtsError::ConfigError(format!(
+                "unsupported proxy scheme '{}', expected http, https, socks5 or socks5h",
+                proxy.scheme()
+            )));
+        }
+        builder = builder.proxy(reqwest::Proxy::all(proxy.as_str())?);
+    } else if no_proxy {
+        builder = builder.no_proxy();
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Header carrying [`YtsApi::api_key`]. Sent as a header rather than a query
+/// parameter so the key never shows up in logged URLs.
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Fetches a page with the [`ApiClient`] of `api.version`.
+pub async fn fetch_page(api: &YtsApi, params: &FetchParams, page: u32) -> Result<ApiResponse> {
+    api_client(api, params).fetch_page(page).await
+}
+
+/// Fetches page `page` from `url`, retrying with exponential back-off
+/// (`2^attempt * 500ms`) when YTS answers with 429 or a 5xx status. With a
+/// [`PageCache`], pages seen before are requested conditionally and a 304
+/// reuses the cached body.
+async fn fetch_v2_page(api: &YtsApi, url: Url, page: u32) -> Result<ApiResponse> {
+    let cache_lock = || api.page_cache.as_ref().map(|c| c.lock().expect("page cache lock poisoned"));
+    let cached = cache_lock().and_then(|cache| cache.get(url.as_str()).cloned());
+
+    let mut attempt: u32 = 0;
+    loop {
+        tracing::debug!("GET {}", url);
+        let started = Instant::now();
+        let mut request = api.client.get(url.clone());
+        if let Some(key) = &api.api_key {
+            request = request.header(API_KEY_HEADER, key);
+        }
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        tracing::debug!("{} from page {} in {}ms", status, page, started.elapsed().as_millis());
+
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (status, &cached) {
+            tracing::debug!("page {} not modified, using the cached response", page);
+            return api.version.parse_response(&cached.body);
+        }
+
+        if status.is_success() {
+            let headers = response.headers().clone();
+            let body = response.text().await?;
+            if tracing::enabled!(tracing::Level::TRACE) {
+                tracing::trace!("response body: {}", truncate_chars(&body, 500));
+            }
+            let parsed = api.version.parse_response(&body)?;
+            if let (Some(mut cache), Some(fresh)) = (cache_lock(), CachedPage::from_headers(&headers, body)) {
+                cache.insert(url.as_str(), fresh);
+            }
+            return Ok(parsed);
+        }
+
+        let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+        if status == StatusCode::TOO_MANY_REQUESTS && attempt >= api.max_retries as u32 {
+            return Err(YtsError::RateLimit);
+        }
+        if !retryable || attempt >= api.max_retries as u32 {
+            return Err(YtsError::ApiError(format!(
+                "request for page {} failed with HTTP {} after {} attempts",
+                page,
+                status,
+                attempt + 1
+            )));
+        }
+
+        let delay = Duration::from_millis(500 * 2u64.pow(attempt));
+        tracing::info!(page, %status, attempt = attempt + 1, "retrying in {}ms", delay.as_millis());
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Fetches `pages` with up to `concurrency` requests in flight, calling `on_page`
+/// as each page arrives. Results are returned sorted by page number.
+pub async fn fetch_page_batch<F>(
+    api: &YtsApi,
+    params: &FetchParams,
+    pages: Vec<u32>,
+    concurrency: u32,
+    mut on_page: F,
+) -> Result<Vec<(u32, Vec<ApiMovie>)>>
+where
+    F: FnMut(u32, &[ApiMovie]) -> Result<()>,
+{
+    let mut results = Vec::new();
+    fetch_pages_tolerant(api, params, pages, concurrency, |page, result| {
+        let movies = result?;
+        on_page(page, &movies)?;
+        results.push((page, movies));
+        Ok(())
+    })
+    .await?;
+
+    results.sort_by_key(|(page, _)| *page);
+    Ok(results)
+}
+
+/// Like [`fetch_page_batch`], but a page that still fails after all retries
+/// is handed to `on_page` as an error instead of aborting the batch. Only an
+/// error returned by `on_page` stops it.
+pub async fn fetch_pages_tolerant<F>(
+    api: &YtsApi,
+    params: &FetchParams,
+    pages: Vec<u32>,
+    concurrency: u32,
+    mut on_page: F,
+) -> Result<()>
+where
+    F: FnMut(u32, Result<Vec<ApiMovie>>) -> Result<()>,
+{
+    let mut requests = stream::iter(pages)
+        .map(|page| async move { (page, fetch_page(api, params, page).await.map(|r| r.data.movies.unwrap_or_default())) })
+        .buffer_unordered(concurrency as usize);
+
+    while let Some((page, result)) = requests.next().await {
+        on_page(page, result)?;
+    }
+    Ok(())
+}
+
+/// Like [`fetch_pages_tolerant`], but the pages are requested by a rayon pool
+/// of `workers` threads, each waiting on one page at a time. Every `workers`
+/// pages are collected first and then handed to `on_page` in page order.
+///
+/// Blocks the current runtime thread while a batch is fetched, so it must run
+/// on the multi-threaded tokio runtime.
+pub async fn fetch_pages_with_workers<F>(
+    api: &YtsApi,
+    params: &FetchParams,
+    pages: Vec<u32>,
+    workers: u32,
+    mut on_page: F,
+) -> Result<()>
+where
+    F: FnMut(u32, Result<Vec<ApiMovie>>) -> Result<()>,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(workers as usize)
+        .build()
+        .map_err(|e| YtsError::ConfigError(format!("cannot start {} workers: {}", workers, e)))?;
+    let runtime = tokio::runtime::Handle::current();
+
+    for batch in pages.chunks(workers as usize) {
+        let results: Vec<(u32, Result<Vec<ApiMovie>>)> = tokio::task::block_in_place(|| {
+            pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|&page| {
+                        let movies = runtime.block_on(fetch_page(api, params, page));
+                        (page, movies.map(|r| r.data.movies.unwrap_or_default()))
+                    })
+                    .collect()
+            })
+        });
+        for (page, result) in results {
+            on_page(page, result)?;
+        }
+    }
+    Ok(())
+}
+
+/// Number of API pages needed to list `movie_count` movies at `limit` per
+/// page. `limit` must be non-zero.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::compute_total_pages;
+///
+/// assert_eq!(compute_total_pages(0, 50), 0);
+/// assert_eq!(compute_total_pages(100, 50), 2);
+/// assert_eq!(compute_total_pages(101, 50), 3);
+/// ```
+pub fn compute_total_pages(movie_count: u32, limit: u32) -> u32 {
+    movie_count.div_ceil(limit)
+}
+
+pub fn page_cache_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(PAGE_CACHE_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{list_response, yts_api, Catalogue, LIST_MOVIES_PATH};
+
+    /// A server answering `failures` times with `status`, then with one movie.
+    async fn failing_server(status: u16, failures: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(LIST_MOVIES_PATH))
+            .respond_with(ResponseTemplate::new(status))
+            .up_to_n_times(failures)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(LIST_MOVIES_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(list_response(&[7], 1)))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_are_retried_with_back_off() {
+        let server = failing_server(429, 2).await;
+        let api = YtsApi { max_retries: 5, ..yts_api(&server, 50) };
+
+        let started = Instant::now();
+        let response = fetch_page(&api, &FetchParams::default(), 1).await.unwrap();
+
+        assert_eq!(response.data.movies.unwrap()[0].id, 7);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+        // 500ms after the first 429, 1000ms after the second.
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_error_after_exhausting_retries() {
+        let server = failing_server(429, 2).await;
+        let api = YtsApi { max_retries: 1, ..yts_api(&server, 50) };
+
+        assert!(matches!(fetch_page(&api, &FetchParams::default(), 1).await, Err(YtsError::RateLimit)));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_but_client_errors_are_not() {
+        let server = failing_server(503, 1).await;
+        let api = YtsApi { max_retries: 1, ..yts_api(&server, 50) };
+        assert!(fetch_page(&api, &FetchParams::default(), 1).await.is_ok());
+
+        let server = failing_server(404, 1).await;
+        let api = YtsApi { max_retries: 5, ..yts_api(&server, 50) };
+        let err = fetch_page(&api, &FetchParams::default(), 1).await.unwrap_err();
+        assert_eq!(err.to_string(), "API error: request for page 1 failed with HTTP 404 Not Found after 1 attempts");
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn page_batch_keeps_concurrency_requests_in_flight() {
+        let catalogue = Catalogue::new(600).delayed(|_| Duration::from_millis(200));
+        let server = catalogue.serve().await;
+        let api = yts_api(&server, 50);
+
+        let mut seen = Vec::new();
+        let pages = fetch_page_batch(&api, &FetchParams::default(), (1..=12).collect(), 4, |page, movies| {
+            seen.push((page, movies.len()));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(catalogue.max_in_flight(), 4);
+        assert_eq!(pages.iter().map(|(page, _)| *page).collect::<Vec<_>>(), (1..=12).collect::<Vec<_>>());
+        assert_eq!(pages[0].1[0].id, 600);
+        assert_eq!(pages[11].1[49].id, 1);
+        assert_eq!(seen.len(), 12);
+    }
+
+    #[tokio::test]
+    async fn page_batch_with_concurrency_one_is_sequential() {
+        let catalogue = Catalogue::new(200).delayed(|_| Duration::from_millis(50));
+        let server = catalogue.serve().await;
+
+        fetch_page_batch(&yts_api(&server, 50), &FetchParams::default(), (1..=4).collect(), 1, |_, _| Ok(()))
+            .await
+            .unwrap();
+
+        assert_eq!(catalogue.max_in_flight(), 1);
+        assert_eq!(catalogue.pages(), [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn requests_are_logged_at_debug_level() {
+        let server = Catalogue::new(100).serve().await;
+        let api = yts_api(&server, 20);
+
+        fetch_page(&api, &FetchParams::default(), 3).await.unwrap();
+
+        assert!(logs_contain(&format!("GET {}{}?limit=20&page=3", server.uri(), LIST_MOVIES_PATH)));
+        assert!(logs_contain("200 OK from page 3 in"));
+    }
+
+    #[tokio::test]
+    async fn not_modified_pages_are_served_from_the_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("ETag", "\"v1\"").set_body_json(list_response(&[7], 1)),
+            )
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = page_cache_path(&dir.path().join("yts_movies.json"));
+        let cache = Arc::new(Mutex::new(PageCache::load(&cache_path)));
+        let api = YtsApi { page_cache: Some(cache.clone()), ..yts_api(&server, 50) };
+
+        let first = fetch_page(&api, &FetchParams::default(), 1).await.unwrap();
+        assert_eq!(first.data.movies.unwrap()[0].id, 7);
+        cache.lock().unwrap().save(&cache_path).unwrap();
+
+        // A later run: the saved validator is sent and the 304 reuses the cached body.
+        let api = YtsApi { page_cache: Some(Arc::new(Mutex::new(PageCache::load(&cache_path)))), ..api };
+        let second = fetch_page(&api, &FetchParams::default(), 1).await.unwrap();
+        assert_eq!(second.data.movie_count, 1);
+        assert_eq!(second.data.movies.unwrap()[0].title, "Movie 7");
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].headers.get("If-None-Match").is_none());
+        assert_eq!(requests[1].headers.get("If-None-Match").unwrap(), "\"v1\"");
+    }
+
+    #[tokio::test]
+    async fn slow_responses_time_out() {
+        let server = Catalogue::new(1).delayed(|_| Duration::from_secs(5)).serve().await;
+        let timeouts = HttpTimeouts { total: Duration::from_millis(200), ..HttpTimeouts::default() };
+        let api = YtsApi { client: build_client(None, true, timeouts).unwrap(), ..yts_api(&server, 50) };
+
+        let started = Instant::now();
+        let err = fetch_page(&api, &FetchParams::default(), 1).await.unwrap_err();
+
+        assert!(matches!(err, YtsError::Timeout(_)), "{:?}", err);
+        assert!(err.to_string().ends_with("timed out; try a larger --timeout"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn unusable_proxies_are_an_error() {
+        let proxy: Url = "ftp://proxy.test:21".parse().unwrap();
+        let err = build_client(Some(&proxy), false, HttpTimeouts::default()).unwrap_err();
+        assert!(matches!(err, YtsError::ConfigError(_)), "{:?}", err);
+        assert!(err.to_string().contains("unsupported proxy scheme 'ftp'"), "{}", err);
+    }
+
+    #[test]
+    fn clients_build_with_and_without_a_proxy() {
+        for proxy in ["http://proxy.test:8080", "socks5://proxy.test:1080"] {
+            build_client(Some(&proxy.parse().unwrap()), false, HttpTimeouts::default()).unwrap();
+        }
+        build_client(None, true, HttpTimeouts::default()).unwrap();
+        build_client(None, false, HttpTimeouts::default()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn no_proxy_ignores_the_proxy_environment() {
+        // The proxy variables of the test run cannot be changed safely, so
+        // only check that a --no-proxy client reaches a local server directly.
+        let server = Catalogue::new(1).serve().await;
+        let api = YtsApi { client: build_client(None, true, HttpTimeouts::default()).unwrap(), ..yts_api(&server, 50) };
+        assert_eq!(fetch_page(&api, &FetchParams::default(), 1).await.unwrap().data.movie_count, 1);
+    }
+
+    fn accepted_encodings(request: &wiremock::Request) -> Vec<String> {
+        let accepted = request.headers.get("accept-encoding").and_then(|v| v.to_str().ok()).unwrap_or_default();
+        accepted.split(',').map(|encoding| encoding.trim().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn clients_accept_gzip_and_brotli() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(LIST_MOVIES_PATH))
+            .and(|request: &wiremock::Request| {
+                let accepted = accepted_encodings(request);
+                accepted.iter().any(|e| e == "gzip") && accepted.iter().any(|e| e == "br")
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(list_response(&[7], 1)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let api = YtsApi { client: build_client(None, true, HttpTimeouts::default()).unwrap(), ..yts_api(&server, 50) };
+
+        fetch_page(&api, &FetchParams::default(), 1).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn gzip_responses_are_decompressed() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        serde_json::to_writer(&mut encoder, &list_response(&[9, 8], 2)).unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(LIST_MOVIES_PATH))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .insert_header("content-type", "application/json")
+                    .set_body_bytes(encoder.finish().unwrap()),
+            )
+            .mount(&server)
+            .await;
+        let api = YtsApi { client: build_client(None, true, HttpTimeouts::default()).unwrap(), ..yts_api(&server, 50) };
+
+        let response = fetch_page(&api, &FetchParams::default(), 1).await.unwrap();
+        let ids: Vec<u32> = response.data.movies.unwrap().iter().map(|m| m.id).collect();
+        assert_eq!((response.data.movie_count, ids), (2, vec![9, 8]));
+    }
+
+    fn client_for(version: ApiVersion) -> YtsApi {
+        YtsApi {
+            client: reqwest::Client::new(),
+            base_url: version.default_url().parse().unwrap(),
+            version,
+            max_retries: 0,
+            page_size: 20,
+            api_key: None,
+            page_cache: None,
+        }
+    }
+
+    #[test]
+    fn v2_urls_carry_the_page_size_page_and_filters() {
+        let api = client_for(ApiVersion::V2);
+        let params = FetchParams { genre: Some("comedy".to_string()), minimum_rating: Some(7), ..Default::default() };
+
+        let url = api_client(&api, &params).page_url(3);
+
+        assert_eq!(url.as_str().split('?').next().unwrap(), "https://yts.bz/api/v2/list_movies.json");
+        let query: Vec<(String, String)> = url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect();
+        for pair in [("limit", "20"), ("page", "3"), ("genre", "comedy"), ("minimum_rating", "7")] {
+            assert!(query.contains(&(pair.0.to_string(), pair.1.to_string())), "{} missing from {}", pair.0, url);
+        }
+    }
+
+    #[test]
+    fn v3_urls_use_the_v3_endpoint() {
+        let api = client_for(ApiVersion::V3);
+
+        let url = api_client(&api, &FetchParams::default()).page_url(2);
+
+        assert!(url.as_str().starts_with("https://yts.bz/api/v3/list_movies.json?page=2&page_size=20"), "{}", url);
+    }
+
+    #[tokio::test]
+    async fn v3_requests_fail_without_reaching_the_server() {
+        let server = Catalogue::new(3).serve().await;
+        let api = YtsApi { version: ApiVersion::V3, ..yts_api(&server, 50) };
+
+        let err = fetch_page(&api, &FetchParams::default(), 1).await.unwrap_err();
+
+        assert!(err.to_string().contains("v3 is not supported yet"), "{}", err);
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+//! Commands that move the database between files: `export`, `diff`, `merge`, `sync` and `schema`.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{Movie, Result, Storage, Torrent};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    /// One JSON movie object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Header row of CSV exports, matching the fields of [`ExportRow`].
+pub(crate) const EXPORT_COLUMNS: [&str; 9] =
+    ["id", "title", "year", "imdb_code", "quality", "torrent_type", "size_bytes", "hash", "magnet_url"];
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ExportRow<'a> {
+    id: u32,
+    title: &'a str,
+    year: u32,
+    imdb_code: &'a str,
+    quality: Option<&'a str>,
+    torrent_type: Option<&'a str>,
+    size_bytes: Option<u64>,
+    hash: Option<&'a str>,
+    magnet_url: Option<&'a str>,
+}
+
+impl<'a> ExportRow<'a> {
+    pub(crate) fn new(movie: &'a Movie, torrent: Option<&'a Torrent>) -> Self {
+        ExportRow {
+            id: movie.id,
+            title: &movie.title,
+            year: movie.year,
+            imdb_code: &movie.imdb_code,
+            quality: torrent.map(|t| t.quality.as_str()),
+            torrent_type: torrent.map(|t| t.torrent_type.as_str()),
+            size_bytes: torrent.map(|t| t.size_bytes),
+            hash: torrent.map(|t| t.hash.as_str()),
+            magnet_url: torrent.map(|t| t.magnet_url.as_str()),
+        }
+    }
+}
+
+pub fn write_export<W: Write>(writer: W, movies: &[Movie], delimiter: u8) -> Result<usize> {
+    let mut csv_writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(writer);
+    let mut rows = 0;
+
+    for movie in movies {
+        if movie.torrents.is_empty() {
+            csv_writer.serialize(ExportRow::new(movie, None))?;
+            rows += 1;
+        }
+
+        for torrent in &movie.torrents {
+            csv_writer.serialize(ExportRow::new(movie, Some(torrent)))?;
+            rows += 1;
+        }
+    }
+
+    csv_writer.flush()?;
+    Ok(rows)
+}
+
+/// Movies added and removed between two database snapshots.
+pub struct DbDiff<'a> {
+    pub added: Vec<&'a Movie>,
+    pub removed: Vec<&'a Movie>,
+}
+
+pub fn diff_databases<'a>(before: &'a [Movie], after: &'a [Movie]) -> DbDiff<'a> {
+    let before_ids: HashSet<u32> = before.iter().map(|m| m.id).collect();
+    let after_ids: HashSet<u32> = after.iter().map(|m| m.id).collect();
+
+    DbDiff {
+        added: after.iter().filter(|m| !before_ids.contains(&m.id)).collect(),
+        removed: before.iter().filter(|m| !after_ids.contains(&m.id)).collect(),
+    }
+}
+
+pub fn diff_command(before: &dyn Storage, after: &dyn Storage) -> Result<()> {
+    let before_movies = before.load()?;
+    let after_movies = after.load()?;
+    let diff = diff_databases(&before_movies, &after_movies);
+
+    say!("🔀 Comparing {} → {}\n", before.path().display(), after.path().display());
+
+    say!("🆕 Added ({}):", diff.added.len());
+    for movie in &diff.added {
+        say!("  + [ID: {}] {} ({})", movie.id, movie.title, movie.year);
+    }
+
+    say!("\n🗑️  Removed ({}):", diff.removed.len());
+    for movie in &diff.removed {
+        say!("  - [ID: {}] {} ({})", movie.id, movie.title, movie.year);
+    }
+
+    say!("\nBefore: {} movies", before_movies.len());
+    say!("After:  {} movies", after_movies.len());
+    say!("Total:  +{} / -{}", diff.added.len(), diff.removed.len());
+
+    Ok(())
+}
+
+/// Unions several databases by id. When the same id appears more than once,
+/// the entry with the most torrents wins (the earliest input on a tie).
+pub fn merge_databases(inputs: Vec<Vec<Movie>>) -> Vec<Movie> {
+    let mut by_id: HashMap<u32, Movie> = HashMap::new();
+
+    for movie in inputs.into_iter().flatten() {
+        match by_id.get(&movie.id) {
+            Some(existing) if existing.torrents.len() >= movie.torrents.len() => {}
+            _ => {
+                by_id.insert(movie.id, movie);
+            }
+        }
+    }
+
+    let mut merged: Vec<Movie> = by_id.into_values().collect();
+    merged.sort_by_key(|m| Reverse(m.id));
+    merged
+}
+
+pub fn merge_command(inputs: &[Box<dyn Storage>], dest: &dyn Storage) -> Result<()> {
+    let mut databases = Vec::new();
+    let mut input_total = 0;
+
+    say!("🔗 Merging {} databases\n", inputs.len());
+    for input in inputs {
+        let movies = input.load()?;
+        say!("  {} movies from {}", movies.len(), input.path().display());
+        input_total += movies.len();
+        databases.push(movies);
+    }
+
+    let merged = merge_databases(databases);
+    dest.save(&merged)?;
+
+    say!("\n🧹 Dropped {} duplicate movies", input_total - merged.len());
+    say!("✅ Wrote {} movies to {}", merged.len(), dest.path().display());
+
+    Ok(())
+}
+
+/// Movies each side gained in a [`sync_databases`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    pub added_to_a: usize,
+    pub added_to_b: usize,
+}
+
+/// Gives both databases the union of their movies by id. A movie present on
+/// both sides is left as each side has it; only missing ids are copied over.
+/// Both results are sorted newest id first.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{sync_databases, Movie, SyncStats};
+///
+/// let movies = |ids: &[u32]| -> Vec<Movie> { ids.iter().map(|&id| Movie { id, ..Default::default() }).collect() };
+/// let ids = |movies: &[Movie]| -> Vec<u32> { movies.iter().map(|m| m.id).collect() };
+///
+/// // Each side has movies the other lacks.
+/// let (a, b, stats) = sync_databases(movies(&[1, 2, 3]), movies(&[3, 4]));
+/// assert_eq!(ids(&a), [4, 3, 2, 1]);
+/// assert_eq!(ids(&b), [4, 3, 2, 1]);
+/// assert_eq!(stats, SyncStats { added_to_a: 1, added_to_b: 2 });
+///
+/// // Only one side is behind.
+/// let (a, b, stats) = sync_databases(movies(&[5]), movies(&[5, 6, 7]));
+/// assert_eq!(ids(&a), [7, 6, 5]);
+/// assert_eq!(ids(&b), [7, 6, 5]);
+/// assert_eq!(stats, SyncStats { added_to_a: 2, added_to_b: 0 });
+///
+/// // Already in sync.
+/// let (_, _, stats) = sync_databases(movies(&[1, 2]), movies(&[2, 1]));
+/// assert_eq!(stats, SyncStats::default());
+/// ```
+pub fn sync_databases(mut a: Vec<Movie>, mut b: Vec<Movie>) -> (Vec<Movie>, Vec<Movie>, SyncStats) {
+    let a_ids: HashSet<u32> = a.iter().map(|m| m.id).collect();
+    let b_ids: HashSet<u32> = b.iter().map(|m| m.id).collect();
+
+    let for_a: Vec<Movie> = b.iter().filter(|m| !a_ids.contains(&m.id)).cloned().collect();
+    let for_b: Vec<Movie> = a.iter().filter(|m| !b_ids.contains(&m.id)).cloned().collect();
+    let stats = SyncStats {
+        added_to_a: for_a.len(),
+        added_to_b: for_b.len(),
+    };
+
+    a.extend(for_a);
+    b.extend(for_b);
+    a.sort_by_key(|m| Reverse(m.id));
+    b.sort_by_key(|m| Reverse(m.id));
+
+    (a, b, stats)
+}
+
+pub fn sync_command(a: &dyn Storage, b: &dyn Storage, dry_run: bool) -> Result<()> {
+    let a_movies = a.load()?;
+    let b_movies = b.load()?;
+
+    say!(
+        "🔄 Syncing {} ({} movies) and {} ({} movies)\n",
+        a.path().display(),
+        a_movies.len(),
+        b.path().display(),
+        b_movies.len()
+    );
+
+    let (a_movies, b_movies, stats) = sync_databases(a_movies, b_movies);
+
+    if stats == SyncStats::default() {
+        say!("✅ Already in sync: both have {} movies", a_movies.len());
+        return Ok(());
+    }
+
+    say!("  +{} movies for {}", stats.added_to_a, a.path().display());
+    say!("  +{} movies for {}", stats.added_to_b, b.path().display());
+
+    if dry_run {
+        say!("\n🔎 Dry run: databases left unchanged.");
+        return Ok(());
+    }
+
+    if stats.added_to_a > 0 {
+        a.save(&a_movies)?;
+    }
+    if stats.added_to_b > 0 {
+        b.save(&b_movies)?;
+    }
+    say!("\n✅ Both databases now have {} movies", a_movies.len());
+
+    Ok(())
+}
+
+/// JSON Schema of a [`Movie`] (one element of the JSON database), with
+/// [`Torrent`] under `definitions`.
+///
+/// # Examples
+///
+/// ```
+/// let schema: serde_json::Value = serde_json::from_str(&yts_movie_scraper::movie_schema()).unwrap();
+/// let required = schema["required"].as_array().unwrap();
+///
+/// for field in ["id", "title", "year"] {
+///     assert!(required.iter().any(|r| r == field));
+/// }
+/// ```
+pub fn movie_schema() -> String {
+    serde_json::to_string_pretty(&schemars::schema_for!(Movie)).expect("schema serializes to JSON")
+}
+
+/// Prints the movie JSON Schema, or writes it to `dest`.
+pub fn schema_command(dest: Option<&Path>) -> Result<()> {
+    let schema = movie_schema();
+
+    match dest {
+        Some(dest) => {
+            fs::write(dest, schema + "\n")?;
+            say!("✅ Wrote JSON Schema to {}", dest.display());
+        }
+        None => say!("{}", schema),
+    }
+
+    Ok(())
+}
+
+/// Writes one JSON object per movie per line. Returns the number of lines.
+pub fn write_ndjson<'a, W, I>(mut writer: W, movies: I) -> Result<usize>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a Movie>,
+{
+    let mut lines = 0;
+
+    for movie in movies {
+        serde_json::to_writer(&mut writer, movie)?;
+        writer.write_all(b"\n")?;
+        lines += 1;
+    }
+
+    writer.flush()?;
+    Ok(lines)
+}
+
+pub fn export_movies(storage: &dyn Storage, format: ExportFormat, dest: Option<PathBuf>) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let dest = dest.unwrap_or_else(|| storage.path().with_extension(format.extension()));
+    let file = fs::File::create(&dest)?;
+    let rows = match format {
+        ExportFormat::Csv => write_export(file, &movies, b',')?,
+        ExportFormat::Tsv => write_export(file, &movies, b'\t')?,
+        ExportFormat::Ndjson => write_ndjson(io::BufWriter::new(file), &movies)?,
+    };
+
+    say!("✅ Exported {} rows ({} movies)", rows, movies.len());
+    say!("📝 File: {}", dest.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_support::{json_storage, movie, torrent};
+
+    #[test]
+    fn ndjson_has_one_parsable_line_per_movie() {
+        let mut movies: Vec<Movie> = (1..=25).map(|id| movie(id, &format!("Movie {}", id), 2000)).collect();
+        movies[3].title = "Two\nLines \"quoted\"".to_string();
+
+        let mut out = Vec::new();
+        assert_eq!(write_ndjson(&mut out, &movies).unwrap(), 25);
+
+        let text = String::from_utf8(out).unwrap();
+        let ids: Vec<u32> = text.lines().map(|line| serde_json::from_str::<Movie>(line).unwrap().id).collect();
+        let unique: std::collections::HashSet<u32> = ids.iter().copied().collect();
+        assert_eq!(ids.len(), 25);
+        assert_eq!(unique, (1..=25).collect());
+    }
+
+    #[test]
+    fn export_writes_a_header_and_one_row_per_torrent() {
+        let mut heat = movie(2, "Heat", 1995);
+        heat.torrents.push(torrent("2160p", 4_000_000_000));
+        let movies = [heat, Movie { torrents: Vec::new(), ..movie(1, "Ronin, Director's Cut", 1998) }];
+
+        let mut out = Cursor::new(Vec::new());
+        assert_eq!(write_export(&mut out, &movies, b',').unwrap(), 3);
+
+        let text = String::from_utf8(out.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], EXPORT_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            format!("2,Heat,1995,tt0000002,1080p,,1000000002,{0},magnet:?xt=urn:btih:{0}", "0".repeat(32) + "3B9ACA02")
+        );
+        assert!(lines[2].starts_with("2,Heat,1995,tt0000002,2160p,,4000000000,"));
+        assert_eq!(lines[3], "1,\"Ronin, Director's Cut\",1998,tt0000001,,,,,");
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn tsv_export_uses_tabs() {
+        let mut out = Cursor::new(Vec::new());
+        write_export(&mut out, &[movie(1, "Ronin, Director's Cut", 1998)], b'\t').unwrap();
+
+        let text = String::from_utf8(out.into_inner()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next().unwrap(), EXPORT_COLUMNS.join("\t"));
+        assert!(lines.next().unwrap().starts_with("1\tRonin, Director's Cut\t1998\t"));
+    }
+
+    fn ids(movies: &[&Movie]) -> Vec<u32> {
+        movies.iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn diff_lists_added_and_removed_movies() {
+        let before = [movie(3, "Heat", 1995), movie(2, "Ronin", 1998), movie(1, "Alien", 1979)];
+        let after = [movie(5, "Drive", 2011), movie(4, "Collateral", 2004), movie(2, "Ronin", 1998)];
+
+        let diff = diff_databases(&before, &after);
+        assert_eq!(ids(&diff.added), [5, 4]);
+        assert_eq!(ids(&diff.removed), [3, 1]);
+    }
+
+    #[test]
+    fn diff_ignores_changes_to_movies_on_both_sides() {
+        let before = [movie(1, "Alien", 1979)];
+        let after = [Movie { title: "Alien: Director's Cut".to_string(), ..movie(1, "Alien", 1979) }];
+
+        let diff = diff_databases(&before, &after);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn diff_against_an_empty_database() {
+        let movies = [movie(2, "Ronin", 1998), movie(1, "Alien", 1979)];
+
+        assert_eq!(ids(&diff_databases(&[], &movies).added), [2, 1]);
+        assert_eq!(ids(&diff_databases(&movies, &[]).removed), [2, 1]);
+    }
+
+    fn merged_ids(movies: &[Movie]) -> Vec<u32> {
+        movies.iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn merge_unions_disjoint_databases_newest_first() {
+        let merged = merge_databases(vec![
+            vec![movie(5, "Drive", 2011), movie(1, "Alien", 1979)],
+            vec![movie(4, "Collateral", 2004), movie(2, "Ronin", 1998)],
+        ]);
+
+        assert_eq!(merged_ids(&merged), [5, 4, 2, 1]);
+    }
+
+    #[test]
+    fn merge_keeps_one_copy_of_overlapping_movies() {
+        let merged = merge_databases(vec![
+            vec![movie(3, "Heat", 1995), movie(2, "Ronin", 1998)],
+            vec![movie(3, "Heat", 1995), movie(1, "Alien", 1979)],
+            vec![movie(2, "Ronin", 1998)],
+        ]);
+
+        assert_eq!(merged_ids(&merged), [3, 2, 1]);
+    }
+
+    #[test]
+    fn merge_prefers_the_copy_with_more_torrents() {
+        let mut full = movie(3, "Heat", 1995);
+        full.torrents.push(torrent("2160p", 4_000_000_000));
+        let bare = Movie { torrents: Vec::new(), ..movie(3, "Heat (bare)", 1995) };
+
+        let merged = merge_databases(vec![vec![bare], vec![full], vec![movie(3, "Heat (720p)", 1995)]]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].title, "Heat");
+        assert_eq!(merged[0].torrents.len(), 2);
+
+        // On a tie the earliest input wins.
+        let merged = merge_databases(vec![vec![movie(3, "First", 1995)], vec![movie(3, "Second", 1995)]]);
+        assert_eq!(merged[0].title, "First");
+    }
+
+    #[test]
+    fn sync_gives_both_sides_the_union() {
+        let (a, b, stats) = sync_databases(
+            vec![movie(4, "Collateral", 2004), movie(2, "Ronin", 1998)],
+            vec![movie(3, "Heat", 1995), movie(2, "Ronin", 1998), movie(1, "Alien", 1979)],
+        );
+
+        assert_eq!(merged_ids(&a), [4, 3, 2, 1]);
+        assert_eq!(merged_ids(&b), [4, 3, 2, 1]);
+        assert_eq!(stats, SyncStats { added_to_a: 2, added_to_b: 1 });
+    }
+
+    #[test]
+    fn sync_only_fills_the_side_that_is_behind() {
+        let (a, b, stats) = sync_databases(vec![movie(1, "Alien", 1979)], Vec::new());
+
+        assert_eq!(merged_ids(&a), [1]);
+        assert_eq!(merged_ids(&b), [1]);
+        assert_eq!(stats, SyncStats { added_to_a: 0, added_to_b: 1 });
+    }
+
+    #[test]
+    fn sync_leaves_shared_movies_as_each_side_has_them() {
+        let renamed = Movie { title: "Alien: Director's Cut".to_string(), ..movie(1, "Alien", 1979) };
+
+        let (a, b, stats) = sync_databases(vec![movie(1, "Alien", 1979)], vec![renamed]);
+
+        assert_eq!(a[0].title, "Alien");
+        assert_eq!(b[0].title, "Alien: Director's Cut");
+        assert_eq!(stats, SyncStats::default());
+    }
+
+    #[test]
+    fn sync_command_writes_both_files_unless_dry_run() {
+        let (dir_a, dir_b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let a = json_storage(dir_a.path(), &[movie(2, "Ronin", 1998)]);
+        let b = json_storage(dir_b.path(), &[movie(1, "Alien", 1979)]);
+
+        sync_command(&a, &b, true).unwrap();
+        assert_eq!(merged_ids(&a.load().unwrap()), [2]);
+        assert_eq!(merged_ids(&b.load().unwrap()), [1]);
+
+        sync_command(&a, &b, false).unwrap();
+        assert_eq!(merged_ids(&a.load().unwrap()), [2, 1]);
+        assert_eq!(merged_ids(&b.load().unwrap()), [2, 1]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Downloading poster and background images.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use futures::stream::{self, StreamExt};
+use tokio::io::AsyncWriteExt;
+
+use crate::{genre_matches, Movie, Printer, Result, Storage, YtsApi};
+
+#[derive(Args, Debug)]
+pub struct CoverGridOpts {
+    /// Path of the HTML file to write (-o/--output names the database)
+    #[arg(default_value = "covers.html")]
+    pub path: PathBuf,
+
+    /// Number of posters to show, newest first (0 = all)
+    #[arg(short, long, default_value_t = 0)]
+    pub limit: usize,
+
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Earliest release year (inclusive)
+    #[arg(long)]
+    pub min_year: Option<u32>,
+
+    /// Latest release year (inclusive)
+    #[arg(long)]
+    pub max_year: Option<u32>,
+
+    /// Minimum IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+}
+
+impl CoverGridOpts {
+    /// Movies without a cover image never match.
+    pub fn matches(&self, movie: &Movie) -> bool {
+        !movie.large_cover_image.is_empty()
+            && self.min_year.is_none_or(|y| movie.year >= y)
+            && self.max_year.is_none_or(|y| movie.year <= y)
+            && self.min_rating.is_none_or(|r| movie.rating >= r)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CoverSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl CoverSize {
+    pub fn url(self, movie: &Movie) -> &str {
+        match self {
+            CoverSize::Small => &movie.small_cover_image,
+            CoverSize::Medium => &movie.medium_cover_image,
+            CoverSize::Large => &movie.large_cover_image,
+        }
+    }
+}
+
+/// Replaces anything but ASCII letters and digits with `_` so titles are safe
+/// to use in file names.
+pub(crate) fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+async fn download_file(client: &reqwest::Client, url: &str, dest: &Path) -> Result<()> {
+    tracing::debug!("GET {}", url);
+    let response = client.get(url).send().await?.error_for_status()?;
+    let bytes = response.bytes().await?;
+    tokio::fs::write(dest, &bytes).await?;
+    Ok(())
+}
+
+pub async fn download_images(
+    storage: &dyn Storage,
+    api: &YtsApi,
+    output_dir: &Path,
+    size: CoverSize,
+    limit: usize,
+    concurrency: u32,
+) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    fs::create_dir_all(output_dir)?;
+
+    let count = if limit == 0 { movies.len() } else { limit.min(movies.len()) };
+    let mut skipped = 0;
+    let mut jobs = Vec::new();
+
+    for movie in movies.iter().take(count) {
+        let url = size.url(movie);
+        let dest = output_dir.join(format!("{}_{}.jpg", movie.id, sanitize_filename(&movie.title)));
+
+        if url.is_empty() || dest.exists() {
+            skipped += 1;
+            continue;
+        }
+
+        jobs.push((movie, url, dest));
+    }
+
+    say!("🖼️  Downloading {} covers to {} ({} skipped)\n", jobs.len(), output_dir.display(), skipped);
+
+    let pb = Printer::global().progress_bar(Some(jobs.len() as u64), "{pos}/{len} images");
+
+    let client = &api.client;
+    let mut downloads = stream::iter(jobs)
+        .map(|(movie, url, dest)| async move { (movie, download_file(client, url, &dest).await) })
+        .buffer_unordered(concurrency as usize);
+
+    let mut failed = 0;
+    while let Some((movie, result)) = downloads.next().await {
+        if let Err(e) = result {
+            pb.suspend(|| say_err!("⚠️  Failed to download cover for {} ({}): {}", movie.title, movie.id, e));
+            failed += 1;
+        }
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("✅ Downloads complete");
+
+    say!("\n✅ Downloaded {} covers ({} failed)", pb.position() - failed, failed);
+
+    Ok(())
+}
+
+/// Streams `url` into `dest`, showing a byte progress bar. Returns the number
+/// of bytes written.
+pub(crate) async fn download_with_progress(client: &reqwest::Client, url: &str, dest: &Path) -> Result<u64> {
+    tracing::debug!("GET {}", url);
+    let mut response = client.get(url).send().await?.error_for_status()?;
+
+    let pb = Printer::global().progress_bar(response.content_length(), "{bytes}/{total_bytes}");
+
+    let mut file = tokio::fs::File::create(dest).await?;
+    let mut written = 0;
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+        pb.set_position(written);
+    }
+    file.flush().await?;
+
+    pb.finish_and_clear();
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{json_storage, movie, yts_api};
+
+    async fn serve_cover(server: &MockServer, name: &str, bytes: &[u8]) {
+        Mock::given(method("GET"))
+            .and(path(format!("/covers/{}", name)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(bytes))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn covers_are_written_once() {
+        let server = MockServer::start().await;
+        serve_cover(&server, "heat.jpg", b"heat poster").await;
+        serve_cover(&server, "alien.jpg", b"alien poster").await;
+        let with_cover = |id, title: &str, year, name: &str| Movie {
+            large_cover_image: format!("{}/covers/{}", server.uri(), name),
+            ..movie(id, title, year)
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(
+            dir.path(),
+            &[with_cover(2, "Heat", 1995, "heat.jpg"), with_cover(1, "Alien: 1979", 1979, "alien.jpg")],
+        );
+        let covers = dir.path().join("covers");
+        let api = yts_api(&server, 20);
+
+        download_images(&storage, &api, &covers, CoverSize::Large, 0, 2).await.unwrap();
+        assert_eq!(fs::read(covers.join("2_Heat.jpg")).unwrap(), b"heat poster");
+        assert_eq!(fs::read(covers.join("1_Alien__1979.jpg")).unwrap(), b"alien poster");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        fs::write(covers.join("2_Heat.jpg"), b"edited").unwrap();
+        download_images(&storage, &api, &covers, CoverSize::Large, 0, 2).await.unwrap();
+        assert_eq!(fs::read(covers.join("2_Heat.jpg")).unwrap(), b"edited");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn movies_without_the_cover_size_are_skipped() {
+        let server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995)]);
+        let covers = dir.path().join("covers");
+
+        download_images(&storage, &yts_api(&server, 20), &covers, CoverSize::Small, 0, 2).await.unwrap();
+        assert_eq!(fs::read_dir(&covers).unwrap().count(), 0);
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
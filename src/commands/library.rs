@@ -0,0 +1,264 @@
+//! The watchlist and tags kept on each movie.
+
+use crate::{build_histogram, print_movie_table, Movie, Result, Storage, YtsError};
+
+/// Sets the watchlist flag of movie `id`. Returns whether the flag changed,
+/// or [`YtsError::NotFound`] if there is no such movie.
+pub fn set_watchlisted(movies: &mut [Movie], id: u32, watchlisted: bool) -> Result<bool> {
+    let movie = movies.iter_mut().find(|m| m.id == id).ok_or(YtsError::NotFound(id))?;
+    let changed = movie.watchlisted != watchlisted;
+    movie.watchlisted = watchlisted;
+    Ok(changed)
+}
+
+pub fn watchlist_add(storage: &dyn Storage, id: u32) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    if set_watchlisted(&mut movies, id, true)? {
+        storage.save(&movies)?;
+    }
+
+    let movie = movies.iter().find(|m| m.id == id).expect("movie exists after set_watchlisted");
+    say!("⭐ {} ({}) is on the watchlist", movie.title, movie.year);
+
+    Ok(())
+}
+
+/// Removing a movie that is not on the watchlist (or not in the database at
+/// all) is not an error.
+pub fn watchlist_remove(storage: &dyn Storage, id: u32) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    match set_watchlisted(&mut movies, id, false) {
+        Ok(true) => {
+            storage.save(&movies)?;
+            say!("🗑️  Removed movie {} from the watchlist", id);
+        }
+        Ok(false) | Err(YtsError::NotFound(_)) => say!("ℹ️  Movie {} is not on the watchlist", id),
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+/// Prints the movies on the watchlist and returns how many there are.
+pub fn watchlist_show(storage: &dyn Storage) -> Result<usize> {
+    let movies = storage.load()?;
+    let watchlist: Vec<&Movie> = movies.iter().filter(|m| m.watchlisted).collect();
+
+    if watchlist.is_empty() {
+        say!("⭐ The watchlist is empty. Add movies with 'watchlist add --id ID'.");
+        return Ok(0);
+    }
+
+    print_movie_table(&watchlist, 0)?;
+    Ok(watchlist.len())
+}
+
+/// Trims and lowercases `tag`. Tags are stored comma-separated in SQLite, so
+/// they may not contain commas.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::normalize_tag;
+///
+/// assert_eq!(normalize_tag("  Rewatch ").unwrap(), "rewatch");
+/// assert_eq!(normalize_tag("Date Night").unwrap(), "date night");
+/// assert!(normalize_tag("   ").is_err());
+/// assert!(normalize_tag("a,b").is_err());
+/// ```
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let normalized = tag.trim().to_lowercase();
+    if normalized.is_empty() || normalized.contains(',') {
+        return Err(YtsError::InvalidTag(tag.to_string()));
+    }
+    Ok(normalized)
+}
+
+/// `tag` is compared after normalizing, so `"Rewatch"` finds `"rewatch"`.
+pub(crate) fn has_tag(movie: &Movie, tag: &str) -> bool {
+    let tag = tag.trim().to_lowercase();
+    movie.tags.contains(&tag)
+}
+
+/// Attaches `tag` to movie `id`. Returns whether the movie changed (false if
+/// it already had the tag), or [`YtsError::NotFound`] if there is no such movie.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{add_tag, Movie};
+///
+/// let mut movies = vec![Movie { id: 7, ..Default::default() }];
+///
+/// assert!(add_tag(&mut movies, 7, "Rewatch").unwrap());
+/// assert!(!add_tag(&mut movies, 7, "rewatch").unwrap());
+/// assert!(add_tag(&mut movies, 7, "comfort").unwrap());
+/// assert_eq!(movies[0].tags, ["rewatch", "comfort"]);
+///
+/// assert!(add_tag(&mut movies, 8, "rewatch").is_err());
+/// assert!(add_tag(&mut movies, 7, "").is_err());
+/// ```
+pub fn add_tag(movies: &mut [Movie], id: u32, tag: &str) -> Result<bool> {
+    let tag = normalize_tag(tag)?;
+    let movie = movies.iter_mut().find(|m| m.id == id).ok_or(YtsError::NotFound(id))?;
+    if movie.tags.contains(&tag) {
+        return Ok(false);
+    }
+    movie.tags.push(tag);
+    Ok(true)
+}
+
+/// Detaches `tag` from movie `id`. Returns whether the movie had the tag.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{remove_tag, Movie};
+///
+/// let mut movies = vec![Movie { id: 7, tags: vec!["rewatch".into()], ..Default::default() }];
+///
+/// assert!(remove_tag(&mut movies, 7, "Rewatch").unwrap());
+/// assert!(!remove_tag(&mut movies, 7, "rewatch").unwrap());
+/// assert!(movies[0].tags.is_empty());
+/// assert!(remove_tag(&mut movies, 8, "rewatch").is_err());
+/// ```
+pub fn remove_tag(movies: &mut [Movie], id: u32, tag: &str) -> Result<bool> {
+    let tag = normalize_tag(tag)?;
+    let movie = movies.iter_mut().find(|m| m.id == id).ok_or(YtsError::NotFound(id))?;
+    let before = movie.tags.len();
+    movie.tags.retain(|t| *t != tag);
+    Ok(movie.tags.len() != before)
+}
+
+/// Movies carrying `tag`, in database order.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{movies_with_tag, Movie};
+///
+/// let tagged = |id, tags: &[&str]| Movie {
+///     id,
+///     tags: tags.iter().map(|t| t.to_string()).collect(),
+///     ..Default::default()
+/// };
+/// let movies = vec![tagged(1, &["rewatch"]), tagged(2, &[]), tagged(3, &["comfort", "rewatch"])];
+///
+/// let ids: Vec<u32> = movies_with_tag(&movies, "REWATCH").iter().map(|m| m.id).collect();
+/// assert_eq!(ids, [1, 3]);
+/// assert_eq!(movies_with_tag(&movies, "comfort").len(), 1);
+/// assert!(movies_with_tag(&movies, "unknown").is_empty());
+/// ```
+pub fn movies_with_tag<'a>(movies: &'a [Movie], tag: &str) -> Vec<&'a Movie> {
+    movies.iter().filter(|m| has_tag(m, tag)).collect()
+}
+
+pub fn tag_add(storage: &dyn Storage, id: u32, tag: &str) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    if add_tag(&mut movies, id, tag)? {
+        storage.save(&movies)?;
+    }
+
+    let movie = movies.iter().find(|m| m.id == id).expect("movie exists after add_tag");
+    say!("🏷️  {} ({}) is tagged {}", movie.title, movie.year, movie.tags.join(", "));
+
+    Ok(())
+}
+
+/// Removing a tag the movie does not have (or from a movie not in the
+/// database at all) is not an error.
+pub fn tag_remove(storage: &dyn Storage, id: u32, tag: &str) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    match remove_tag(&mut movies, id, tag) {
+        Ok(true) => {
+            storage.save(&movies)?;
+            say!("🗑️  Removed tag '{}' from movie {}", tag.trim().to_lowercase(), id);
+        }
+        Ok(false) | Err(YtsError::NotFound(_)) => {
+            say!("ℹ️  Movie {} is not tagged '{}'", id, tag.trim().to_lowercase())
+        }
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+/// Without `tag`, lists every tag in use with its number of movies.
+pub fn tag_list(storage: &dyn Storage, tag: Option<&str>) -> Result<()> {
+    let movies = storage.load()?;
+
+    if let Some(tag) = tag {
+        let tagged = movies_with_tag(&movies, tag);
+        if tagged.is_empty() {
+            say!("🏷️  No movies are tagged '{}'.", tag.trim().to_lowercase());
+            return Ok(());
+        }
+        return print_movie_table(&tagged, 0);
+    }
+
+    let tags: Vec<&str> = movies.iter().flat_map(|m| m.tags.iter().map(String::as_str)).collect();
+    if tags.is_empty() {
+        say!("🏷️  No tags yet. Add one with 'tag add --id ID --tag LABEL'.");
+        return Ok(());
+    }
+
+    say!("🏷️  Tags:");
+    for (tag, count) in build_histogram(&tags) {
+        say!("  {} ({})", tag, count);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{json_storage, movie};
+
+    fn watchlisted(storage: &dyn Storage) -> Vec<u32> {
+        storage.load().unwrap().iter().filter(|m| m.watchlisted).map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn watchlist_add_flags_the_movie() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995), movie(1, "Alien", 1979)]);
+
+        watchlist_add(&storage, 2).unwrap();
+        watchlist_add(&storage, 2).unwrap();
+        assert_eq!(watchlisted(&storage), [2]);
+
+        assert!(matches!(watchlist_add(&storage, 9), Err(YtsError::NotFound(9))));
+    }
+
+    #[test]
+    fn watchlist_remove_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995), movie(1, "Alien", 1979)]);
+        watchlist_add(&storage, 1).unwrap();
+        watchlist_add(&storage, 2).unwrap();
+
+        watchlist_remove(&storage, 1).unwrap();
+        assert_eq!(watchlisted(&storage), [2]);
+
+        watchlist_remove(&storage, 1).unwrap();
+        watchlist_remove(&storage, 9).unwrap();
+        assert_eq!(watchlisted(&storage), [2]);
+    }
+
+    #[test]
+    fn watchlist_show_counts_only_watchlisted_movies() {
+        let dir = tempfile::tempdir().unwrap();
+        let movies = [movie(3, "Ronin", 1998), movie(2, "Heat", 1995), movie(1, "Alien", 1979)];
+        let storage = json_storage(dir.path(), &movies);
+        assert_eq!(watchlist_show(&storage).unwrap(), 0);
+
+        watchlist_add(&storage, 3).unwrap();
+        watchlist_add(&storage, 1).unwrap();
+        assert_eq!(watchlist_show(&storage).unwrap(), 2);
+    }
+}
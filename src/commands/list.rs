@@ -0,0 +1,638 @@
+//! `list`, `search`, `info`, `filter` and `quality-report`.
+
+use std::io::{self, IsTerminal, Write};
+
+use chrono::DateTime;
+use clap::Args;
+
+use crate::{
+    compute_total_pages, fetch_page, fetch_page_batch, filter_movies, format_fields, format_size, genre_matches,
+    has_tag, movie_from_api, movies_missing_quality, parse_size_str, quality_matches, sort_movies, title_matches, tui,
+    write_movies, ColorScheme, FetchParams, FilterOpts, ListField, Movie, OutputFormat, Printer, Result, SortField,
+    Storage, TableFormatter, YtsApi, YtsError,
+};
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Number of movies to display (0 = all)
+    #[arg(short, long, default_value_t = 10)]
+    pub limit: usize,
+
+    /// Sort movies by this field instead of database order
+    #[arg(short, long, value_enum)]
+    pub sort: Option<SortField>,
+
+    /// Sort in ascending order
+    #[arg(long, conflicts_with = "desc")]
+    pub asc: bool,
+
+    /// Sort in descending order (default)
+    #[arg(long)]
+    pub desc: bool,
+
+    /// How to print the movies
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output_format: OutputFormat,
+
+    /// Print one JSON object per line; short for `--output-format ndjson`
+    #[arg(long, conflicts_with = "output_format")]
+    pub ndjson: bool,
+
+    /// Browse the movies in a full-screen terminal UI (ignores --limit)
+    #[arg(short, long, conflicts_with_all = ["ndjson", "output_format"])]
+    pub interactive: bool,
+
+    /// Print only these comma-separated fields, one line per movie (per torrent with
+    /// quality, size, hash or magnet): id, title, year, imdb, rating, quality, size, hash, magnet
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = str::parse::<ListField>,
+        conflicts_with_all = ["ndjson", "output_format", "interactive"]
+    )]
+    pub fields: Option<Vec<ListField>>,
+
+    /// Open the IMDb page of the movie shown in the browser (prints the URL without a desktop)
+    #[arg(long, conflicts_with_all = ["interactive", "fields"])]
+    pub imdb_link: bool,
+
+    /// With --imdb-link, open every movie shown instead of requiring exactly one
+    #[arg(long, requires = "imdb_link")]
+    pub all: bool,
+
+    /// Colour the torrent qualities of the table; defaults to quality-color when writing to a terminal
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "quality-color",
+        conflicts_with_all = ["ndjson", "output_format", "interactive", "fields"]
+    )]
+    pub color_output: Option<ColorScheme>,
+
+    /// Separator between --fields values
+    #[arg(long, default_value = "\t", hide_default_value = true, requires = "fields")]
+    pub separator: String,
+
+    /// Only show movies with at least this IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+
+    /// Only show movies listed under this genre, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Only show movies with a torrent of this type, e.g. "bluray"
+    #[arg(short, long)]
+    pub torrent_type: Option<String>,
+
+    /// Only show movies on the watchlist
+    #[arg(short, long)]
+    pub watchlisted: bool,
+
+    /// Only show movies with this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Only show movies with a torrent of at least this size, e.g. "700MB"
+    #[arg(long, value_parser = parse_size_str)]
+    pub min_size: Option<u64>,
+
+    /// Only show movies with a torrent of at most this size, e.g. "1.5GB"
+    #[arg(long, value_parser = parse_size_str)]
+    pub max_size: Option<u64>,
+}
+
+impl ListArgs {
+    /// The requested format, honouring `--ndjson`.
+    pub fn format(&self) -> OutputFormat {
+        if self.ndjson {
+            OutputFormat::Ndjson
+        } else {
+            self.output_format
+        }
+    }
+
+    /// The colours of the table: `--color-output` if given, otherwise
+    /// [`ColorScheme::QualityColor`] when stdout is a terminal. Always
+    /// [`ColorScheme::None`] with `--no-color` or `NO_COLOR`.
+    pub fn color_scheme(&self) -> ColorScheme {
+        if !Printer::global().decorates() {
+            return ColorScheme::None;
+        }
+        self.color_output.unwrap_or(if io::stdout().is_terminal() {
+            ColorScheme::QualityColor
+        } else {
+            ColorScheme::None
+        })
+    }
+
+    pub fn matches(&self, movie: &Movie) -> bool {
+        (!self.watchlisted || movie.watchlisted)
+            && self.tag.as_deref().is_none_or(|t| has_tag(movie, t))
+            && self.min_rating.is_none_or(|r| movie.rating >= r)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+            && self.torrent_type.as_deref().is_none_or(|t| {
+                movie.torrents.iter().any(|torrent| torrent.torrent_type.eq_ignore_ascii_case(t))
+            })
+            && self.matches_size(movie)
+    }
+
+    /// Both size limits must hold for the same torrent.
+    fn matches_size(&self, movie: &Movie) -> bool {
+        if self.min_size.is_none() && self.max_size.is_none() {
+            return true;
+        }
+
+        movie.torrents.iter().any(|t| {
+            self.min_size.is_none_or(|min| t.size_bytes >= min) && self.max_size.is_none_or(|max| t.size_bytes <= max)
+        })
+    }
+}
+
+fn display_movies<F>(movies: &[Movie], limit: usize, predicate: F) -> Result<usize>
+where
+    F: Fn(&Movie) -> bool,
+{
+    let matches: Vec<&Movie> = movies.iter().filter(|m| predicate(m)).collect();
+    print_movie_table(&matches, limit)?;
+    Ok(matches.len())
+}
+
+pub(crate) fn print_movie_table(matches: &[&Movie], limit: usize) -> Result<()> {
+    write_movie_table(matches, limit, &mut io::stdout().lock())
+}
+
+/// Writes the first `limit` of `matches` (0 = all) as a table, or nothing if
+/// there are none.
+fn write_movie_table(matches: &[&Movie], limit: usize, out: &mut dyn Write) -> Result<()> {
+    if matches.is_empty() {
+        return Ok(());
+    }
+
+    let display_count = if limit == 0 { matches.len() } else { limit.min(matches.len()) };
+    let formatter = TableFormatter { shown: display_count, total: matches.len(), colors: ColorScheme::None };
+    write_movies(&formatter, &matches[..display_count], out)
+}
+
+pub fn list_movies(storage: &dyn Storage, args: &ListArgs) -> Result<()> {
+    let mut movies = storage.load()?;
+    let format = args.format();
+
+    if movies.is_empty() && format == OutputFormat::Table {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    if let Some(by) = args.sort {
+        sort_movies(&mut movies, by, args.asc);
+    }
+
+    if args.interactive {
+        if !io::stdout().is_terminal() {
+            return Err(YtsError::IoError(io::Error::other("--interactive needs a terminal")));
+        }
+        movies.retain(|m| args.matches(m));
+        return tui::run(movies);
+    }
+
+    let matches: Vec<&Movie> = movies.iter().filter(|m| args.matches(m)).collect();
+    let count = if args.limit == 0 { matches.len() } else { args.limit.min(matches.len()) };
+
+    if let Some(fields) = &args.fields {
+        let mut out = io::stdout().lock();
+        for movie in &matches[..count] {
+            for line in format_fields(movie, fields, &args.separator) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if matches.is_empty() && format == OutputFormat::Table {
+        say!("🔍 No movies match the given filters.");
+        return Ok(());
+    }
+
+    if args.imdb_link && count > 1 && !args.all {
+        return Err(YtsError::TooManyMatches(count));
+    }
+
+    let formatter = match format {
+        OutputFormat::Table => {
+            Box::new(TableFormatter { shown: count, total: matches.len(), colors: args.color_scheme() })
+        }
+        _ => format.formatter(count, matches.len()),
+    };
+    write_movies(formatter.as_ref(), &matches[..count], &mut io::stdout().lock())?;
+
+    if args.imdb_link {
+        for movie in &matches[..count] {
+            open_movie_imdb(movie)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the movies whose title matches `query` (see [`title_matches`]),
+/// optionally only those from `year`, and returns how many matched.
+pub fn search_movies(storage: &dyn Storage, query: &str, year: Option<u32>, exact: bool) -> Result<usize> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(0);
+    }
+
+    let found = display_movies(&movies, 0, |m| {
+        title_matches(&m.title, query, exact) && year.is_none_or(|y| m.year == y)
+    })?;
+
+    if found == 0 {
+        match year {
+            Some(y) => say!("🔍 No movies matching '{}' from {} found in database.", query, y),
+            None => say!("🔍 No movies matching '{}' found in database.", query),
+        }
+    }
+
+    Ok(found)
+}
+
+/// Searches YTS directly with `query_term` and writes the first `limit`
+/// matches (0 = all) to `out` as a table, without touching the local
+/// database. Returns the movies written.
+pub async fn remote_search(
+    api: &YtsApi,
+    params: &FetchParams,
+    limit: usize,
+    concurrency: u32,
+    trackers: &[String],
+    out: &mut dyn Write,
+) -> Result<Vec<Movie>> {
+    let query = params.query_term.as_deref().unwrap_or_default();
+    say!("🌐 Searching YTS for '{}'...\n", query);
+
+    let first_response = fetch_page(api, params, 1).await?;
+    let total_count = first_response.data.movie_count;
+
+    if total_count == 0 {
+        say!("❌ No movies matching '{}' found on YTS.", query);
+        return Ok(Vec::new());
+    }
+
+    let wanted = if limit == 0 { total_count } else { total_count.min(limit as u32) };
+    let last_page = compute_total_pages(wanted, api.page_size);
+
+    let mut api_movies = first_response.data.movies.unwrap_or_default();
+    if last_page > 1 {
+        let batch = fetch_page_batch(api, params, (2..=last_page).collect(), concurrency, |_, _| Ok(())).await?;
+        api_movies.extend(batch.into_iter().flat_map(|(_, movies)| movies));
+    }
+    api_movies.truncate(wanted as usize);
+
+    let movies: Vec<Movie> = api_movies.iter().map(|m| movie_from_api(m, Some(trackers))).collect();
+    let matches: Vec<&Movie> = movies.iter().collect();
+
+    say!("Found {} movies on YTS:\n", total_count);
+    write_movie_table(&matches, limit, out)?;
+    Ok(movies)
+}
+
+/// How `info` picks a movie out of the local database.
+pub enum MovieKey<'a> {
+    Id(u32),
+    /// IMDb code, compared case-insensitively.
+    Imdb(&'a str),
+}
+
+/// Renders every stored field of a movie as a multi-line card, one torrent
+/// per block with its full magnet link.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{format_movie_card, Movie, Torrent};
+///
+/// let movie = Movie {
+///     id: 7,
+///     title: "Heat".into(),
+///     year: 1995,
+///     imdb_code: "tt0113277".into(),
+///     rating: 8.3,
+///     genres: vec!["Crime".into(), "Drama".into()],
+///     director: Some("Michael Mann".into()),
+///     plot: Some("A heist crew is hunted by a detective.".into()),
+///     large_cover_image: "https://img.yts.mx/heat.jpg".into(),
+///     torrents: vec![Torrent {
+///         quality: "1080p".into(),
+///         torrent_type: "bluray".into(),
+///         hash: "ABC123".into(),
+///         size_bytes: 2 * 1024 * 1024 * 1024,
+///         magnet_url: "magnet:?xt=urn:btih:ABC123".into(),
+///         ..Default::default()
+///     }],
+///     ..Default::default()
+/// };
+///
+/// let card = format_movie_card(&movie);
+/// for expected in [
+///     "Heat (1995)", "tt0113277", "8.3/10", "Crime, Drama", "Michael Mann",
+///     "A heist crew is hunted by a detective.", "https://img.yts.mx/heat.jpg",
+///     "1080p bluray", "2.00 GB", "ABC123", "magnet:?xt=urn:btih:ABC123",
+/// ] {
+///     assert!(card.contains(expected), "missing {expected}");
+/// }
+/// ```
+pub fn format_movie_card(movie: &Movie) -> String {
+    let unknown = || "unknown".to_string();
+    let heading = format!("🎬 {} ({})", movie.title, movie.year);
+
+    let mut lines = vec![
+        heading.clone(),
+        "─".repeat(heading.chars().count()),
+        format!("ID:        {}", movie.id),
+        format!("IMDb:      {} ({})", movie.imdb_code, imdb_url(&movie.imdb_code)),
+        format!("Rating:    {:.1}/10", movie.rating),
+    ];
+    if !movie.mpa_rating.is_empty() {
+        lines.push(format!("MPA:       {}", movie.mpa_rating));
+    }
+    lines.push(format!("Genres:    {}", if movie.genres.is_empty() { unknown() } else { movie.genres.join(", ") }));
+    lines.push(format!("Director:  {}", movie.director.clone().unwrap_or_else(unknown)));
+    if let Some(awards) = &movie.awards {
+        lines.push(format!("Awards:    {}", awards));
+    }
+    if movie.date_uploaded_unix > 0 {
+        if let Some(uploaded) = DateTime::from_timestamp(movie.date_uploaded_unix, 0) {
+            lines.push(format!("Added:     {}", uploaded.format("%Y-%m-%d")));
+        }
+    }
+    if let Some(runtime) = movie.runtime_minutes {
+        lines.push(format!("Runtime:   {}h {:02}m", runtime / 60, runtime % 60));
+    }
+    if !movie.spoken_languages.is_empty() {
+        lines.push(format!("Languages: {}", movie.spoken_languages.join(", ")));
+    }
+    if movie.watchlisted {
+        lines.push("Watchlist: ⭐ yes".to_string());
+    }
+    if !movie.tags.is_empty() {
+        lines.push(format!("Tags:      {}", movie.tags.join(", ")));
+    }
+    let cover = if movie.large_cover_image.is_empty() { unknown() } else { movie.large_cover_image.clone() };
+    lines.push(format!("Cover:     {}", cover));
+    if let Some(backdrop) = &movie.backdrop_url {
+        lines.push(format!("Backdrop:  {}", backdrop));
+    }
+    lines.push(String::new());
+    lines.push("Plot:".to_string());
+    let plot = movie.plot.as_deref().unwrap_or("Not available, fetch with --enrich-omdb");
+    lines.push(format!("  {}", plot));
+    lines.push(String::new());
+
+    if movie.torrents.is_empty() {
+        lines.push("Torrents:  none".to_string());
+    } else {
+        lines.push(format!("Torrents ({}):", movie.torrents.len()));
+    }
+    for torrent in &movie.torrents {
+        let kind = if torrent.torrent_type.is_empty() {
+            torrent.quality.clone()
+        } else {
+            format!("{} {}", torrent.quality, torrent.torrent_type)
+        };
+        lines.push(format!("  • {}, {}", kind, format_size(torrent.size_bytes)));
+        lines.push(format!("    Hash:   {}", torrent.hash));
+        let magnet = if torrent.magnet_url.is_empty() {
+            "not stored, run 'regenerate-magnets'"
+        } else {
+            &torrent.magnet_url
+        };
+        lines.push(format!("    Magnet: {}", magnet));
+    }
+
+    lines.join("\n")
+}
+
+/// IMDb page of a title, e.g. "tt0113277".
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::imdb_url;
+///
+/// assert_eq!(imdb_url("tt0113277"), "https://www.imdb.com/title/tt0113277/");
+/// ```
+pub fn imdb_url(imdb_code: &str) -> String {
+    format!("https://www.imdb.com/title/{}/", imdb_code)
+}
+
+/// Whether a browser can be opened: always on macOS and Windows, elsewhere
+/// only with a display server or `$BROWSER`.
+fn has_browser() -> bool {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY", "BROWSER"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+}
+
+/// Opens the IMDb page of `imdb_code` in the default browser, or prints its
+/// URL when there is no browser to open.
+pub fn open_imdb(imdb_code: &str) -> Result<()> {
+    let url = imdb_url(imdb_code);
+
+    if !has_browser() {
+        say!("{}", url);
+        return Ok(());
+    }
+
+    say!("🌐 Opening {}", url);
+    open::that_detached(&url)?;
+    Ok(())
+}
+
+fn open_movie_imdb(movie: &Movie) -> Result<()> {
+    if movie.imdb_code.is_empty() {
+        say_err!("⚠️  {} ({}) has no IMDb code", movie.title, movie.year);
+        return Ok(());
+    }
+    open_imdb(&movie.imdb_code)
+}
+
+pub fn info_command(storage: &dyn Storage, key: MovieKey<'_>, imdb_link: bool) -> Result<()> {
+    let movies = storage.load()?;
+
+    let movie = match key {
+        MovieKey::Id(id) => movies.iter().find(|m| m.id == id).ok_or(YtsError::NotFound(id))?,
+        MovieKey::Imdb(code) => movies
+            .iter()
+            .find(|m| m.imdb_code.eq_ignore_ascii_case(code))
+            .ok_or_else(|| YtsError::ImdbNotFound(code.to_string()))?,
+    };
+
+    say!("{}", format_movie_card(movie));
+
+    if imdb_link {
+        open_movie_imdb(movie)?;
+    }
+
+    Ok(())
+}
+
+/// Lists movies lacking the `want` quality. With `have` set, only movies that
+/// do have that quality are listed, e.g. 720p-only movies when upgrading to 1080p.
+pub fn quality_report_command(storage: &dyn Storage, want: &str, have: Option<&str>, limit: usize) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let mut missing = movies_missing_quality(&movies, want);
+    if let Some(have) = have {
+        missing.retain(|m| m.torrents.iter().any(|t| quality_matches(&t.label(), have)));
+    }
+
+    let description = match have {
+        Some(have) => format!("with {} but no {}", have, want),
+        None => format!("without a {} torrent", want),
+    };
+    if missing.is_empty() {
+        say!("✅ No movies {}.", description);
+        return Ok(());
+    }
+
+    say!("📉 {} of {} movies {}\n", missing.len(), movies.len(), description);
+    print_movie_table(&missing, limit)
+}
+
+pub fn filter_command(storage: &dyn Storage, opts: &FilterOpts, limit: usize) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let matches = filter_movies(&movies, opts);
+
+    if matches.is_empty() {
+        say!("🔍 No movies match the given filters.");
+        return Ok(());
+    }
+
+    print_movie_table(&matches, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{json_storage, list_response, movie, yts_api, LIST_MOVIES_PATH};
+
+    fn library(dir: &std::path::Path) -> crate::JsonStorage {
+        json_storage(
+            dir,
+            &[
+                movie(4, "The Matrix Resurrections", 2021),
+                movie(3, "The Matrix Revolutions", 2003),
+                movie(2, "The Matrix Reloaded", 2003),
+                movie(1, "The Matrix", 1999),
+            ],
+        )
+    }
+
+    #[test]
+    fn search_matches_title_substrings_ignoring_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = library(dir.path());
+
+        assert_eq!(search_movies(&storage, "matrix", None, false).unwrap(), 4);
+        assert_eq!(search_movies(&storage, "RELOADED", None, false).unwrap(), 1);
+        assert_eq!(search_movies(&storage, "the matrix", None, true).unwrap(), 1);
+    }
+
+    #[test]
+    fn search_filters_by_year() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = library(dir.path());
+
+        assert_eq!(search_movies(&storage, "matrix", Some(2003), false).unwrap(), 2);
+        assert_eq!(search_movies(&storage, "matrix", Some(1999), true).unwrap(), 0);
+        assert_eq!(search_movies(&storage, "the matrix", Some(1999), true).unwrap(), 1);
+    }
+
+    #[test]
+    fn search_without_results_returns_zero() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = library(dir.path());
+        assert_eq!(search_movies(&storage, "inception", None, false).unwrap(), 0);
+
+        let other = tempfile::tempdir().unwrap();
+        let empty = json_storage(other.path(), &[]);
+        assert_eq!(search_movies(&empty, "matrix", None, false).unwrap(), 0);
+    }
+
+    /// A server answering searches for "heat" with `pages`, one response per page.
+    async fn search_server(pages: &[(&[u32], u32)]) -> MockServer {
+        let server = MockServer::start().await;
+        for (page, (ids, movie_count)) in (1..).zip(pages) {
+            Mock::given(method("GET"))
+                .and(path(LIST_MOVIES_PATH))
+                .and(query_param("query_term", "heat"))
+                .and(query_param("page", page.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(list_response(ids, *movie_count)))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        server
+    }
+
+    fn heat() -> FetchParams {
+        FetchParams { query_term: Some("heat".to_string()), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn remote_search_lists_every_page_of_matches() {
+        let server = search_server(&[(&[9, 8], 3), (&[7], 3)]).await;
+        let mut out = Vec::new();
+
+        let found = remote_search(&yts_api(&server, 2), &heat(), 0, 2, &[], &mut out).await.unwrap();
+
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), [9, 8, 7]);
+        let table = String::from_utf8(out).unwrap();
+        for title in ["Movie 9", "Movie 8", "Movie 7"] {
+            assert!(table.contains(title), "{} missing from\n{}", title, table);
+        }
+    }
+
+    #[tokio::test]
+    async fn remote_search_stops_at_the_limit() {
+        let server = search_server(&[(&[9, 8], 3)]).await;
+        let mut out = Vec::new();
+
+        let found = remote_search(&yts_api(&server, 2), &heat(), 1, 2, &[], &mut out).await.unwrap();
+
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), [9]);
+        let table = String::from_utf8(out).unwrap();
+        assert!(table.contains("Movie 9") && !table.contains("Movie 8"), "{}", table);
+    }
+
+    #[tokio::test]
+    async fn remote_search_without_matches_writes_nothing() {
+        let server = search_server(&[(&[], 0)]).await;
+        let mut out = Vec::new();
+
+        let found = remote_search(&yts_api(&server, 2), &heat(), 0, 2, &[], &mut out).await.unwrap();
+
+        assert!(found.is_empty());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,568 @@
+//! Handing magnet links to other programs: `magnets`, `magnet-batch`, `download-all` and `send`.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    build_client, genre_matches, quality_matches, title_matches, HttpTimeouts, Movie, Result, Storage, Torrent,
+    YtsError,
+};
+
+/// Where to reach the Transmission RPC interface.
+#[derive(Args, Debug)]
+pub struct TransmissionOpts {
+    /// Host running Transmission
+    #[arg(long, default_value = "localhost")]
+    pub host: String,
+
+    /// RPC port of Transmission
+    #[arg(long, default_value_t = 9091)]
+    pub port: u16,
+
+    /// RPC username, if authentication is enabled
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// RPC password, if authentication is enabled
+    #[arg(long, requires = "user")]
+    pub password: Option<String>,
+}
+
+impl TransmissionOpts {
+    pub fn rpc_url(&self) -> String {
+        format!("http://{}:{}/transmission/rpc", self.host, self.port)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct MagnetBatchOpts {
+    /// Preferred torrent quality, e.g. "1080p" or "2160p-bluray"; movies without it are skipped
+    #[arg(short, long)]
+    pub quality: Option<String>,
+
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Earliest release year (inclusive)
+    #[arg(long)]
+    pub min_year: Option<u32>,
+
+    /// Latest release year (inclusive)
+    #[arg(long)]
+    pub max_year: Option<u32>,
+
+    /// Minimum IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+
+    /// Layout of the file
+    #[arg(short, long, value_enum, default_value_t = BatchFormat::Plain)]
+    pub format: BatchFormat,
+
+    /// File to write the links to (defaults to stdout)
+    #[arg(long)]
+    pub dest: Option<PathBuf>,
+}
+
+impl MagnetBatchOpts {
+    pub fn matches(&self, movie: &Movie) -> bool {
+        self.min_year.is_none_or(|y| movie.year >= y)
+            && self.max_year.is_none_or(|y| movie.year <= y)
+            && self.min_rating.is_none_or(|r| movie.rating >= r)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+    }
+
+    /// The torrent to list for `movie`: the largest one of the preferred
+    /// quality, or the largest overall. Torrents without a magnet are skipped.
+    pub fn pick_torrent<'a>(&self, movie: &'a Movie) -> Option<&'a Torrent> {
+        movie
+            .torrents
+            .iter()
+            .filter(|t| !t.magnet_url.is_empty())
+            .filter(|t| self.quality.as_deref().is_none_or(|q| quality_matches(&t.label(), q)))
+            .max_by_key(|t| t.size_bytes)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DownloadAllOpts {
+    /// Torrent quality to send, e.g. "1080p" or "2160p-bluray"; movies without it are skipped
+    #[arg(short, long)]
+    pub quality: String,
+
+    /// Command run for each movie, with the magnet link appended as its last argument
+    /// (not run through a shell), e.g. "transmission-remote localhost --add"
+    #[arg(long, value_name = "CMD", value_parser = parse_command_line)]
+    pub command: String,
+
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Earliest release year (inclusive)
+    #[arg(long)]
+    pub min_year: Option<u32>,
+
+    /// Latest release year (inclusive)
+    #[arg(long)]
+    pub max_year: Option<u32>,
+
+    /// Milliseconds to wait between two runs of the command
+    #[arg(long, default_value_t = 1000)]
+    pub delay_ms: u64,
+
+    /// Print the commands instead of running them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl DownloadAllOpts {
+    pub fn matches(&self, movie: &Movie) -> bool {
+        self.min_year.is_none_or(|y| movie.year >= y)
+            && self.max_year.is_none_or(|y| movie.year <= y)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+    }
+}
+
+/// Checks that `s` splits into words like a shell would, with at least one word.
+fn parse_command_line(s: &str) -> std::result::Result<String, String> {
+    match shlex::split(s) {
+        Some(words) if !words.is_empty() => Ok(s.to_string()),
+        Some(_) => Err("the command is empty".to_string()),
+        None => Err("unbalanced quotes or a trailing backslash".to_string()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum BatchFormat {
+    /// A "# Title (Year)" comment line above each magnet link
+    Plain,
+    /// Extended M3U playlist with an #EXTINF line per link
+    M3u,
+}
+
+pub fn print_magnets(
+    storage: &dyn Storage,
+    query: &str,
+    quality: &str,
+    torrent_type: Option<&str>,
+    all: bool,
+) -> Result<()> {
+    let movies = storage.load()?;
+    let matches: Vec<&Movie> = movies.iter().filter(|m| title_matches(&m.title, query, false)).collect();
+
+    if matches.is_empty() {
+        say_err!("❌ No movies matching '{}' found in database.", query);
+        return Ok(());
+    }
+
+    for movie in matches {
+        let torrents: Vec<&Torrent> = movie
+            .torrents
+            .iter()
+            .filter(|t| quality_matches(&t.label(), quality))
+            .filter(|t| torrent_type.is_none_or(|wanted| t.torrent_type.eq_ignore_ascii_case(wanted)))
+            .collect();
+
+        if torrents.is_empty() {
+            let wanted = match torrent_type {
+                Some(t) => format!("{} {}", quality, t),
+                None => quality.to_string(),
+            };
+            say_err!("⚠️  No {} torrent for {} ({}), skipping", wanted, movie.title, movie.year);
+            continue;
+        }
+
+        let selected = if all { &torrents[..] } else { &torrents[..1] };
+        for torrent in selected {
+            if torrent.magnet_url.is_empty() {
+                say_err!("⚠️  {} ({}) was fetched without magnets, run 'regenerate-magnets'", movie.title, movie.year);
+                continue;
+            }
+            say!("{}", torrent.magnet_url);
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders `entries` as a batch file of magnet links, one per line.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{format_magnet_batch, BatchFormat, Movie, Torrent};
+///
+/// let movie = Movie { title: "Heat".into(), year: 1995, ..Default::default() };
+/// let torrent = Torrent { magnet_url: "magnet:?xt=urn:btih:AAA".into(), ..Default::default() };
+/// let entries = [(&movie, &torrent)];
+///
+/// assert_eq!(
+///     format_magnet_batch(&entries, BatchFormat::Plain),
+///     "# Heat (1995)\nmagnet:?xt=urn:btih:AAA\n"
+/// );
+/// assert_eq!(
+///     format_magnet_batch(&entries, BatchFormat::M3u),
+///     "#EXTM3U\n#EXTINF:-1,Heat (1995)\nmagnet:?xt=urn:btih:AAA\n"
+/// );
+/// ```
+pub fn format_magnet_batch(entries: &[(&Movie, &Torrent)], format: BatchFormat) -> String {
+    let mut out = String::new();
+
+    if let BatchFormat::M3u = format {
+        out.push_str("#EXTM3U\n");
+    }
+    for (movie, torrent) in entries {
+        match format {
+            BatchFormat::Plain => out.push_str(&format!("# {} ({})\n", movie.title, movie.year)),
+            BatchFormat::M3u => out.push_str(&format!("#EXTINF:-1,{} ({})\n", movie.title, movie.year)),
+        }
+        out.push_str(&torrent.magnet_url);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Each movie with a magnet link of `quality` (see [`quality_matches`]),
+/// paired with its largest such torrent, in database order.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{iter_magnets, Movie, Torrent};
+///
+/// let torrent = |quality: &str, torrent_type: &str, size_bytes, magnet: &str| Torrent {
+///     quality: quality.into(),
+///     torrent_type: torrent_type.into(),
+///     size_bytes,
+///     magnet_url: magnet.into(),
+///     ..Default::default()
+/// };
+/// let movie = |title: &str, torrents| Movie { title: title.into(), torrents, ..Default::default() };
+/// let movies = vec![
+///     movie("Heat", vec![torrent("1080p", "web", 2, "magnet:a"), torrent("1080p", "bluray", 3, "magnet:b")]),
+///     movie("Dune", vec![torrent("720p", "web", 1, "magnet:c")]),
+///     movie("Alien", vec![torrent("1080p", "web", 9, "")]),
+/// ];
+///
+/// let picks: Vec<(&str, &str)> =
+///     iter_magnets(&movies, "1080p").map(|(m, t)| (m.title.as_str(), t.magnet_url.as_str())).collect();
+/// assert_eq!(picks, [("Heat", "magnet:b")]);
+/// assert_eq!(iter_magnets(&movies, "1080P-WEB").next().unwrap().1.magnet_url, "magnet:a");
+/// assert_eq!(iter_magnets(&movies, "2160p").count(), 0);
+/// ```
+pub fn iter_magnets<'a>(movies: &'a [Movie], quality: &str) -> impl Iterator<Item = (&'a Movie, &'a Torrent)> {
+    let quality = quality.to_string();
+    movies.iter().filter_map(move |movie| {
+        movie
+            .torrents
+            .iter()
+            .filter(|t| !t.magnet_url.is_empty() && quality_matches(&t.label(), &quality))
+            .max_by_key(|t| t.size_bytes)
+            .map(|t| (movie, t))
+    })
+}
+
+/// `download-all`: runs `opts.command` with each matching magnet link, one
+/// run every `opts.delay_ms`. Returns how many runs failed.
+pub async fn download_all_command(storage: &dyn Storage, opts: &DownloadAllOpts) -> Result<usize> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(0);
+    }
+
+    let entries: Vec<(&Movie, &Torrent)> =
+        iter_magnets(&movies, &opts.quality).filter(|(m, _)| opts.matches(m)).collect();
+    if entries.is_empty() {
+        say!("🔍 No movies with a {} magnet link match the given filters.", opts.quality);
+        return Ok(0);
+    }
+
+    let words = shlex::split(&opts.command).expect("checked by parse_command_line");
+    let (program, args) = words.split_first().expect("checked by parse_command_line");
+    let delay = Duration::from_millis(opts.delay_ms);
+    let mut failed = 0;
+
+    for (i, (movie, torrent)) in entries.iter().enumerate() {
+        if opts.dry_run {
+            let line = words.iter().map(String::as_str).chain([torrent.magnet_url.as_str()]);
+            say!("{}", shlex::try_join(line).unwrap_or_else(|_| format!("{} {}", opts.command, torrent.magnet_url)));
+            continue;
+        }
+        if i > 0 {
+            tokio::time::sleep(delay).await;
+        }
+
+        let name = format!("{} ({}) {}", movie.title, movie.year, torrent.label());
+        match tokio::process::Command::new(program).args(args).arg(&torrent.magnet_url).status().await {
+            Ok(status) if status.success() => say!("✅ {}", name),
+            Ok(status) => {
+                say_err!("❌ {}: command failed with {}", name, status);
+                failed += 1;
+            }
+            Err(e) => {
+                say_err!("❌ {}: could not run {}: {}", name, program, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if opts.dry_run {
+        say!("\n🔎 Dry run: {} magnet links would be sent", entries.len());
+    } else {
+        say!("\n📤 Sent {} of {} magnet links", entries.len() - failed, entries.len());
+    }
+
+    Ok(failed)
+}
+
+pub fn magnet_batch_command(storage: &dyn Storage, opts: &MagnetBatchOpts) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say_err!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let entries: Vec<(&Movie, &Torrent)> = movies
+        .iter()
+        .filter(|m| opts.matches(m))
+        .filter_map(|m| opts.pick_torrent(m).map(|t| (m, t)))
+        .collect();
+
+    if entries.is_empty() {
+        say_err!("🔍 No movies with a matching magnet link found.");
+        return Ok(());
+    }
+
+    let batch = format_magnet_batch(&entries, opts.format);
+    match &opts.dest {
+        Some(dest) => {
+            fs::write(dest, batch)?;
+            say!("✅ Wrote {} magnet links to {}", entries.len(), dest.display());
+        }
+        None => io::stdout().lock().write_all(batch.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+/// Header Transmission uses for its CSRF protection.
+const TRANSMISSION_SESSION_HEADER: &str = "X-Transmission-Session-Id";
+
+#[derive(Deserialize)]
+struct TransmissionResponse {
+    result: String,
+}
+
+/// Adds a magnet link to Transmission with a `torrent-add` RPC call.
+///
+/// Transmission rejects requests without a current session ID with 409 and
+/// sends the ID to use in the response headers, so the request is repeated
+/// once with it.
+pub async fn add_magnet_to_transmission(magnet: &str, opts: &TransmissionOpts) -> Result<()> {
+    // Transmission normally runs on the local network, so no proxy applies.
+    let client = build_client(None, true, HttpTimeouts::default())?;
+    let url = opts.rpc_url();
+    let body = serde_json::json!({
+        "method": "torrent-add",
+        "arguments": { "filename": magnet },
+    });
+    let mut session_id: Option<String> = None;
+
+    for _ in 0..2 {
+        let mut request = client.post(&url).json(&body);
+        if let Some(id) = &session_id {
+            request = request.header(TRANSMISSION_SESSION_HEADER, id);
+        }
+        if let Some(user) = &opts.user {
+            request = request.basic_auth(user, opts.password.as_deref());
+        }
+
+        tracing::debug!("POST {} (session {:?})", url, session_id);
+        let response = request.send().await?;
+        match response.status() {
+            StatusCode::CONFLICT => {
+                let id = response
+                    .headers()
+                    .get(TRANSMISSION_SESSION_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or_else(|| YtsError::ApiError("Transmission answered 409 without a session ID".to_string()))?;
+                session_id = Some(id.to_string());
+                continue;
+            }
+            StatusCode::UNAUTHORIZED => {
+                return Err(YtsError::ApiError(
+                    "Transmission rejected the credentials, check --user and --password".to_string(),
+                ));
+            }
+            status if !status.is_success() => {
+                return Err(YtsError::ApiError(format!("Transmission answered {}", status)));
+            }
+            _ => {}
+        }
+
+        let reply: TransmissionResponse = response.json().await?;
+        if reply.result != "success" {
+            return Err(YtsError::ApiError(format!("Transmission: {}", reply.result)));
+        }
+        return Ok(());
+    }
+
+    Err(YtsError::ApiError("Transmission did not accept its own session ID".to_string()))
+}
+
+/// Sends the first torrent of the movie matching `quality` to Transmission.
+pub async fn send_command(storage: &dyn Storage, id: u32, quality: &str, opts: &TransmissionOpts) -> Result<()> {
+    let movies = storage.load()?;
+    let movie = movies.iter().find(|m| m.id == id).ok_or(YtsError::NotFound(id))?;
+
+    let Some(torrent) = movie.torrents.iter().find(|t| quality_matches(&t.label(), quality)) else {
+        let available: Vec<String> = movie.torrents.iter().map(|t| t.label()).collect();
+        say_err!("❌ No {} torrent for {} ({}), available: {}", quality, movie.title, movie.year, available.join(", "));
+        return Ok(());
+    };
+    if torrent.magnet_url.is_empty() {
+        say_err!("⚠️  {} ({}) was fetched without magnets, run 'regenerate-magnets'", movie.title, movie.year);
+        return Ok(());
+    }
+
+    add_magnet_to_transmission(&torrent.magnet_url, opts).await?;
+    say!("📤 Sent {} ({}) {} to Transmission at {}:{}", movie.title, movie.year, torrent.label(), opts.host, opts.port);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{json_storage, movie, torrent};
+
+    fn picks(movies: &[Movie], quality: &str) -> Vec<(u32, String)> {
+        iter_magnets(movies, quality).map(|(m, t)| (m.id, t.label())).collect()
+    }
+
+    #[test]
+    fn iter_magnets_picks_the_largest_matching_torrent() {
+        let mut heat = movie(2, "Heat", 1995);
+        heat.torrents = vec![torrent("1080p", 5), torrent("1080p", 9), torrent("720p", 20)];
+        heat.torrents[1].torrent_type = "bluray".to_string();
+
+        let movies = [heat];
+        assert_eq!(iter_magnets(&movies, "1080p").next().unwrap().1.size_bytes, 9);
+        assert_eq!(iter_magnets(&movies, "720p").next().unwrap().1.size_bytes, 20);
+        assert_eq!(picks(&movies, "1080P-BLURAY"), [(2, "1080p-bluray".to_string())]);
+    }
+
+    #[test]
+    fn iter_magnets_skips_movies_without_a_matching_magnet() {
+        let mut fetched_without_magnets = movie(3, "Dune", 2021);
+        fetched_without_magnets.torrents[0].magnet_url.clear();
+        let mut only_720p = movie(2, "Heat", 1995);
+        only_720p.torrents = vec![torrent("720p", 7)];
+        let movies = [fetched_without_magnets, only_720p, movie(1, "Alien", 1979)];
+
+        assert_eq!(picks(&movies, "1080p"), [(1, "1080p".to_string())]);
+        assert!(picks(&movies, "2160p").is_empty());
+    }
+
+    #[test]
+    fn iter_magnets_keeps_database_order() {
+        let movies = [movie(1, "Alien", 1979), movie(3, "Dune", 2021), movie(2, "Heat", 1995)];
+        let ids: Vec<u32> = iter_magnets(&movies, "1080p").map(|(m, _)| m.id).collect();
+        assert_eq!(ids, [1, 3, 2]);
+    }
+
+    fn download_all(command: &str) -> DownloadAllOpts {
+        DownloadAllOpts {
+            quality: "1080p".to_string(),
+            command: command.to_string(),
+            genre: None,
+            min_year: None,
+            max_year: None,
+            delay_ms: 0,
+            dry_run: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn download_all_counts_the_failed_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995), movie(1, "Alien", 1979)]);
+
+        assert_eq!(download_all_command(&storage, &download_all("true")).await.unwrap(), 0);
+        assert_eq!(download_all_command(&storage, &download_all("false")).await.unwrap(), 2);
+        assert_eq!(download_all_command(&storage, &download_all("/nonexistent/yts-add")).await.unwrap(), 2);
+        let dry_run = DownloadAllOpts { dry_run: true, ..download_all("false") };
+        assert_eq!(download_all_command(&storage, &dry_run).await.unwrap(), 0);
+    }
+
+    const MAGNET: &str = "magnet:?xt=urn:btih:0123456789ABCDEF0123456789ABCDEF01234567";
+
+    fn transmission(server: &MockServer) -> TransmissionOpts {
+        let address = server.address();
+        TransmissionOpts { host: address.ip().to_string(), port: address.port(), user: None, password: None }
+    }
+
+    #[tokio::test]
+    async fn transmission_requests_are_repeated_with_the_session_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/transmission/rpc"))
+            .and(header(TRANSMISSION_SESSION_HEADER, "session-1"))
+            .and(body_json(serde_json::json!({ "method": "torrent-add", "arguments": { "filename": MAGNET } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "result": "success" })))
+            .expect(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/transmission/rpc"))
+            .respond_with(ResponseTemplate::new(409).insert_header(TRANSMISSION_SESSION_HEADER, "session-1"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        add_magnet_to_transmission(MAGNET, &transmission(&server)).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key(TRANSMISSION_SESSION_HEADER));
+        assert_eq!(requests[1].headers[TRANSMISSION_SESSION_HEADER], "session-1");
+    }
+
+    #[tokio::test]
+    async fn rejected_transmission_credentials_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/transmission/rpc"))
+            .and(header("authorization", "Basic dXNlcjp3cm9uZw=="))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let opts = TransmissionOpts {
+            user: Some("user".to_string()),
+            password: Some("wrong".to_string()),
+            ..transmission(&server)
+        };
+
+        let err = add_magnet_to_transmission(MAGNET, &opts).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "API error: Transmission rejected the credentials, check --user and --password"
+        );
+    }
+}
@@ -0,0 +1,812 @@
+//! Commands that check or repair the local database in place.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use clap::ValueEnum;
+
+use crate::{
+    create_magnet_url, dedup_movies, find_by_hash, find_dead_trackers, format_movie_card, format_size, is_dead_tracker,
+    is_info_hash, is_valid_sha1_hash, regenerate_magnets, truncate_chars, Movie, Result, Storage, Torrent, YtsError,
+    DEAD_TRACKERS,
+};
+
+/// Backups kept by `backup` and `fetch --auto-backup` unless told otherwise.
+pub const DEFAULT_BACKUP_KEEP: usize = 5;
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// Data `strip` can drop to make a database smaller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StripField {
+    /// Magnet links; `regenerate-magnets` rebuilds them from the hashes
+    Magnets,
+    /// Cover image and backdrop URLs
+    CoverUrls,
+    /// OMDb plot summaries
+    Plot,
+}
+
+fn timestamped_backup_path(db_path: &Path) -> PathBuf {
+    let stem = db_path.file_stem().and_then(|s| s.to_str()).unwrap_or("yts_movies");
+    let extension = db_path.extension().and_then(|s| s.to_str()).unwrap_or("json");
+    let timestamp = Utc::now().format(BACKUP_TIMESTAMP_FORMAT);
+    db_path.with_file_name(format!("{}_{}.{}", stem, timestamp, extension))
+}
+
+/// Backups of `source` in `dir` (files named like `yts_movies_20240101_120000.json`),
+/// newest first.
+fn list_backups(source: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("yts_movies");
+    let extension = source.extension().and_then(|s| s.to_str()).unwrap_or("json");
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let timestamp = name
+            .strip_prefix(stem)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .and_then(|rest| rest.strip_suffix(extension))
+            .and_then(|rest| rest.strip_suffix('.'))
+            .and_then(|ts| NaiveDateTime::parse_from_str(ts, BACKUP_TIMESTAMP_FORMAT).ok());
+        if let Some(timestamp) = timestamp {
+            backups.push((timestamp, path));
+        }
+    }
+
+    backups.sort_by_key(|(timestamp, _)| Reverse(*timestamp));
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Copies `source` to a timestamped file in `dest_dir`, then deletes all but
+/// the `keep` most recent backups there (the new one is always kept).
+/// Returns the path of the new backup.
+pub fn backup_database(source: &Path, dest_dir: &Path, keep: usize) -> Result<PathBuf> {
+    fs::create_dir_all(dest_dir)?;
+
+    let file_name = timestamped_backup_path(source).file_name().map(|n| n.to_owned()).unwrap_or_default();
+    let dest = dest_dir.join(file_name);
+    fs::copy(source, &dest)?;
+
+    for old in list_backups(source, dest_dir)?.into_iter().skip(keep.max(1)) {
+        fs::remove_file(old)?;
+    }
+
+    Ok(dest)
+}
+
+/// Backs up the database into `backup_dir`, or next to the database itself.
+pub fn backup_command(storage: &dyn Storage, backup_dir: Option<&Path>, keep: usize) -> Result<()> {
+    let db_path = storage.path();
+
+    if !db_path.exists() {
+        say!("❌ No database found at {}. Run 'fetch' first.", db_path.display());
+        return Ok(());
+    }
+
+    let db_dir = db_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let backup = backup_database(db_path, backup_dir.unwrap_or(db_dir), keep)?;
+
+    say!("🗄️  Backup written to {}", backup.display());
+
+    Ok(())
+}
+
+/// Gives the movies sequential IDs from 1, oldest YTS ID first, keeping each
+/// original YTS ID in `yts_id`. Already compacted movies are numbered by their
+/// `yts_id` again. The result is in database order, newest first.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{compact_ids, Movie};
+///
+/// let movie = |id, title: &str| Movie { id, title: title.into(), ..Default::default() };
+/// let movies = compact_ids(vec![movie(5120, "Dune"), movie(12, "Heat"), movie(830, "Alien")]);
+///
+/// let ids: Vec<(u32, u32, &str)> = movies.iter().map(|m| (m.id, m.yts_id, m.title.as_str())).collect();
+/// assert_eq!(ids, [(3, 5120, "Dune"), (2, 830, "Alien"), (1, 12, "Heat")]);
+/// assert_eq!(compact_ids(movies.clone())[0].yts_id, 5120);
+/// ```
+pub fn compact_ids(mut movies: Vec<Movie>) -> Vec<Movie> {
+    for movie in &mut movies {
+        if movie.yts_id == 0 {
+            movie.yts_id = movie.id;
+        }
+    }
+    movies.sort_by_key(|m| m.yts_id);
+    for (i, movie) in movies.iter_mut().enumerate() {
+        movie.id = i as u32 + 1;
+    }
+    movies.reverse();
+    movies
+}
+
+/// Undoes [`compact_ids`], making `id` the YTS ID again.
+pub fn restore_ids(mut movies: Vec<Movie>) -> Vec<Movie> {
+    for movie in &mut movies {
+        if movie.yts_id != 0 {
+            movie.id = std::mem::take(&mut movie.yts_id);
+        }
+    }
+    movies.sort_by_key(|m| Reverse(m.id));
+    movies
+}
+
+/// The movie with the YTS ID `yts_id`, whether or not its IDs are compacted.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{compact_ids, find_by_yts_id, Movie};
+///
+/// let movie = |id, title: &str| Movie { id, title: title.into(), ..Default::default() };
+/// let movies = vec![movie(5120, "Dune"), movie(12, "Heat")];
+/// assert_eq!(find_by_yts_id(&movies, 12).unwrap().title, "Heat");
+///
+/// let compacted = compact_ids(movies);
+/// assert_eq!(find_by_yts_id(&compacted, 12).unwrap().id, 1);
+/// assert_eq!(find_by_yts_id(&compacted, 5120).unwrap().title, "Dune");
+/// assert!(find_by_yts_id(&compacted, 2).is_none());
+/// ```
+pub fn find_by_yts_id(movies: &[Movie], yts_id: u32) -> Option<&Movie> {
+    movies.iter().find(|m| if m.yts_id == 0 { m.id == yts_id } else { m.yts_id == yts_id })
+}
+
+/// Fails if `movies` have compacted IDs, which must not be compared with the
+/// IDs YTS sends.
+pub(crate) fn ensure_yts_ids(movies: &[Movie]) -> Result<()> {
+    if movies.iter().any(|m| m.yts_id != 0) {
+        return Err(YtsError::CompactedIds);
+    }
+    Ok(())
+}
+
+/// `fix-ids`: compacts the IDs with `compact`, otherwise restores the YTS IDs.
+pub fn fix_ids_command(storage: &dyn Storage, compact: bool) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let count = movies.len();
+    if compact {
+        storage.save(&compact_ids(movies))?;
+        say!("✅ Renumbered {} movies 1-{}; the YTS IDs are kept as yts_id", count, count);
+        say!("   Run 'fix-ids --restore' before fetching again.");
+    } else if movies.iter().all(|m| m.yts_id == 0) {
+        say!("✅ Movie IDs are already YTS IDs ({} movies).", count);
+    } else {
+        storage.save(&restore_ids(movies))?;
+        say!("✅ Restored the YTS IDs of {} movies", count);
+    }
+
+    Ok(())
+}
+
+pub fn dedup_command(storage: &dyn Storage, backup: bool) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let original_count = movies.len();
+    let deduped = dedup_movies(movies);
+    let removed = original_count - deduped.len();
+
+    if removed == 0 {
+        say!("✅ No duplicate movies found ({} movies).", original_count);
+        return Ok(());
+    }
+
+    if backup {
+        let backup_path = timestamped_backup_path(storage.path());
+        fs::copy(storage.path(), &backup_path)?;
+        say!("🗄️  Backup written to {}", backup_path.display());
+    }
+
+    storage.save(&deduped)?;
+
+    say!("🧹 Removed {} duplicate movies", removed);
+    say!("✅ {} movies remaining in {}", deduped.len(), storage.path().display());
+
+    Ok(())
+}
+
+/// Splits `movies` into those released in or after `before_year` and those
+/// released earlier, keeping the original order in both.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{prune_movies, Movie};
+///
+/// // One movie a year from 2000 to 2019.
+/// let movies: Vec<Movie> = (2000..2020)
+///     .map(|year| Movie { id: year - 1999, year, ..Default::default() })
+///     .collect();
+///
+/// let (kept, removed) = prune_movies(movies.clone(), 2010);
+/// assert_eq!(kept.len(), 10);
+/// assert_eq!(removed.len(), 10);
+/// assert!(kept.iter().all(|m| m.year >= 2010));
+/// assert_eq!(removed.last().unwrap().year, 2009);
+///
+/// let (kept, removed) = prune_movies(movies.clone(), 2000);
+/// assert_eq!((kept.len(), removed.len()), (20, 0));
+///
+/// let (kept, removed) = prune_movies(movies, 2020);
+/// assert_eq!((kept.len(), removed.len()), (0, 20));
+/// ```
+pub fn prune_movies(movies: Vec<Movie>, before_year: u32) -> (Vec<Movie>, Vec<Movie>) {
+    movies.into_iter().partition(|m| m.year >= before_year)
+}
+
+/// Like [`prune_movies`], but compares the YTS upload time with `before`.
+/// Movies without an upload time are kept.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use yts_movie_scraper::{prune_movies_uploaded_before, Movie};
+///
+/// let uploaded = |id, year| Movie {
+///     id,
+///     date_uploaded_unix: Utc.with_ymd_and_hms(year, 6, 1, 0, 0, 0).unwrap().timestamp(),
+///     ..Default::default()
+/// };
+/// let movies = vec![uploaded(1, 2012), uploaded(2, 2021), Movie { id: 3, ..Default::default() }];
+///
+/// let cutoff = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+/// let (kept, removed) = prune_movies_uploaded_before(movies, cutoff);
+/// assert_eq!(kept.iter().map(|m| m.id).collect::<Vec<_>>(), [2, 3]);
+/// assert_eq!(removed.iter().map(|m| m.id).collect::<Vec<_>>(), [1]);
+/// ```
+pub fn prune_movies_uploaded_before(movies: Vec<Movie>, before: DateTime<Utc>) -> (Vec<Movie>, Vec<Movie>) {
+    let before = before.timestamp();
+    movies.into_iter().partition(|m| m.date_uploaded_unix == 0 || m.date_uploaded_unix >= before)
+}
+
+/// Which movies `prune` removes.
+pub enum PruneCutoff {
+    /// Released before this year.
+    Year(u32),
+    /// Uploaded to YTS before this time.
+    Uploaded(DateTime<Utc>),
+}
+
+pub fn prune_command(storage: &dyn Storage, cutoff: PruneCutoff, dry_run: bool) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let (kept, removed, description) = match cutoff {
+        PruneCutoff::Year(year) => {
+            let (kept, removed) = prune_movies(movies, year);
+            (kept, removed, format!("released before {}", year))
+        }
+        PruneCutoff::Uploaded(date) => {
+            let (kept, removed) = prune_movies_uploaded_before(movies, date);
+            (kept, removed, format!("uploaded before {}", date.format("%Y-%m-%d")))
+        }
+    };
+
+    if removed.is_empty() {
+        say!("✅ No movies {} ({} movies).", description, kept.len());
+        return Ok(());
+    }
+
+    if dry_run {
+        say!("🗑️  {} movies {} would be removed:", removed.len(), description);
+        for movie in &removed {
+            say!("  - [ID: {}] {} ({})", movie.id, movie.title, movie.year);
+        }
+        say!("\n🔎 Dry run: database left unchanged.");
+        return Ok(());
+    }
+
+    storage.save(&kept)?;
+
+    say!("🧹 Removed {} movies {}", removed.len(), description);
+    say!("✅ {} movies remaining in {}", kept.len(), storage.path().display());
+
+    Ok(())
+}
+
+/// A single integrity problem found by [`validate_database`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    DuplicateId { id: u32 },
+    EmptyTitle { id: u32 },
+    ZeroSizeTorrent { id: u32, quality: String },
+    EmptyHash { id: u32, quality: String },
+    InvalidMagnet { id: u32, quality: String },
+}
+
+impl ValidationError {
+    pub fn movie_id(&self) -> u32 {
+        match self {
+            ValidationError::DuplicateId { id }
+            | ValidationError::EmptyTitle { id }
+            | ValidationError::ZeroSizeTorrent { id, .. }
+            | ValidationError::EmptyHash { id, .. }
+            | ValidationError::InvalidMagnet { id, .. } => *id,
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::DuplicateId { .. } => write!(f, "duplicate movie id"),
+            ValidationError::EmptyTitle { .. } => write!(f, "empty title"),
+            ValidationError::ZeroSizeTorrent { quality, .. } => write!(f, "{} torrent has zero size_bytes", quality),
+            ValidationError::EmptyHash { quality, .. } => write!(f, "{} torrent has an empty hash", quality),
+            ValidationError::InvalidMagnet { quality, .. } => {
+                write!(f, "{} torrent magnet URL does not start with 'magnet:?'", quality)
+            }
+        }
+    }
+}
+
+/// Checks movies for duplicate ids, empty titles, and broken torrents.
+pub fn validate_database(movies: &[Movie]) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+
+    for movie in movies {
+        if !seen.insert(movie.id) {
+            errors.push(ValidationError::DuplicateId { id: movie.id });
+        }
+
+        if movie.title.trim().is_empty() {
+            errors.push(ValidationError::EmptyTitle { id: movie.id });
+        }
+
+        for torrent in &movie.torrents {
+            if torrent.size_bytes == 0 {
+                errors.push(ValidationError::ZeroSizeTorrent {
+                    id: movie.id,
+                    quality: torrent.label(),
+                });
+            }
+            if torrent.hash.is_empty() {
+                errors.push(ValidationError::EmptyHash {
+                    id: movie.id,
+                    quality: torrent.label(),
+                });
+            }
+            // Empty magnets come from `fetch --no-magnet` and are not an error.
+            if !torrent.magnet_url.is_empty() && !torrent.magnet_url.starts_with("magnet:?") {
+                errors.push(ValidationError::InvalidMagnet {
+                    id: movie.id,
+                    quality: torrent.label(),
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+/// Prints a violation report and returns the number of violations found.
+pub fn validate_command(storage: &dyn Storage) -> Result<usize> {
+    let movies = storage.load()?;
+    let errors = validate_database(&movies);
+
+    if errors.is_empty() {
+        say!("✅ {} movies checked, no problems found", movies.len());
+        return Ok(0);
+    }
+
+    say!("❌ Found {} problems in {} movies:\n", errors.len(), movies.len());
+    say!("{:<8} Problem", "ID");
+    say!("{}", "-".repeat(70));
+    for error in &errors {
+        say!("{:<8} {}", error.movie_id(), error);
+    }
+
+    Ok(errors.len())
+}
+
+/// Prints the movie owning torrent `hash`. Returns false if there is none.
+pub fn hash_lookup_command(storage: &dyn Storage, hash: &str) -> Result<bool> {
+    if !is_info_hash(&hash.trim().to_ascii_lowercase()) {
+        say_err!("❌ '{}' is not a torrent info hash (40 or 64 hex characters)", hash);
+        return Ok(false);
+    }
+
+    let movies = storage.load()?;
+    let Some((movie, torrent)) = find_by_hash(&movies, hash) else {
+        say_err!("❌ No torrent with hash {} found in database.", hash.trim());
+        return Ok(false);
+    };
+
+    say!("🔑 {} torrent of:\n", torrent.label());
+    say!("{}", format_movie_card(movie));
+
+    Ok(true)
+}
+
+/// Lists every torrent whose hash fails [`is_valid_sha1_hash`] and returns
+/// how many there are. With `ignore_case`, uppercase hashes (which YTS
+/// itself sends) are accepted too.
+pub fn hash_check_command(storage: &dyn Storage, ignore_case: bool) -> Result<usize> {
+    let movies = storage.load()?;
+    let is_valid = |hash: &str| {
+        if ignore_case {
+            is_valid_sha1_hash(&hash.to_ascii_lowercase())
+        } else {
+            is_valid_sha1_hash(hash)
+        }
+    };
+
+    let bad: Vec<(&Movie, &Torrent)> = movies
+        .iter()
+        .flat_map(|m| m.torrents.iter().map(move |t| (m, t)))
+        .filter(|(_, t)| !is_valid(&t.hash))
+        .collect();
+    let torrent_count: usize = movies.iter().map(|m| m.torrents.len()).sum();
+
+    if bad.is_empty() {
+        say!("✅ {} torrent hashes checked, all valid", torrent_count);
+        return Ok(0);
+    }
+
+    say!("❌ Found {} malformed hashes in {} torrents:\n", bad.len(), torrent_count);
+    say!("{:<8} {:<40} {:<14} Hash", "ID", "Title", "Quality");
+    say!("{}", "-".repeat(108));
+    for (movie, torrent) in &bad {
+        let hash = if torrent.hash.is_empty() { "(empty)" } else { &torrent.hash };
+        say!("{:<8} {:<40} {:<14} {}", movie.id, truncate_chars(&movie.title, 40), torrent.label(), hash);
+    }
+    if !ignore_case && bad.iter().all(|(_, t)| is_valid_sha1_hash(&t.hash.to_ascii_lowercase())) {
+        say!("\nℹ️  All of these are only uppercase; pass --ignore-case to accept them.");
+    }
+
+    Ok(bad.len())
+}
+
+/// `regenerate-magnets`: rebuilds and saves every magnet link. Returns how
+/// many were rebuilt.
+pub fn regenerate_magnets_command(storage: &dyn Storage, trackers: &[String]) -> Result<usize> {
+    let mut movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(0);
+    }
+
+    let updated = regenerate_magnets(&mut movies, trackers);
+    storage.save(&movies)?;
+
+    say!(
+        "🧲 Regenerated {} magnet links for {} movies using {} trackers",
+        updated,
+        movies.len(),
+        trackers.len()
+    );
+
+    Ok(updated)
+}
+
+/// Clears `field` on every movie.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{strip_field, Movie, StripField, Torrent};
+///
+/// let mut movies = vec![Movie {
+///     large_cover_image: "https://img.test/l.jpg".into(),
+///     medium_cover_image: "https://img.test/m.jpg".into(),
+///     small_cover_image: "https://img.test/s.jpg".into(),
+///     backdrop_url: Some("https://img.test/bd.jpg".into()),
+///     plot: Some("A heist goes wrong.".into()),
+///     torrents: vec![Torrent {
+///         hash: "AAA".into(),
+///         magnet_url: "magnet:?xt=urn:btih:AAA".into(),
+///         ..Default::default()
+///     }],
+///     ..Default::default()
+/// }];
+///
+/// strip_field(&mut movies, StripField::Magnets);
+/// assert_eq!(movies[0].torrents[0].magnet_url, "");
+/// assert_eq!(movies[0].torrents[0].hash, "AAA");
+/// assert!(movies[0].plot.is_some());
+///
+/// strip_field(&mut movies, StripField::CoverUrls);
+/// assert_eq!(movies[0].large_cover_image, "");
+/// assert_eq!(movies[0].medium_cover_image, "");
+/// assert_eq!(movies[0].small_cover_image, "");
+/// assert_eq!(movies[0].backdrop_url, None);
+///
+/// strip_field(&mut movies, StripField::Plot);
+/// assert_eq!(movies[0].plot, None);
+/// ```
+pub fn strip_field(movies: &mut [Movie], field: StripField) {
+    for movie in movies {
+        match field {
+            StripField::Magnets => movie.torrents.iter_mut().for_each(|t| t.magnet_url.clear()),
+            StripField::CoverUrls => {
+                movie.large_cover_image.clear();
+                movie.medium_cover_image.clear();
+                movie.small_cover_image.clear();
+                movie.backdrop_url = None;
+            }
+            StripField::Plot => movie.plot = None,
+        }
+    }
+}
+
+/// Strips `fields` from the database and saves it to `dest`, or in place.
+pub fn strip_command(storage: &dyn Storage, fields: &[StripField], dest: Option<&dyn Storage>) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let before = fs::metadata(storage.path())?.len();
+    for &field in fields {
+        strip_field(&mut movies, field);
+    }
+    let dest = dest.unwrap_or(storage);
+    dest.save(&movies)?;
+    let after = fs::metadata(dest.path())?.len();
+
+    say!("✂️  Stripped {} movies", movies.len());
+    say!("   {}: {}", storage.path().display(), format_size(before));
+    say!(
+        "   {}: {} ({:.0}% smaller)",
+        dest.path().display(),
+        format_size(after),
+        100.0 - after as f64 * 100.0 / before.max(1) as f64
+    );
+
+    Ok(())
+}
+
+/// Reports movies whose magnets use trackers from [`DEAD_TRACKERS`]. With
+/// `fix`, those magnets are rebuilt from the healthy entries of `trackers`.
+pub fn lint_command(storage: &dyn Storage, trackers: &[String], fix: bool, limit: usize) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let mut dead_counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut affected: Vec<(usize, Vec<String>)> = Vec::new();
+    for (index, movie) in movies.iter().enumerate() {
+        let mut dead: Vec<String> = movie
+            .torrents
+            .iter()
+            .flat_map(|t| find_dead_trackers(&t.magnet_url, DEAD_TRACKERS))
+            .collect();
+        dead.sort();
+        dead.dedup();
+        if dead.is_empty() {
+            continue;
+        }
+        for tracker in &dead {
+            *dead_counts.entry(tracker.clone()).or_default() += 1;
+        }
+        affected.push((index, dead));
+    }
+
+    if affected.is_empty() {
+        say!("✅ {} movies checked, no dead trackers found", movies.len());
+        return Ok(());
+    }
+
+    say!("⚠️  {} of {} movies use dead trackers:\n", affected.len(), movies.len());
+    for (tracker, count) in &dead_counts {
+        say!("  {:<50} {} movies", tracker, count);
+    }
+
+    let display_count = if limit == 0 { affected.len() } else { limit.min(affected.len()) };
+    say!("\n{:<8} {:<50} Dead trackers", "ID", "Title");
+    say!("{}", "-".repeat(80));
+    for (index, dead) in affected.iter().take(display_count) {
+        let movie = &movies[*index];
+        say!("{:<8} {:<50} {}", movie.id, truncate_chars(&movie.title, 50), dead.len());
+    }
+    if display_count < affected.len() {
+        say!("... and {} more (use --limit 0 to show all)", affected.len() - display_count);
+    }
+
+    if !fix {
+        say!("\nRun 'lint --fix' to rebuild these magnets without the dead trackers.");
+        return Ok(());
+    }
+
+    let healthy: Vec<String> = trackers.iter().filter(|t| !is_dead_tracker(t, DEAD_TRACKERS)).cloned().collect();
+    if healthy.is_empty() {
+        return Err(YtsError::ConfigError("every configured tracker is dead; add working ones to the config file".to_string()));
+    }
+
+    for (index, _) in &affected {
+        let movie = &mut movies[*index];
+        for torrent in &mut movie.torrents {
+            torrent.magnet_url = create_magnet_url(&torrent.hash, &movie.title, &healthy);
+        }
+    }
+    storage.save(&movies)?;
+
+    say!("\n🧲 Rebuilt magnets of {} movies using {} healthy trackers", affected.len(), healthy.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{json_storage, movie, torrent};
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> =
+            fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// A database in `dir` with backups taken on each of `days` of January 2020.
+    fn database_with_backups(dir: &Path, days: &[u32]) -> PathBuf {
+        let source = dir.join("yts_movies.json");
+        fs::write(&source, "[]").unwrap();
+        for day in days {
+            fs::write(dir.join(format!("yts_movies_202001{:02}_120000.json", day)), "[]").unwrap();
+        }
+        source
+    }
+
+    #[test]
+    fn backup_prunes_all_but_the_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = database_with_backups(dir.path(), &[3, 1, 4, 2]);
+        // Neither are backups of this database.
+        fs::write(dir.path().join("yts_movies_latest.json"), "[]").unwrap();
+        fs::write(dir.path().join("action_20200105_120000.json"), "[]").unwrap();
+
+        let backup = backup_database(&source, dir.path(), 3).unwrap();
+
+        let mut expected = vec![
+            "action_20200105_120000.json".to_string(),
+            "yts_movies.json".to_string(),
+            "yts_movies_20200103_120000.json".to_string(),
+            "yts_movies_20200104_120000.json".to_string(),
+            "yts_movies_latest.json".to_string(),
+            backup.file_name().unwrap().to_string_lossy().into_owned(),
+        ];
+        expected.sort_unstable();
+        assert_eq!(file_names(dir.path()), expected);
+    }
+
+    #[test]
+    fn backup_always_keeps_the_new_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = database_with_backups(dir.path(), &[1, 2]);
+        let backup_dir = dir.path().join("backups");
+
+        let backup = backup_database(&source, &backup_dir, 0).unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "[]");
+        assert_eq!(file_names(&backup_dir).len(), 1);
+
+        // Backups outside the backup directory are left alone.
+        assert_eq!(
+            list_backups(&source, dir.path()).unwrap(),
+            [dir.path().join("yts_movies_20200102_120000.json"), dir.path().join("yts_movies_20200101_120000.json")]
+        );
+    }
+
+    #[test]
+    fn a_clean_database_validates() {
+        assert!(validate_database(&[movie(2, "Heat", 1995), movie(1, "Alien", 1979)]).is_empty());
+    }
+
+    #[test]
+    fn validate_reports_duplicate_ids_once_per_repeat() {
+        let movies = [movie(1, "Alien", 1979), movie(1, "Alien", 1979), movie(1, "Alien", 1979)];
+
+        assert_eq!(
+            validate_database(&movies),
+            [ValidationError::DuplicateId { id: 1 }, ValidationError::DuplicateId { id: 1 }]
+        );
+    }
+
+    #[test]
+    fn validate_reports_blank_titles() {
+        let movies = [movie(1, "", 1979), movie(2, "   ", 1995)];
+
+        assert_eq!(
+            validate_database(&movies),
+            [ValidationError::EmptyTitle { id: 1 }, ValidationError::EmptyTitle { id: 2 }]
+        );
+    }
+
+    #[test]
+    fn validate_reports_each_broken_torrent() {
+        let mut heat = movie(2, "Heat", 1995);
+        heat.torrents = vec![
+            Torrent { size_bytes: 0, ..torrent("720p", 1) },
+            Torrent {
+                hash: String::new(),
+                torrent_type: "web".to_string(),
+                ..torrent("1080p", 2)
+            },
+            Torrent {
+                magnet_url: "http://example.com/heat.torrent".to_string(),
+                ..torrent("2160p", 3)
+            },
+            Torrent { magnet_url: String::new(), ..torrent("3D", 4) },
+        ];
+
+        assert_eq!(
+            validate_database(&[heat]),
+            [
+                ValidationError::ZeroSizeTorrent { id: 2, quality: "720p".to_string() },
+                ValidationError::EmptyHash { id: 2, quality: "1080p-web".to_string() },
+                ValidationError::InvalidMagnet { id: 2, quality: "2160p".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn validation_errors_name_the_problem() {
+        let error = ValidationError::ZeroSizeTorrent { id: 7, quality: "720p".to_string() };
+
+        assert_eq!(error.movie_id(), 7);
+        assert_eq!(error.to_string(), "720p torrent has zero size_bytes");
+    }
+
+    #[test]
+    fn regenerate_magnets_rebuilds_empty_and_stale_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut heat = movie(2, "Heat Wave", 1995);
+        heat.torrents = vec![torrent("1080p", 10), torrent("720p", 11)];
+        heat.torrents[0].magnet_url.clear();
+        heat.torrents[1].magnet_url = "magnet:?xt=urn:btih:OLD&tr=udp://tracker.coppersurfer.tk:6969".to_string();
+        let storage = json_storage(dir.path(), &[heat, movie(1, "Alien", 1979)]);
+        let trackers = ["udp://a.test:1337/announce".to_string(), "udp://b.test:80".to_string()];
+
+        assert_eq!(regenerate_magnets_command(&storage, &trackers).unwrap(), 3);
+
+        let magnets: Vec<String> =
+            storage.load().unwrap().into_iter().flat_map(|m| m.torrents).map(|t| t.magnet_url).collect();
+        let expected = |hash: &str, title: &str| {
+            format!("magnet:?xt=urn:btih:{}&dn={}&tr=udp://a.test:1337/announce&tr=udp://b.test:80", hash, title)
+        };
+        assert_eq!(
+            magnets,
+            [
+                expected(&format!("{:040X}", 10), "Heat+Wave"),
+                expected(&format!("{:040X}", 11), "Heat+Wave"),
+                expected(&format!("{:040X}", 1_000_000_001), "Alien"),
+            ]
+        );
+    }
+
+    #[test]
+    fn regenerating_an_empty_database_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+        assert_eq!(regenerate_magnets_command(&storage, &[]).unwrap(), 0);
+    }
+}
@@ -0,0 +1,19 @@
+//! The subcommands that work on the local database.
+
+mod files;
+mod images;
+mod library;
+mod list;
+mod magnets;
+mod maintenance;
+mod picks;
+mod stats;
+
+pub use files::*;
+pub use images::*;
+pub use library::*;
+pub use list::*;
+pub use magnets::*;
+pub use maintenance::*;
+pub use picks::*;
+pub use stats::*;
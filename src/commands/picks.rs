@@ -0,0 +1,535 @@
+//! `top`, `recent`, `random`, `top-seeders` and `seeding`.
+
+use chrono::Utc;
+use clap::Args;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::{
+    format_movie_card, genre_matches, print_movie_table, quality_matches, truncate_chars, Movie, Result, Storage,
+    Torrent,
+};
+
+#[derive(Args, Debug)]
+pub struct TopOpts {
+    /// Number of movies to show (0 = all)
+    #[arg(short, long, default_value_t = 10)]
+    pub count: usize,
+
+    /// Earliest release year (inclusive)
+    #[arg(long)]
+    pub min_year: Option<u32>,
+
+    /// Latest release year (inclusive)
+    #[arg(long)]
+    pub max_year: Option<u32>,
+
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Torrent quality the movie must have, e.g. "2160p"
+    #[arg(short, long)]
+    pub quality: Option<String>,
+}
+
+impl TopOpts {
+    pub fn matches(&self, movie: &Movie) -> bool {
+        self.min_year.is_none_or(|y| movie.year >= y)
+            && self.max_year.is_none_or(|y| movie.year <= y)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+            && self
+                .quality
+                .as_deref()
+                .is_none_or(|q| movie.torrents.iter().any(|t| quality_matches(&t.label(), q)))
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct RecentOpts {
+    /// How many days back to look
+    #[arg(short, long, default_value_t = 7)]
+    pub days: u32,
+
+    /// Minimum IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+
+    /// Torrent quality the movie must have, e.g. "2160p"
+    #[arg(short, long)]
+    pub quality: Option<String>,
+
+    /// Number of movies to display (0 = all)
+    #[arg(short, long, default_value_t = 10)]
+    pub limit: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct RandomOpts {
+    /// How many movies to pick
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub count: usize,
+
+    /// Minimum IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Release year
+    #[arg(short, long)]
+    pub year: Option<u32>,
+
+    /// Torrent quality the movie must have, e.g. "2160p"
+    #[arg(short, long)]
+    pub quality: Option<String>,
+
+    /// Seed the random generator, so the same database always gives the same picks
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl RandomOpts {
+    pub fn matches(&self, movie: &Movie) -> bool {
+        self.min_rating.is_none_or(|r| movie.rating >= r)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+            && self.year.is_none_or(|y| movie.year == y)
+            && self
+                .quality
+                .as_deref()
+                .is_none_or(|q| movie.torrents.iter().any(|t| quality_matches(&t.label(), q)))
+    }
+}
+
+/// Movies uploaded within `opts.days` days before `now` (a Unix timestamp)
+/// that pass the rating and quality filters, in database order.
+pub fn recent_movies<'a>(movies: &'a [Movie], opts: &RecentOpts, now: i64) -> Vec<&'a Movie> {
+    let cutoff = now - opts.days as i64 * 86_400;
+
+    movies
+        .iter()
+        .filter(|m| m.date_uploaded_unix >= cutoff)
+        .filter(|m| opts.min_rating.is_none_or(|r| m.rating >= r))
+        .filter(|m| {
+            opts.quality
+                .as_deref()
+                .is_none_or(|q| m.torrents.iter().any(|t| quality_matches(&t.label(), q)))
+        })
+        .collect()
+}
+
+/// Picks up to `opts.count` distinct movies uniformly from those matching the
+/// filters. With `opts.seed` set the picks only depend on the database.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{random_movies, Movie, RandomOpts};
+///
+/// let movies: Vec<Movie> = (1..=50)
+///     .map(|id| Movie { id, year: 2000 + id % 5, ..Default::default() })
+///     .collect();
+/// let opts = RandomOpts { count: 1, min_rating: None, genre: None, year: Some(2003), quality: None, seed: Some(42) };
+///
+/// let first = random_movies(&movies, &opts);
+/// assert_eq!(first.len(), 1);
+/// assert_eq!(first[0].year, 2003);
+/// for _ in 0..10 {
+///     assert_eq!(random_movies(&movies, &opts)[0].id, first[0].id);
+/// }
+/// ```
+pub fn random_movies<'a>(movies: &'a [Movie], opts: &RandomOpts) -> Vec<&'a Movie> {
+    let matches: Vec<&Movie> = movies.iter().filter(|m| opts.matches(m)).collect();
+
+    match opts.seed {
+        Some(seed) => matches.choose_multiple(&mut StdRng::seed_from_u64(seed), opts.count).copied().collect(),
+        None => matches.choose_multiple(&mut rand::thread_rng(), opts.count).copied().collect(),
+    }
+}
+
+pub fn random_command(storage: &dyn Storage, opts: &RandomOpts) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let picked = random_movies(&movies, opts);
+
+    if picked.is_empty() {
+        say!("🔍 No movies match the given filters.");
+        return Ok(());
+    }
+
+    if picked.len() < opts.count {
+        say!("ℹ️  Only {} movies match the given filters, showing all of them\n", picked.len());
+    }
+
+    let cards: Vec<String> = picked.iter().map(|m| format_movie_card(m)).collect();
+    say!("{}", cards.join("\n\n"));
+
+    Ok(())
+}
+
+pub fn recent_command(storage: &dyn Storage, opts: &RecentOpts) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    if movies.iter().all(|m| m.date_uploaded_unix == 0) {
+        say!("❌ This database has no upload dates (it was created by an older version).");
+        say!("   Delete {} and run 'fetch' again to store them.", storage.path().display());
+        return Ok(());
+    }
+
+    let recent = recent_movies(&movies, opts, Utc::now().timestamp());
+
+    if recent.is_empty() {
+        say!("🔍 No movies uploaded in the last {} days match the given filters.", opts.days);
+        return Ok(());
+    }
+
+    say!("🆕 Movies uploaded in the last {} days\n", opts.days);
+    print_movie_table(&recent, opts.limit)
+}
+
+/// Highest-rated movies matching `opts`. Ties are broken by year (newest
+/// first) and then title.
+pub fn top_movies<'a>(movies: &'a [Movie], opts: &TopOpts) -> Vec<&'a Movie> {
+    let mut ranked: Vec<&Movie> = movies.iter().filter(|m| opts.matches(m)).collect();
+    ranked.sort_by(|a, b| {
+        b.rating
+            .total_cmp(&a.rating)
+            .then_with(|| b.year.cmp(&a.year))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+
+    if opts.count > 0 {
+        ranked.truncate(opts.count);
+    }
+    ranked
+}
+
+pub fn top_command(storage: &dyn Storage, opts: &TopOpts) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let ranked = top_movies(&movies, opts);
+
+    if ranked.is_empty() {
+        say!("🔍 No movies match the given filters.");
+        return Ok(());
+    }
+
+    say!("🏆 Top {} movies by IMDb rating\n", ranked.len());
+    for (rank, movie) in ranked.iter().enumerate() {
+        say!("{:>3}. ⭐ {:.1}  {} ({}) [ID: {}]", rank + 1, movie.rating, movie.title, movie.year, movie.id);
+    }
+
+    Ok(())
+}
+
+/// The torrent of `movie` with the most seeds, or the most peers among
+/// equally seeded ones. `None` for a movie without torrents.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{best_seeded_torrent, Movie, Torrent};
+///
+/// let torrent = |quality: &str, torrent_type: &str, seeds, peers| Torrent {
+///     quality: quality.into(),
+///     torrent_type: torrent_type.into(),
+///     seeds,
+///     peers,
+///     ..Default::default()
+/// };
+/// let movie = Movie {
+///     torrents: vec![
+///         torrent("1080p", "web", 12, 3),
+///         torrent("1080p", "bluray", 31, 2),
+///         torrent("720p", "web", 31, 9),
+///         torrent("720p", "bluray", 4, 40),
+///     ],
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(best_seeded_torrent(&movie).unwrap().label(), "720p-web");
+/// assert!(best_seeded_torrent(&Movie::default()).is_none());
+/// ```
+pub fn best_seeded_torrent(movie: &Movie) -> Option<&Torrent> {
+    movie.torrents.iter().max_by_key(|t| (t.seeds, t.peers))
+}
+
+/// Most seeded movies first with the torrent they rank by, limited to
+/// torrents matching `quality` and movies listed under `genre`.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{top_seeders, Movie, Torrent};
+///
+/// let torrent = |quality: &str, seeds| Torrent { quality: quality.into(), seeds, ..Default::default() };
+/// let movie = |id, genre: &str, torrents| Movie { id, genres: vec![genre.into()], torrents, ..Default::default() };
+/// let movies = vec![
+///     movie(1, "Crime", vec![torrent("720p", 50), torrent("1080p", 10), torrent("1080p", 20)]),
+///     movie(2, "Drama", vec![torrent("1080p", 30)]),
+///     movie(3, "Crime", vec![torrent("2160p", 5)]),
+/// ];
+/// let ranked = |quality, genre| -> Vec<(u32, u32)> {
+///     top_seeders(&movies, quality, genre).iter().map(|(m, t)| (m.id, t.seeds)).collect()
+/// };
+///
+/// assert_eq!(ranked(None, None), [(1, 50), (2, 30), (3, 5)]);
+/// assert_eq!(ranked(Some("1080p"), None), [(2, 30), (1, 20)]);
+/// assert_eq!(ranked(None, Some("crime")), [(1, 50), (3, 5)]);
+/// ```
+pub fn top_seeders<'a>(
+    movies: &'a [Movie],
+    quality: Option<&str>,
+    genre: Option<&str>,
+) -> Vec<(&'a Movie, &'a Torrent)> {
+    let mut ranked: Vec<(&Movie, &Torrent)> = movies
+        .iter()
+        .filter(|m| genre.is_none_or(|g| genre_matches(&m.genres, g)))
+        .filter_map(|m| {
+            let torrent = match quality {
+                Some(q) => m
+                    .torrents
+                    .iter()
+                    .filter(|t| quality_matches(&t.label(), q))
+                    .max_by_key(|t| (t.seeds, t.peers)),
+                None => best_seeded_torrent(m),
+            };
+            torrent.map(|t| (m, t))
+        })
+        .collect();
+    ranked.sort_by(|(a, ta), (b, tb)| tb.seeds.cmp(&ta.seeds).then_with(|| a.title.cmp(&b.title)));
+    ranked
+}
+
+pub fn top_seeders_command(
+    storage: &dyn Storage,
+    count: usize,
+    quality: Option<&str>,
+    genre: Option<&str>,
+) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let ranked = top_seeders(&movies, quality, genre);
+
+    if ranked.is_empty() {
+        say!("🔍 No movies match the given filters.");
+        return Ok(());
+    }
+
+    let shown = if count == 0 { ranked.len() } else { count.min(ranked.len()) };
+    say!("🌱 Top {} of {} movies by seeds (counts as of the last fetch)\n", shown, ranked.len());
+    say!("{:>3}  {:<40} {:>4}  {:<14} {:>6} {:>6}", "#", "Title", "Year", "Quality", "Seeds", "Peers");
+    for (rank, (movie, torrent)) in ranked.iter().take(shown).enumerate() {
+        say!(
+            "{:>3}. {:<40} {:>4}  {:<14} {:>6} {:>6}",
+            rank + 1,
+            truncate_chars(&movie.title, 40),
+            movie.year,
+            torrent.label(),
+            torrent.seeds,
+            torrent.peers
+        );
+    }
+
+    Ok(())
+}
+
+/// Each movie's best-seeded torrent with at least `min_seeds` seeds, most
+/// seeded first. Torrents stored before seed counts were recorded have 0.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{best_seeded, Movie, Torrent};
+///
+/// let torrent = |quality: &str, seeds| Torrent { quality: quality.into(), seeds, ..Default::default() };
+/// let movies = vec![
+///     Movie { id: 1, title: "Heat".into(), torrents: vec![torrent("720p", 5), torrent("1080p", 40)], ..Default::default() },
+///     Movie { id: 2, title: "Dune".into(), torrents: vec![torrent("2160p", 90)], ..Default::default() },
+///     Movie { id: 3, title: "Thief".into(), torrents: vec![torrent("720p", 2)], ..Default::default() },
+/// ];
+///
+/// let seeded: Vec<(u32, &str, u32)> =
+///     best_seeded(&movies, 10).iter().map(|(m, t)| (m.id, t.quality.as_str(), t.seeds)).collect();
+/// assert_eq!(seeded, [(2, "2160p", 90), (1, "1080p", 40)]);
+///
+/// // Databases written before seeds and peers were stored still load.
+/// let old: Torrent = serde_json::from_str(
+///     r#"{"quality": "1080p", "hash": "ABC", "size_bytes": 1, "magnet_url": ""}"#,
+/// ).unwrap();
+/// assert_eq!((old.seeds, old.peers), (0, 0));
+/// ```
+pub fn best_seeded(movies: &[Movie], min_seeds: u32) -> Vec<(&Movie, &Torrent)> {
+    let mut seeded: Vec<(&Movie, &Torrent)> = movies
+        .iter()
+        .filter_map(|m| best_seeded_torrent(m).map(|t| (m, t)))
+        .filter(|(_, t)| t.seeds >= min_seeds)
+        .collect();
+    seeded.sort_by(|(a, ta), (b, tb)| tb.seeds.cmp(&ta.seeds).then_with(|| a.title.cmp(&b.title)));
+    seeded
+}
+
+pub fn seeding_command(storage: &dyn Storage, min_seeds: u32, limit: usize) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let seeded = best_seeded(&movies, min_seeds);
+
+    if seeded.is_empty() {
+        say!("🔍 No movies have a torrent with at least {} seeds.", min_seeds);
+        say!("   Movies fetched before seed counts were stored show 0 seeds.");
+        return Ok(());
+    }
+
+    let shown = if limit == 0 { seeded.len() } else { limit.min(seeded.len()) };
+    say!("🌱 {} movies with at least {} seeds (counts as of the last fetch)\n", seeded.len(), min_seeds);
+    for (rank, (movie, torrent)) in seeded.iter().take(shown).enumerate() {
+        say!(
+            "{:>3}. {:>6} seeds {:>6} peers  {:<12} {} ({}) [ID: {}]",
+            rank + 1,
+            torrent.seeds,
+            torrent.peers,
+            torrent.label(),
+            movie.title,
+            movie.year,
+            movie.id
+        );
+    }
+    if shown < seeded.len() {
+        say!("\n… and {} more (use --limit 0 to show all)", seeded.len() - shown);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{movie, torrent};
+
+    fn ids(movies: &[&Movie]) -> Vec<u32> {
+        movies.iter().map(|m| m.id).collect()
+    }
+
+    /// Twenty movies rated 5 to 8 over three years, so the ranking needs
+    /// every tie-breaker.
+    fn ranked_library() -> Vec<Movie> {
+        (1..=20)
+            .map(|id| Movie {
+                rating: 5.0 + (id % 4) as f32,
+                ..movie(id, &format!("Movie {:02}", id), 1990 + id % 3)
+            })
+            .collect()
+    }
+
+    fn top(count: usize) -> TopOpts {
+        TopOpts { count, min_year: None, max_year: None, genre: None, quality: None }
+    }
+
+    #[test]
+    fn top_movies_break_rating_ties_by_year_then_title() {
+        let movies = ranked_library();
+        // Rated 8: 11 (1992), 07 and 19 (1991), 03 and 15 (1990).
+        assert_eq!(ids(&top_movies(&movies, &top(5))), [11, 7, 19, 3, 15]);
+        // Then rated 7: 02 and 14 (1992), 10 (1991), 06 and 18 (1990).
+        assert_eq!(ids(&top_movies(&movies, &top(10))[5..]), [2, 14, 10, 6, 18]);
+    }
+
+    #[test]
+    fn top_movies_return_at_most_count_movies() {
+        let movies = ranked_library();
+        assert_eq!(ids(&top_movies(&movies, &top(3))), [11, 7, 19]);
+        assert_eq!(top_movies(&movies, &top(0)).len(), 20);
+        assert_eq!(top_movies(&movies, &top(50)).len(), 20);
+    }
+
+    #[test]
+    fn top_movies_only_rank_matching_movies() {
+        let movies = ranked_library();
+        let opts = TopOpts { min_year: Some(1991), max_year: Some(1991), ..top(3) };
+        assert_eq!(ids(&top_movies(&movies, &opts)), [7, 19, 10]);
+    }
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 86_400;
+
+    /// Movies uploaded 0, 1, 7 and 8 days before `NOW`, plus one uploaded a
+    /// second too early to be within seven days.
+    fn uploads() -> Vec<Movie> {
+        let uploaded = |id, age, rating, quality| Movie {
+            date_uploaded_unix: NOW - age,
+            rating,
+            torrents: vec![torrent(quality, id as u64)],
+            ..movie(id, &format!("Movie {}", id), 2020)
+        };
+        vec![
+            uploaded(1, 0, 6.0, "1080p"),
+            uploaded(2, DAY, 8.0, "2160p"),
+            uploaded(3, 7 * DAY, 7.5, "1080p"),
+            uploaded(4, 7 * DAY + 1, 9.0, "1080p"),
+            uploaded(5, 8 * DAY, 9.0, "2160p"),
+        ]
+    }
+
+    fn recent(days: u32) -> RecentOpts {
+        RecentOpts { days, min_rating: None, quality: None, limit: 10 }
+    }
+
+    #[test]
+    fn recent_movies_include_the_boundary_day() {
+        let movies = uploads();
+        assert_eq!(ids(&recent_movies(&movies, &recent(7), NOW)), [1, 2, 3]);
+        assert_eq!(ids(&recent_movies(&movies, &recent(1), NOW)), [1, 2]);
+        assert_eq!(ids(&recent_movies(&movies, &recent(8), NOW)), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn zero_days_only_include_movies_uploaded_now() {
+        let movies = uploads();
+        assert_eq!(ids(&recent_movies(&movies, &recent(0), NOW)), [1]);
+        assert!(recent_movies(&movies, &recent(0), NOW + 1).is_empty());
+    }
+
+    #[test]
+    fn recent_movies_pass_the_rating_and_quality_filters() {
+        let movies = uploads();
+        let rated = RecentOpts { min_rating: Some(7.5), ..recent(7) };
+        assert_eq!(ids(&recent_movies(&movies, &rated, NOW)), [2, 3]);
+        let uhd = RecentOpts { quality: Some("2160p".to_string()), ..recent(30) };
+        assert_eq!(ids(&recent_movies(&movies, &uhd, NOW)), [2, 5]);
+        let both = RecentOpts { min_rating: Some(8.5), ..uhd };
+        assert_eq!(ids(&recent_movies(&movies, &both, NOW)), [5]);
+    }
+}
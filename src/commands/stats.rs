@@ -0,0 +1,666 @@
+//! Summaries of the local database: `stats`, `summary`, `count`, `size` and `plot`.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+    format_elapsed_at, format_size, genre_matches, largest_torrent_size, load_meta, quality_matches, DatabaseStats,
+    FetchMeta, Movie, Result, Storage,
+};
+
+#[derive(Args, Debug, Default)]
+pub struct CountOpts {
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Release year
+    #[arg(short, long)]
+    pub year: Option<u32>,
+
+    /// Minimum IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+
+    /// Torrent quality the movie must have, e.g. "2160p"
+    #[arg(short, long)]
+    pub quality: Option<String>,
+
+    /// Count the distinct values of this field among the matching movies instead
+    #[arg(short, long, value_enum)]
+    pub field: Option<CountField>,
+}
+
+impl CountOpts {
+    pub fn matches(&self, movie: &Movie) -> bool {
+        self.min_rating.is_none_or(|r| movie.rating >= r)
+            && self.genre.as_deref().is_none_or(|g| genre_matches(&movie.genres, g))
+            && self.year.is_none_or(|y| movie.year == y)
+            && self
+                .quality
+                .as_deref()
+                .is_none_or(|q| movie.torrents.iter().any(|t| quality_matches(&t.label(), q)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CountField {
+    Genre,
+    Year,
+    /// Torrent quality, e.g. "1080p"
+    Quality,
+    Director,
+}
+
+/// Template used by `summary` unless `--format` is given.
+pub const DEFAULT_SUMMARY_FORMAT: &str = "YTS: {count} movies | last sync: {last_sync} | {new_estimate} new";
+
+/// What `summary` reports, taken from the [`FetchMeta`] file alone. Every
+/// field is `None` before the first fetch.
+#[derive(Debug, Default, Serialize)]
+pub struct DbSummary {
+    /// Movies in the database at the last sync.
+    pub count: Option<u32>,
+    pub last_sync: Option<DateTime<Utc>>,
+    /// Movies YTS listed at the last sync that the database did not have.
+    pub new_estimate: Option<u32>,
+}
+
+impl DbSummary {
+    pub fn from_meta(meta: Option<&FetchMeta>) -> Self {
+        let Some(meta) = meta else {
+            return DbSummary::default();
+        };
+        DbSummary {
+            count: Some(meta.movie_count),
+            last_sync: Some(meta.last_fetched_at),
+            new_estimate: meta.total_count.map(|total| total.saturating_sub(meta.movie_count)),
+        }
+    }
+
+    /// Replaces `{count}`, `{last_sync}` and `{new_estimate}` in `template`.
+    /// Unknown values become "?", and an unknown sync time "never".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use yts_movie_scraper::{DbSummary, DEFAULT_SUMMARY_FORMAT};
+    ///
+    /// let now = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+    /// let summary = DbSummary {
+    ///     count: Some(52143),
+    ///     last_sync: Some(Utc.with_ymd_and_hms(2026, 1, 5, 10, 0, 0).unwrap()),
+    ///     new_estimate: Some(3),
+    /// };
+    /// assert_eq!(summary.render(DEFAULT_SUMMARY_FORMAT, now), "YTS: 52143 movies | last sync: 2h 0m ago | 3 new");
+    /// assert_eq!(summary.render("🎬 {count}/{count}", now), "🎬 52143/52143");
+    /// assert_eq!(summary.render("no placeholders", now), "no placeholders");
+    ///
+    /// // Metadata written before total counts were stored.
+    /// let summary = DbSummary { new_estimate: None, ..summary };
+    /// assert_eq!(summary.render("{count} (+{new_estimate})", now), "52143 (+?)");
+    ///
+    /// // No fetch yet.
+    /// assert_eq!(
+    ///     DbSummary::default().render(DEFAULT_SUMMARY_FORMAT, now),
+    ///     "YTS: ? movies | last sync: never | ? new"
+    /// );
+    /// ```
+    pub fn render(&self, template: &str, now: DateTime<Utc>) -> String {
+        let or_unknown = |value: Option<u32>| value.map_or_else(|| "?".to_string(), |v| v.to_string());
+        let last_sync = self.last_sync.map_or_else(|| "never".to_string(), |t| format_elapsed_at(t, now));
+
+        template
+            .replace("{count}", &or_unknown(self.count))
+            .replace("{last_sync}", &last_sync)
+            .replace("{new_estimate}", &or_unknown(self.new_estimate))
+    }
+}
+
+/// Prints one line for status bars, without touching the network.
+pub fn summary_command(storage: &dyn Storage, format: &str, json: bool) -> Result<()> {
+    let meta = load_meta(storage.path())?;
+    let summary = DbSummary::from_meta(meta.as_ref());
+
+    if json {
+        say!("{}", serde_json::to_string(&summary)?);
+    } else {
+        say!("{}", summary.render(format, Utc::now()));
+    }
+    Ok(())
+}
+
+/// Number of movies matching `opts`, or with `opts.field` set, the number
+/// of distinct values of that field among them. Movies without a director
+/// don't count towards [`CountField::Director`].
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{count_matching, CountField, CountOpts, Movie, Torrent};
+///
+/// let movie = |year, rating, genres: &[&str], quality: &str| Movie {
+///     year,
+///     rating,
+///     genres: genres.iter().map(|g| g.to_string()).collect(),
+///     torrents: vec![Torrent { quality: quality.into(), ..Default::default() }],
+///     ..Default::default()
+/// };
+/// let movies = vec![
+///     movie(1999, 8.7, &["Action", "Sci-Fi"], "1080p"),
+///     movie(1999, 6.1, &["Comedy"], "720p"),
+///     movie(2010, 8.8, &["Action"], "2160p"),
+/// ];
+/// let count = |opts: CountOpts| count_matching(&movies, &opts);
+///
+/// assert_eq!(count(CountOpts::default()), 3);
+/// assert_eq!(count(CountOpts { genre: Some("action".into()), ..Default::default() }), 2);
+/// assert_eq!(count(CountOpts { year: Some(1999), ..Default::default() }), 2);
+/// assert_eq!(count(CountOpts { min_rating: Some(8.0), ..Default::default() }), 2);
+/// assert_eq!(count(CountOpts { quality: Some("720p".into()), ..Default::default() }), 1);
+/// assert_eq!(
+///     count(CountOpts { genre: Some("Action".into()), year: Some(1999), min_rating: Some(8.0), ..Default::default() }),
+///     1
+/// );
+/// assert_eq!(count(CountOpts { year: Some(1980), ..Default::default() }), 0);
+///
+/// assert_eq!(count(CountOpts { field: Some(CountField::Genre), ..Default::default() }), 3);
+/// assert_eq!(count(CountOpts { field: Some(CountField::Year), ..Default::default() }), 2);
+/// assert_eq!(
+///     count(CountOpts { field: Some(CountField::Quality), year: Some(1999), ..Default::default() }),
+///     2
+/// );
+/// assert_eq!(count(CountOpts { field: Some(CountField::Director), ..Default::default() }), 0);
+/// ```
+pub fn count_matching(movies: &[Movie], opts: &CountOpts) -> usize {
+    let matches = movies.iter().filter(|m| opts.matches(m));
+
+    let Some(field) = opts.field else {
+        return matches.count();
+    };
+
+    let mut values: HashSet<String> = HashSet::new();
+    for movie in matches {
+        match field {
+            CountField::Genre => values.extend(movie.genres.iter().cloned()),
+            CountField::Year => {
+                values.insert(movie.year.to_string());
+            }
+            CountField::Quality => values.extend(movie.torrents.iter().map(|t| t.quality.clone())),
+            CountField::Director => values.extend(movie.director.clone()),
+        }
+    }
+    values.len()
+}
+
+/// Prints just the count, so the output can be used in shell arithmetic.
+pub fn count_movies(storage: &dyn Storage, opts: &CountOpts) -> Result<()> {
+    let movies = storage.load()?;
+    say!("{}", count_matching(&movies, opts));
+    Ok(())
+}
+
+/// Which torrent of each movie `calculate_size` counts.
+#[derive(Debug, Clone)]
+pub enum QualitySelector {
+    /// The largest torrent, whatever its quality.
+    Largest,
+    /// The largest torrent of this quality; movies without one are skipped.
+    Specific(String),
+    /// The largest torrent of this quality, or the largest overall if there is none.
+    SpecificOrLargest(String),
+}
+
+impl QualitySelector {
+    /// Size of the torrent picked for `movie`, or `None` if nothing qualifies.
+    pub fn select_size(&self, movie: &Movie) -> Option<u64> {
+        let largest = || movie.torrents.iter().map(|t| t.size_bytes).max();
+        let specific = |quality: &str| {
+            movie
+                .torrents
+                .iter()
+                .filter(|t| quality_matches(&t.label(), quality))
+                .map(|t| t.size_bytes)
+                .max()
+        };
+
+        match self {
+            QualitySelector::Largest => largest(),
+            QualitySelector::Specific(quality) => specific(quality),
+            QualitySelector::SpecificOrLargest(quality) => specific(quality).or_else(largest),
+        }
+    }
+
+    fn quality(&self) -> Option<&str> {
+        match self {
+            QualitySelector::Largest => None,
+            QualitySelector::Specific(quality) | QualitySelector::SpecificOrLargest(quality) => Some(quality),
+        }
+    }
+}
+
+pub fn calculate_size(storage: &dyn Storage, selector: &QualitySelector) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let sizes: Vec<u64> = movies.iter().filter_map(|m| selector.select_size(m)).collect();
+    let total_size: u64 = sizes.iter().sum();
+
+    match selector {
+        QualitySelector::Largest => say!("💾 Total Database Size (largest torrent per movie)\n"),
+        QualitySelector::Specific(quality) => say!("💾 Total Database Size ({} torrents)\n", quality),
+        QualitySelector::SpecificOrLargest(quality) => {
+            say!("💾 Total Database Size ({} torrents, largest otherwise)\n", quality)
+        }
+    }
+    say!("Total movies: {}", movies.len());
+    say!("Movies counted: {}", sizes.len());
+    say!("Combined size: {}", format_size(total_size));
+    if !sizes.is_empty() {
+        say!("Average size per movie: {}", format_size(total_size / sizes.len() as u64));
+    }
+
+    if let Some(quality) = selector.quality() {
+        let missing = movies
+            .iter()
+            .filter(|m| !m.torrents.iter().any(|t| quality_matches(&t.label(), quality)))
+            .count();
+        if missing > 0 {
+            let action = match selector {
+                QualitySelector::SpecificOrLargest(_) => "used the largest torrent instead",
+                _ => "not counted",
+            };
+            say!("\n⚠️  {} movies have no {} torrent ({})", missing, quality, action);
+        }
+    }
+
+    Ok(())
+}
+
+/// Movies having a quality and their combined size at it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SizeAggregate {
+    pub count: usize,
+    pub total_bytes: u64,
+}
+
+/// What downloading every movie in each quality (e.g. "1080p-bluray") would
+/// take. A movie with several torrents of one quality counts its largest.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{aggregate_size_by_quality, Movie, SizeAggregate, Torrent};
+///
+/// let torrent = |quality: &str, size_bytes| Torrent {
+///     quality: quality.into(),
+///     torrent_type: "web".into(),
+///     size_bytes,
+///     ..Default::default()
+/// };
+/// let movie = |torrents| Movie { torrents, ..Default::default() };
+/// let movies = vec![
+///     movie(vec![torrent("720p", 700), torrent("1080p", 1500), torrent("1080p", 1400)]),
+///     movie(vec![torrent("1080p", 2000)]),
+///     Movie::default(),
+/// ];
+///
+/// let sizes = aggregate_size_by_quality(&movies);
+/// assert_eq!(sizes.len(), 2);
+/// assert_eq!(sizes["1080p-web"], SizeAggregate { count: 2, total_bytes: 3500 });
+/// assert_eq!(sizes["720p-web"], SizeAggregate { count: 1, total_bytes: 700 });
+/// ```
+pub fn aggregate_size_by_quality(movies: &[Movie]) -> BTreeMap<String, SizeAggregate> {
+    let mut sizes: BTreeMap<String, SizeAggregate> = BTreeMap::new();
+
+    for movie in movies {
+        let mut largest: HashMap<String, u64> = HashMap::new();
+        for torrent in &movie.torrents {
+            let size = largest.entry(torrent.label()).or_default();
+            *size = (*size).max(torrent.size_bytes);
+        }
+        for (quality, size) in largest {
+            let aggregate = sizes.entry(quality).or_default();
+            aggregate.count += 1;
+            aggregate.total_bytes += size;
+        }
+    }
+
+    sizes
+}
+
+pub fn size_by_quality_command(storage: &dyn Storage) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let mut sizes: Vec<(String, SizeAggregate)> = aggregate_size_by_quality(&movies).into_iter().collect();
+    sizes.sort_by(|(qa, a), (qb, b)| b.total_bytes.cmp(&a.total_bytes).then_with(|| qa.cmp(qb)));
+
+    say!("💾 Size of every movie per quality ({} movies)\n", movies.len());
+    say!("{:<16} {:>8} {:>12} {:>12}", "Quality", "Movies", "Total", "Average");
+    say!("{}", "-".repeat(51));
+    for (quality, aggregate) in &sizes {
+        say!(
+            "{:<16} {:>8} {:>12} {:>12}",
+            quality,
+            aggregate.count,
+            format_size(aggregate.total_bytes),
+            format_size(aggregate.total_bytes / aggregate.count as u64)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn compute_stats(movies: &[Movie]) -> DatabaseStats {
+    let mut stats = DatabaseStats {
+        total_movies: movies.len(),
+        year_range: (
+            movies.iter().map(|m| m.year).min().unwrap_or(0),
+            movies.iter().map(|m| m.year).max().unwrap_or(0),
+        ),
+        id_range: (
+            movies.iter().map(|m| m.id).min().unwrap_or(0),
+            movies.iter().map(|m| m.id).max().unwrap_or(0),
+        ),
+        ..Default::default()
+    };
+
+    for movie in movies {
+        let size = largest_torrent_size(movie);
+
+        stats.total_torrents += movie.torrents.len();
+        stats.total_size += size;
+
+        if movie.torrents.is_empty() {
+            stats.movies_without_torrents += 1;
+        }
+
+        let year = stats.per_year.entry(movie.year).or_default();
+        year.count += 1;
+        year.total_size += size;
+
+        for torrent in &movie.torrents {
+            *stats.per_quality.entry(torrent.label()).or_default() += 1;
+        }
+
+        for genre in &movie.genres {
+            *stats.per_genre.entry(genre.clone()).or_default() += 1;
+        }
+    }
+
+    let mut by_size: Vec<&Movie> = movies.iter().collect();
+    by_size.sort_by_key(|m| Reverse(largest_torrent_size(m)));
+    stats.largest_movies = by_size
+        .into_iter()
+        .take(10)
+        .map(|m| (m.id, m.title.clone(), largest_torrent_size(m)))
+        .collect();
+
+    stats
+}
+
+/// What `plot` counts movies by.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PlotField {
+    Year,
+    /// Movies with several genres count once for each
+    Genre,
+    /// Torrents per quality
+    Quality,
+    /// IMDb rating rounded down to a whole point
+    Rating,
+}
+
+/// How often each distinct value occurs in `values`.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::build_histogram;
+///
+/// let histogram = build_histogram(&[1999, 2003, 1999, 2010, 1999]);
+/// assert_eq!(histogram.into_iter().collect::<Vec<_>>(), [(1999, 3), (2003, 1), (2010, 1)]);
+///
+/// let histogram = build_histogram(&["Drama", "Action", "Drama"]);
+/// assert_eq!(histogram["Drama"], 2);
+/// assert_eq!(histogram["Action"], 1);
+///
+/// assert!(build_histogram::<u32>(&[]).is_empty());
+/// ```
+pub fn build_histogram<T: Ord + Clone>(values: &[T]) -> BTreeMap<T, usize> {
+    let mut histogram = BTreeMap::new();
+    for value in values {
+        *histogram.entry(value.clone()).or_insert(0) += 1;
+    }
+    histogram
+}
+
+/// Draws one horizontal bar per row, scaled so the largest count fills
+/// `width` characters. Every non-zero count gets at least one block.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::format_histogram;
+///
+/// let rows = [("1999".to_string(), 10), ("2003".to_string(), 5), ("2010".to_string(), 1)];
+/// assert_eq!(
+///     format_histogram(&rows, 10),
+///     "1999 ██████████ 10\n\
+///      2003 █████ 5\n\
+///      2010 █ 1\n"
+/// );
+/// assert_eq!(format_histogram(&[], 10), "");
+/// ```
+pub fn format_histogram(rows: &[(String, usize)], width: usize) -> String {
+    let max = rows.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let label_width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    for (label, count) in rows {
+        let bar = (count * width).div_ceil(max.max(1)).max(usize::from(*count > 0));
+        out.push_str(&format!("{:<label_width$} {} {}\n", label, "█".repeat(bar), count));
+    }
+    out
+}
+
+pub fn plot_command(storage: &dyn Storage, field: PlotField, width: usize) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let rows: Vec<(String, usize)> = match field {
+        PlotField::Year => {
+            let years: Vec<u32> = movies.iter().map(|m| m.year).collect();
+            build_histogram(&years).into_iter().map(|(year, n)| (year.to_string(), n)).collect()
+        }
+        PlotField::Genre => {
+            let genres: Vec<&str> = movies.iter().flat_map(|m| m.genres.iter().map(String::as_str)).collect();
+            build_histogram(&genres).into_iter().map(|(genre, n)| (genre.to_string(), n)).collect()
+        }
+        PlotField::Quality => {
+            let qualities: Vec<&str> = movies.iter().flat_map(|m| m.torrents.iter().map(|t| t.quality.as_str())).collect();
+            build_histogram(&qualities).into_iter().map(|(quality, n)| (quality.to_string(), n)).collect()
+        }
+        PlotField::Rating => {
+            let buckets: Vec<u32> = movies.iter().map(|m| m.rating.max(0.0).floor() as u32).collect();
+            build_histogram(&buckets)
+                .into_iter()
+                .map(|(bucket, n)| (format!("{}-{}", bucket, bucket + 1), n))
+                .collect()
+        }
+    };
+
+    if rows.is_empty() {
+        say!("🔍 No {:?} values to plot.", field);
+        return Ok(());
+    }
+
+    // Printed directly: the Printer would strip the bars as decoration.
+    print!("{}", format_histogram(&rows, width));
+    Ok(())
+}
+
+pub fn show_stats(storage: &dyn Storage) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    let stats = compute_stats(&movies);
+
+    say!("📊 YTS Database Statistics\n");
+    say!("Movies:           {}", stats.total_movies);
+    say!("Total torrents:   {}", stats.total_torrents);
+    say!("Avg torrents/movie: {:.1}", stats.total_torrents as f64 / stats.total_movies as f64);
+    say!("Without torrents: {}", stats.movies_without_torrents);
+    say!("\nYear range:       {} - {}", stats.year_range.0, stats.year_range.1);
+    say!("\nMovie IDs:        {} to {}", stats.id_range.0, stats.id_range.1);
+    say!("\nTotal size (largest/movie): {}", format_size(stats.total_size));
+    say!("Average size per movie:     {}", format_size(stats.total_size / stats.total_movies as u64));
+
+    say!("\n📅 Per-year breakdown\n");
+    say!("{:<6} {:>8} {:>12}", "Year", "Movies", "Avg size");
+    say!("{}", "=".repeat(28));
+    for (year, year_stat) in &stats.per_year {
+        say!("{:<6} {:>8} {:>12}", year, year_stat.count, format_size(year_stat.average_size()));
+    }
+
+    say!("\n🎞️  Torrents per quality\n");
+    say!("{:<20} {:>10}", "Quality", "Torrents");
+    say!("{}", "=".repeat(31));
+    for (quality, count) in &stats.per_quality {
+        say!("{:<20} {:>10}", quality, count);
+    }
+
+    if !stats.per_genre.is_empty() {
+        say!("\n🎭 Movies per genre\n");
+        say!("{:<20} {:>10}", "Genre", "Movies");
+        say!("{}", "=".repeat(31));
+        for (genre, count) in &stats.per_genre {
+            say!("{:<20} {:>10}", genre, count);
+        }
+    }
+
+    say!("\n🏆 Largest movies\n");
+    for (rank, (id, title, size)) in stats.largest_movies.iter().enumerate() {
+        say!("{:>2}. [ID: {}] {} ({})", rank + 1, id, title, format_size(*size));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{movie, torrent};
+    use crate::{Torrent, YearStat};
+
+    fn web(quality: &str, size_bytes: u64) -> Torrent {
+        Torrent { torrent_type: "web".to_string(), ..torrent(quality, size_bytes) }
+    }
+
+    /// Four movies over two years, one of them without torrents.
+    fn library() -> Vec<Movie> {
+        let mut heat = movie(7, "Heat", 1995);
+        heat.torrents = vec![web("1080p", 300), web("720p", 100)];
+        heat.genres = vec!["Crime".to_string(), "Drama".to_string()];
+        let mut casino = movie(3, "Casino", 1995);
+        casino.torrents = vec![web("1080p", 500), torrent("2160p", 900)];
+        casino.genres = vec!["Crime".to_string()];
+        let mut dune = movie(12, "Dune", 2021);
+        dune.torrents = vec![web("720p", 200)];
+        let mut lost = movie(5, "Lost", 2021);
+        lost.torrents.clear();
+        vec![heat, casino, dune, lost]
+    }
+
+    #[test]
+    fn stats_count_movies_torrents_and_sizes() {
+        let stats = compute_stats(&library());
+
+        assert_eq!(stats.total_movies, 4);
+        assert_eq!(stats.total_torrents, 5);
+        assert_eq!(stats.total_size, 300 + 900 + 200);
+        assert_eq!(stats.year_range, (1995, 2021));
+        assert_eq!(stats.id_range, (3, 12));
+        assert_eq!(stats.movies_without_torrents, 1);
+    }
+
+    #[test]
+    fn stats_group_by_year_quality_and_genre() {
+        let stats = compute_stats(&library());
+
+        let per_year: Vec<(u32, YearStat)> = stats.per_year.into_iter().collect();
+        assert_eq!(
+            per_year,
+            [(1995, YearStat { count: 2, total_size: 1200 }), (2021, YearStat { count: 2, total_size: 200 })]
+        );
+        let per_quality: Vec<(&str, usize)> = stats.per_quality.iter().map(|(q, n)| (q.as_str(), *n)).collect();
+        assert_eq!(per_quality, [("1080p-web", 2), ("2160p", 1), ("720p-web", 2)]);
+        let per_genre: Vec<(&str, usize)> = stats.per_genre.iter().map(|(g, n)| (g.as_str(), *n)).collect();
+        assert_eq!(per_genre, [("Crime", 2), ("Drama", 1)]);
+    }
+
+    #[test]
+    fn stats_list_the_largest_movies_first() {
+        let largest: Vec<(u32, u64)> =
+            compute_stats(&library()).largest_movies.into_iter().map(|(id, _, size)| (id, size)).collect();
+        assert_eq!(largest, [(3, 900), (7, 300), (12, 200), (5, 0)]);
+    }
+
+    #[test]
+    fn stats_of_an_empty_database_are_zero() {
+        let stats = compute_stats(&[]);
+        assert_eq!(stats.total_movies, 0);
+        assert_eq!(stats.year_range, (0, 0));
+        assert!(stats.per_year.is_empty());
+        assert!(stats.largest_movies.is_empty());
+    }
+
+    fn heat() -> Movie {
+        library().remove(0)
+    }
+
+    #[test]
+    fn largest_selects_the_biggest_torrent() {
+        assert_eq!(QualitySelector::Largest.select_size(&heat()), Some(300));
+        assert_eq!(QualitySelector::Largest.select_size(&library()[3]), None);
+    }
+
+    #[test]
+    fn specific_selects_the_wanted_quality() {
+        assert_eq!(QualitySelector::Specific("720p".to_string()).select_size(&heat()), Some(100));
+        assert_eq!(QualitySelector::Specific("1080P-WEB".to_string()).select_size(&heat()), Some(300));
+    }
+
+    #[test]
+    fn specific_skips_movies_without_the_quality() {
+        assert_eq!(QualitySelector::Specific("2160p".to_string()).select_size(&heat()), None);
+    }
+
+    #[test]
+    fn specific_or_largest_falls_back_to_the_biggest_torrent() {
+        assert_eq!(QualitySelector::SpecificOrLargest("720p".to_string()).select_size(&heat()), Some(100));
+        assert_eq!(QualitySelector::SpecificOrLargest("2160p".to_string()).select_size(&heat()), Some(300));
+    }
+}
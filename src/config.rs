@@ -0,0 +1,190 @@
+//! The optional TOML config file and how it combines with CLI flags.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{ApiVersion, Result, YtsError, DEFAULT_CONCURRENCY, DEFAULT_TRACKERS, MAX_PAGE_SIZE};
+
+/// User settings loaded from `~/.config/yts-scraper/config.toml`. Unset
+/// fields fall back to the built-in defaults; see [`Config::resolve`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Trackers appended to every generated magnet link.
+    pub trackers: Vec<String>,
+    pub api_url: Option<Url>,
+    pub output_file: Option<PathBuf>,
+    pub page_size: Option<u32>,
+    pub concurrency: Option<u32>,
+    pub proxy: Option<Url>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            trackers: DEFAULT_TRACKERS.iter().map(|t| t.to_string()).collect(),
+            api_url: None,
+            output_file: None,
+            page_size: None,
+            concurrency: None,
+            proxy: None,
+        }
+    }
+}
+
+/// Settings given as flags or environment variables, which win over the
+/// config file.
+#[derive(Debug, Default)]
+pub struct ConfigOverrides {
+    pub api_url: Option<Url>,
+    pub output_file: Option<PathBuf>,
+    pub page_size: Option<u32>,
+    pub concurrency: Option<u32>,
+    pub proxy: Option<Url>,
+    /// Ignore any proxy, including one from the config file.
+    pub no_proxy: bool,
+    /// Picks the default `api_url` when neither a flag nor the config file sets one.
+    pub api_version: ApiVersion,
+}
+
+/// The settings a run uses after merging defaults, the config file, the
+/// environment and flags.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct EffectiveConfig {
+    pub api_url: Url,
+    pub output_file: PathBuf,
+    pub page_size: u32,
+    pub concurrency: u32,
+    pub proxy: Option<Url>,
+    pub trackers: Vec<String>,
+}
+
+impl Config {
+    /// Fills every setting from `overrides` first, then the config file, then
+    /// the built-in defaults. `default_output` is the database path of the
+    /// chosen backend.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::{Path, PathBuf};
+    /// use yts_movie_scraper::{Config, ConfigOverrides, API_BASE, DEFAULT_CONCURRENCY, MAX_PAGE_SIZE};
+    ///
+    /// let file: Config = toml::from_str(
+    ///     r#"
+    ///     api_url = "https://mirror.example/api/v2/list_movies.json"
+    ///     page_size = 20
+    ///     concurrency = 8
+    ///     proxy = "socks5://127.0.0.1:1080"
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// let default_output = Path::new("yts_movies.json");
+    ///
+    /// // Nothing set anywhere: the built-in defaults.
+    /// let config = Config::default().resolve(ConfigOverrides::default(), default_output).unwrap();
+    /// assert_eq!(config.api_url.as_str(), API_BASE);
+    /// assert_eq!(config.output_file, default_output);
+    /// assert_eq!(config.page_size, MAX_PAGE_SIZE);
+    /// assert_eq!(config.concurrency, DEFAULT_CONCURRENCY);
+    /// assert_eq!(config.proxy, None);
+    ///
+    /// // The config file beats the defaults.
+    /// let config = file.resolve(ConfigOverrides::default(), default_output).unwrap();
+    /// assert_eq!(config.api_url.as_str(), "https://mirror.example/api/v2/list_movies.json");
+    /// assert_eq!((config.page_size, config.concurrency), (20, 8));
+    /// assert!(config.proxy.is_some());
+    /// assert_eq!(config.trackers, Config::default().trackers);
+    ///
+    /// // Flags and environment variables beat the config file.
+    /// let overrides = ConfigOverrides {
+    ///     page_size: Some(50),
+    ///     output_file: Some(PathBuf::from("movies.json")),
+    ///     no_proxy: true,
+    ///     ..Default::default()
+    /// };
+    /// let config = file.resolve(overrides, default_output).unwrap();
+    /// assert_eq!(config.page_size, 50);
+    /// assert_eq!(config.concurrency, 8);
+    /// assert_eq!(config.output_file, Path::new("movies.json"));
+    /// assert_eq!(config.proxy, None);
+    ///
+    /// // Values the flags would reject are rejected in the file too.
+    /// let bad: Config = toml::from_str("page_size = 500").unwrap();
+    /// assert!(bad.resolve(ConfigOverrides::default(), default_output).is_err());
+    /// ```
+    pub fn resolve(&self, overrides: ConfigOverrides, default_output: &Path) -> Result<EffectiveConfig> {
+        let page_size = overrides.page_size.or(self.page_size).unwrap_or(MAX_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+            return Err(YtsError::ConfigError(format!("page_size must be between 1 and {}", MAX_PAGE_SIZE)));
+        }
+        let concurrency = overrides.concurrency.or(self.concurrency).unwrap_or(DEFAULT_CONCURRENCY);
+        if concurrency == 0 {
+            return Err(YtsError::ConfigError("concurrency must be at least 1".to_string()));
+        }
+
+        Ok(EffectiveConfig {
+            api_url: match overrides.api_url.or_else(|| self.api_url.clone()) {
+                Some(url) => url,
+                None => Url::parse(overrides.api_version.default_url()).expect("default API URLs are valid"),
+            },
+            output_file: overrides
+                .output_file
+                .or_else(|| self.output_file.clone())
+                .unwrap_or_else(|| default_output.to_path_buf()),
+            page_size,
+            concurrency,
+            proxy: if overrides.no_proxy { None } else { overrides.proxy.or_else(|| self.proxy.clone()) },
+            trackers: self.trackers.clone(),
+        })
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let dirs = directories::BaseDirs::new()?;
+    Some(dirs.home_dir().join(".config").join("yts-scraper").join("config.toml"))
+}
+
+/// Loads the config file, falling back to the built-in defaults if it does not exist.
+pub fn load_config() -> Result<Config> {
+    let Some(path) = config_path().filter(|p| p.exists()) else {
+        return Ok(Config::default());
+    };
+
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|e| YtsError::ConfigError(format!("invalid config file {}: {}", path.display(), e)))
+}
+
+pub fn config_init(force: bool) -> Result<()> {
+    let path = config_path().ok_or_else(|| YtsError::ConfigError("could not determine home directory".to_string()))?;
+
+    if path.exists() && !force {
+        say!("⚠️  Config file already exists at {} (use --force to overwrite)", path.display());
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = toml::to_string_pretty(&Config::default()).map_err(|e| YtsError::ConfigError(e.to_string()))?;
+    fs::write(&path, content)?;
+
+    say!("✅ Wrote default config to {}", path.display());
+
+    Ok(())
+}
+
+/// Prints the merged settings as TOML, in the format of the config file.
+pub fn config_show(config: &EffectiveConfig) -> Result<()> {
+    match config_path() {
+        Some(path) if path.exists() => say!("# Config file: {}", path.display()),
+        Some(path) => say!("# Config file: {} (not found, using defaults)", path.display()),
+        None => say!("# Config file: unknown home directory, using defaults"),
+    }
+    let content = toml::to_string_pretty(config).map_err(|e| YtsError::ConfigError(e.to_string()))?;
+    say!("{}", content.trim_end());
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Peer lookup on the BitTorrent mainline DHT behind `peer-discovery`.
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+
+use crate::{is_info_hash, Result, Storage, YtsError};
+
+/// Well-known DHT routers that every lookup starts from.
+pub const DHT_BOOTSTRAP_NODES: [&str; 3] =
+    ["router.bittorrent.com:6881", "dht.transmissionbt.com:6881", "router.utorrent.com:6881"];
+
+/// The 20 bytes the DHT looks `hash` up by; v2 hashes are truncated, as in BEP 52.
+fn dht_info_hash(hash: &str) -> Result<[u8; 20]> {
+    let lower = hash.trim().to_ascii_lowercase();
+    if !is_info_hash(&lower) {
+        return Err(YtsError::InvalidHash(hash.to_string()));
+    }
+
+    let mut bytes = [0; 20];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&lower[i * 2..i * 2 + 2], 16).expect("checked to be hex");
+    }
+    Ok(bytes)
+}
+
+/// Looks up the peers of torrent `hash` on the BitTorrent DHT, starting from
+/// [`DHT_BOOTSTRAP_NODES`], until `timeout` runs out or the lookup is done.
+pub async fn discover_peers(hash: &str, timeout: Duration) -> Result<Vec<SocketAddr>> {
+    discover_peers_via(hash, &DHT_BOOTSTRAP_NODES.map(str::to_string), timeout).await
+}
+
+/// Like [`discover_peers`], starting from the `bootstrap` nodes (`host:port`).
+/// The lookup itself is left to the `mainline` crate; peers are returned in
+/// the order they were found, without duplicates.
+pub async fn discover_peers_via(hash: &str, bootstrap: &[String], timeout: Duration) -> Result<Vec<SocketAddr>> {
+    let info_hash = mainline::Id::from(dht_info_hash(hash)?);
+    let dht = mainline::Dht::builder().bootstrap(bootstrap).build()?.as_async();
+
+    let mut peers = Vec::new();
+    let mut seen = HashSet::new();
+    let mut responses = dht.get_peers(info_hash);
+    let lookup = async {
+        while let Some(found) = responses.next().await {
+            peers.extend(found.into_iter().filter(|peer| seen.insert(*peer)).map(SocketAddr::V4));
+        }
+    };
+    if tokio::time::timeout(timeout, lookup).await.is_err() {
+        tracing::debug!("DHT lookup of {} stopped after {}s", hash.trim(), timeout.as_secs());
+    }
+
+    tracing::debug!("found {} peers on the DHT", peers.len());
+    Ok(peers)
+}
+
+/// `peer-discovery`: prints the DHT peers of `hash` and returns how many there
+/// are. With `save`, the count is stored on the matching torrent.
+pub async fn peer_discovery_command(storage: &dyn Storage, hash: &str, timeout: Duration, save: bool) -> Result<usize> {
+    dht_info_hash(hash)?;
+    say_err!("🌐 Asking the DHT for peers of {} ({}s)...", hash.trim(), timeout.as_secs());
+
+    let started = Instant::now();
+    let peers = discover_peers(hash, timeout).await?;
+    {
+        let mut out = io::stdout().lock();
+        for peer in &peers {
+            writeln!(out, "{}", peer)?;
+        }
+    }
+    say_err!("\n✅ Found {} peers in {:.1}s", peers.len(), started.elapsed().as_secs_f64());
+
+    if save {
+        let mut movies = storage.load()?;
+        let torrent = movies
+            .iter_mut()
+            .flat_map(|m| m.torrents.iter_mut())
+            .find(|t| t.hash.eq_ignore_ascii_case(hash.trim()));
+        match torrent {
+            Some(torrent) => {
+                torrent.dht_peers = Some(peers.len() as u32);
+                storage.save(&movies)?;
+                say_err!("💾 Saved the peer count to {}", storage.path().display());
+            }
+            None => say_err!("⚠️  No torrent with hash {} in the database; nothing saved", hash.trim()),
+        }
+    }
+
+    Ok(peers.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_hashes_are_read_as_dht_ids() {
+        let v1 = dht_info_hash(" C0EDF0F169275D7D889DEE3C073122B26FDFACA0 ").unwrap();
+        assert_eq!(v1[..4], [0xc0, 0xed, 0xf0, 0xf1]);
+
+        let v2 = dht_info_hash(&"ab".repeat(32)).unwrap();
+        assert_eq!(v2, [0xab; 20]);
+
+        assert!(matches!(dht_info_hash("c0ffee"), Err(YtsError::InvalidHash(_))));
+    }
+
+    #[tokio::test]
+    async fn peers_announced_on_a_local_dht_are_found() {
+        let hash = "c0edf0f169275d7d889dee3c073122b26fdfaca0";
+        let testnet = mainline::Testnet::builder(5).build().unwrap();
+        let node = mainline::Dht::builder().bootstrap(&testnet.bootstrap).build().unwrap().as_async();
+        node.announce_peer(mainline::Id::from(dht_info_hash(hash).unwrap()), Some(6882)).await.unwrap();
+
+        let peers = discover_peers_via(hash, &testnet.bootstrap, Duration::from_secs(10)).await.unwrap();
+
+        assert_eq!(peers.len(), 1, "{:?}", peers);
+        assert_eq!(peers[0].port(), 6882);
+        assert!(peers[0].ip().is_loopback());
+    }
+
+    #[tokio::test]
+    async fn lookups_of_unknown_hashes_find_nothing() {
+        let testnet = mainline::Testnet::builder(3).build().unwrap();
+
+        let peers = discover_peers_via(&"0".repeat(40), &testnet.bootstrap, Duration::from_secs(10)).await.unwrap();
+
+        assert!(peers.is_empty());
+    }
+}
@@ -0,0 +1,486 @@
+//! Metadata enrichment from OMDb and TMDB.
+
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use futures::stream::{self, StreamExt};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{Movie, Printer, Result, Storage, YtsError};
+
+pub const OMDB_API_BASE: &str = "http://www.omdbapi.com/";
+/// Requests allowed per day on a free OMDb API key.
+pub const OMDB_DAILY_LIMIT: u32 = 1000;
+
+/// Default base URL of the TMDB v3 API.
+pub const TMDB_API_BASE: &str = "https://api.themoviedb.org/3/";
+
+/// Prefix turning a TMDB `backdrop_path` into a full-size image URL.
+pub const TMDB_IMAGE_BASE: &str = "https://image.tmdb.org/t/p/original";
+
+/// Movies looked up in parallel by `enrich tmdb`, and the pause between batches.
+const TMDB_BATCH_SIZE: usize = 5;
+const TMDB_BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// OMDb requests made on a given (UTC) day, used to stay under
+/// [`OMDB_DAILY_LIMIT`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct OmdbUsage {
+    pub date: NaiveDate,
+    pub requests: u32,
+}
+
+impl OmdbUsage {
+    /// Today's usage, starting from zero when `usage` is from an earlier day.
+    pub fn today(usage: Option<OmdbUsage>) -> OmdbUsage {
+        let today = Utc::now().date_naive();
+        match usage {
+            Some(usage) if usage.date == today => usage,
+            _ => OmdbUsage { date: today, requests: 0 },
+        }
+    }
+
+    pub fn remaining(&self) -> u32 {
+        OMDB_DAILY_LIMIT.saturating_sub(self.requests)
+    }
+}
+
+/// Connection settings for the OMDb API used to enrich fetched movies.
+pub struct OmdbApi {
+    pub client: reqwest::Client,
+    pub base_url: Url,
+    pub api_key: String,
+}
+
+/// Connection settings for the TMDB API used by `enrich tmdb`.
+pub struct TmdbApi {
+    pub client: reqwest::Client,
+    pub base_url: Url,
+    pub api_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbFindResponse {
+    #[serde(default)]
+    movie_results: Vec<TmdbFindResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbFindResult {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbMovieDetails {
+    runtime: Option<u32>,
+    backdrop_path: Option<String>,
+    #[serde(default)]
+    spoken_languages: Vec<TmdbLanguage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbLanguage {
+    english_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbError {
+    status_message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OmdbResponse {
+    #[serde(rename = "Response")]
+    response: String,
+    #[serde(rename = "Error")]
+    error: Option<String>,
+    #[serde(rename = "Plot")]
+    plot: Option<String>,
+    #[serde(rename = "Director")]
+    director: Option<String>,
+    #[serde(rename = "Awards")]
+    awards: Option<String>,
+}
+
+async fn fetch_omdb(omdb: &OmdbApi, imdb_code: &str) -> Result<OmdbResponse> {
+    let mut url = omdb.base_url.clone();
+    url.query_pairs_mut().append_pair("i", imdb_code).append_pair("apikey", &omdb.api_key);
+
+    // The URL carries the API key, so only the IMDb code is logged.
+    tracing::debug!("OMDb lookup {}", imdb_code);
+    let response: OmdbResponse = omdb.client.get(url).send().await?.json().await?;
+
+    if response.response != "True" {
+        return Err(YtsError::ApiError(response.error.unwrap_or_else(|| "unknown OMDb error".to_string())));
+    }
+    Ok(response)
+}
+
+/// OMDb reports missing values as "N/A".
+fn omdb_field(value: Option<String>) -> Option<String> {
+    value.filter(|v| v != "N/A")
+}
+
+/// Fills in plot, director and awards from OMDb for movies with an IMDb code,
+/// making at most `budget` requests. Failed lookups leave the movie unchanged.
+/// Returns the number of requests made.
+pub async fn enrich_with_omdb(omdb: &OmdbApi, movies: &mut [Movie], budget: u32, concurrency: u32) -> u32 {
+    let candidates: Vec<(usize, String)> = movies
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| !m.imdb_code.is_empty())
+        .map(|(i, m)| (i, m.imdb_code.clone()))
+        .collect();
+    let skipped = candidates.len().saturating_sub(budget as usize);
+    let lookups: Vec<(usize, String)> = candidates.into_iter().take(budget as usize).collect();
+    let requests = lookups.len() as u32;
+
+    if requests > 0 {
+        say!("🎭 Looking up {} movies on OMDb...", requests);
+    }
+    let pb = Printer::global().progress_bar(Some(requests as u64), "{pos}/{len} movies");
+
+    let mut results = stream::iter(lookups)
+        .map(|(index, imdb_code)| async move { (index, fetch_omdb(omdb, &imdb_code).await) })
+        .buffer_unordered(concurrency as usize);
+
+    let mut failed = 0;
+    let mut last_error = None;
+    while let Some((index, result)) = results.next().await {
+        pb.inc(1);
+        match result {
+            Ok(details) => {
+                let movie = &mut movies[index];
+                movie.plot = omdb_field(details.plot);
+                movie.director = omdb_field(details.director);
+                movie.awards = omdb_field(details.awards);
+            }
+            Err(e) => {
+                tracing::debug!("OMDb lookup for {} failed: {}", movies[index].imdb_code, e);
+                failed += 1;
+                last_error = Some(e);
+            }
+        }
+    }
+    pb.finish_and_clear();
+
+    if let Some(e) = last_error {
+        say!("⚠️  {} OMDb lookups failed (last error: {})", failed, e);
+    }
+    if skipped > 0 {
+        say!("⚠️  OMDb daily limit of {} reached, {} movies left without details", OMDB_DAILY_LIMIT, skipped);
+    }
+
+    requests
+}
+
+/// GETs `path` below the TMDB base URL. A 429 is retried after the
+/// `Retry-After` delay; other errors carry TMDB's status message.
+async fn tmdb_get<T: serde::de::DeserializeOwned>(tmdb: &TmdbApi, path: &str, query: &[(&str, &str)]) -> Result<T> {
+    // Without a trailing slash, join() would replace the last path segment.
+    let mut base = tmdb.base_url.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    let mut url = base.join(path).map_err(|e| YtsError::ApiError(e.to_string()))?;
+    url.query_pairs_mut().append_pair("api_key", &tmdb.api_key).extend_pairs(query);
+
+    // The URL carries the API key, so only the path is logged.
+    tracing::debug!("TMDB GET {}", path);
+    let mut attempt: u32 = 0;
+    loop {
+        // reqwest errors include the URL, and with it the API key.
+        let response = tmdb.client.get(url.clone()).send().await.map_err(|e| e.without_url())?;
+        let status = response.status();
+
+        if status == StatusCode::TOO_MANY_REQUESTS && attempt < 3 {
+            let wait = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10);
+            tracing::debug!("TMDB rate limit, waiting {}s", wait);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+            attempt += 1;
+            continue;
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(YtsError::RateLimit);
+        }
+        if !status.is_success() {
+            let message = match response.json::<TmdbError>().await {
+                Ok(error) => error.status_message,
+                Err(_) => status.to_string(),
+            };
+            return Err(YtsError::ApiError(format!("TMDB: {}", message)));
+        }
+        return Ok(response.json().await.map_err(|e| e.without_url())?);
+    }
+}
+
+/// Looks a movie up by IMDb code. `None` when TMDB does not know it.
+async fn fetch_tmdb(tmdb: &TmdbApi, imdb_code: &str) -> Result<Option<TmdbMovieDetails>> {
+    let found: TmdbFindResponse =
+        tmdb_get(tmdb, &format!("find/{}", imdb_code), &[("external_source", "imdb_id")]).await?;
+    let Some(result) = found.movie_results.first() else {
+        return Ok(None);
+    };
+
+    Ok(Some(tmdb_get(tmdb, &format!("movie/{}", result.id), &[]).await?))
+}
+
+/// True once `enrich tmdb` has stored any TMDB data for the movie.
+pub fn is_tmdb_enriched(movie: &Movie) -> bool {
+    movie.runtime_minutes.is_some() || movie.backdrop_url.is_some() || !movie.spoken_languages.is_empty()
+}
+
+/// Totals reported by [`enrich_with_tmdb`].
+#[derive(Debug, Default)]
+pub struct TmdbSummary {
+    pub enriched: usize,
+    /// Movies TMDB has no entry for.
+    pub not_found: usize,
+    pub failed: usize,
+}
+
+/// Fills in runtime, backdrop and spoken languages from TMDB for movies with
+/// an IMDb code, skipping those already enriched unless `force` is set.
+/// Lookups run [`TMDB_BATCH_SIZE`] at a time with a short pause between
+/// batches to stay under TMDB's rate limit.
+///
+/// Returns an error only if every lookup of the first batch fails, which
+/// usually means a wrong API key or URL; later failures are counted.
+pub async fn enrich_with_tmdb(tmdb: &TmdbApi, movies: &mut [Movie], force: bool) -> Result<TmdbSummary> {
+    let lookups: Vec<(usize, String)> = movies
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| !m.imdb_code.is_empty() && (force || !is_tmdb_enriched(m)))
+        .map(|(i, m)| (i, m.imdb_code.clone()))
+        .collect();
+    let mut summary = TmdbSummary::default();
+
+    if lookups.is_empty() {
+        return Ok(summary);
+    }
+    say!("🎞️  Looking up {} movies on TMDB...", lookups.len());
+    let pb = Printer::global().progress_bar(Some(lookups.len() as u64), "{pos}/{len} movies");
+
+    let mut last_error = None;
+    for (batch_number, batch) in lookups.chunks(TMDB_BATCH_SIZE).enumerate() {
+        if batch_number > 0 {
+            tokio::time::sleep(TMDB_BATCH_DELAY).await;
+        }
+        let results = futures::future::join_all(batch.iter().map(|(index, imdb_code)| async move {
+            (*index, fetch_tmdb(tmdb, imdb_code).await)
+        }))
+        .await;
+
+        for (index, result) in results {
+            pb.inc(1);
+            match result {
+                Ok(Some(details)) => {
+                    let movie = &mut movies[index];
+                    movie.runtime_minutes = details.runtime.filter(|&r| r > 0);
+                    movie.backdrop_url = details.backdrop_path.map(|path| format!("{}{}", TMDB_IMAGE_BASE, path));
+                    movie.spoken_languages = details.spoken_languages.into_iter().map(|l| l.english_name).collect();
+                    summary.enriched += 1;
+                }
+                Ok(None) => summary.not_found += 1,
+                Err(e) => {
+                    tracing::debug!("TMDB lookup for {} failed: {}", movies[index].imdb_code, e);
+                    summary.failed += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if batch_number == 0 && summary.failed == batch.len() {
+            pb.finish_and_clear();
+            return Err(last_error.expect("failed lookups store their error"));
+        }
+    }
+    pb.finish_and_clear();
+
+    if let Some(e) = last_error {
+        say!("⚠️  {} TMDB lookups failed (last error: {})", summary.failed, e);
+    }
+
+    Ok(summary)
+}
+
+pub async fn enrich_tmdb_command(storage: &dyn Storage, tmdb: &TmdbApi, force: bool) -> Result<()> {
+    let mut movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let summary = enrich_with_tmdb(tmdb, &mut movies, force).await?;
+    if summary.enriched + summary.not_found + summary.failed == 0 {
+        say!("✅ All movies already have TMDB details (use --force to look them up again).");
+        return Ok(());
+    }
+
+    if summary.enriched > 0 {
+        storage.save(&movies)?;
+    }
+    say!("✅ Added TMDB details to {} movies", summary.enriched);
+    if summary.not_found > 0 {
+        say!("🔍 {} movies are not on TMDB", summary.not_found);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{json_storage, movie, omdb_api};
+
+    async fn omdb_reply(server: &MockServer, imdb_code: &str, body: serde_json::Value) {
+        Mock::given(method("GET"))
+            .and(path("/omdb/"))
+            .and(query_param("i", imdb_code))
+            .and(query_param("apikey", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn omdb_details_are_filled_in() {
+        let server = MockServer::start().await;
+        omdb_reply(
+            &server,
+            "tt0000002",
+            json!({ "Response": "True", "Plot": "A heist.", "Director": "Michael Mann", "Awards": "N/A" }),
+        )
+        .await;
+        let mut movies = [movie(2, "Heat", 1995)];
+
+        assert_eq!(enrich_with_omdb(&omdb_api(&server), &mut movies, 10, 2).await, 1);
+        assert_eq!(movies[0].plot.as_deref(), Some("A heist."));
+        assert_eq!(movies[0].director.as_deref(), Some("Michael Mann"));
+        assert_eq!(movies[0].awards, None);
+    }
+
+    #[tokio::test]
+    async fn omdb_errors_leave_the_movie_unchanged() {
+        let server = MockServer::start().await;
+        omdb_reply(&server, "tt0000002", json!({ "Response": "False", "Error": "Incorrect IMDb ID." })).await;
+        let mut movies = [Movie { plot: Some("Kept.".to_string()), ..movie(2, "Heat", 1995) }];
+
+        assert_eq!(enrich_with_omdb(&omdb_api(&server), &mut movies, 10, 2).await, 1);
+        assert_eq!(movies[0].plot.as_deref(), Some("Kept."));
+        assert_eq!(movies[0].director, None);
+    }
+
+    #[tokio::test]
+    async fn omdb_lookups_stop_at_the_budget() {
+        let server = MockServer::start().await;
+        for id in 1..=3 {
+            omdb_reply(&server, &format!("tt{:07}", id), json!({ "Response": "True", "Plot": "Plot" })).await;
+        }
+        let without_imdb_code = Movie { imdb_code: String::new(), ..movie(1, "A", 2001) };
+        let mut movies = [movie(3, "C", 2003), movie(2, "B", 2002), without_imdb_code];
+
+        assert_eq!(enrich_with_omdb(&omdb_api(&server), &mut movies, 1, 2).await, 1);
+        let plots: Vec<Option<&str>> = movies.iter().map(|m| m.plot.as_deref()).collect();
+        assert_eq!(plots, [Some("Plot"), None, None]);
+        assert_eq!(enrich_with_omdb(&omdb_api(&server), &mut movies, 0, 2).await, 0);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    fn tmdb_api(server: &MockServer) -> TmdbApi {
+        TmdbApi {
+            client: reqwest::Client::new(),
+            base_url: format!("{}/3", server.uri()).parse().unwrap(),
+            api_key: "key".to_string(),
+        }
+    }
+
+    /// TMDB knows the movie `imdb_code` under `tmdb_id`, with a runtime of
+    /// `tmdb_id` minutes.
+    async fn tmdb_movie(server: &MockServer, imdb_code: &str, tmdb_id: u64) {
+        Mock::given(method("GET"))
+            .and(path(format!("/3/find/{}", imdb_code)))
+            .and(query_param("external_source", "imdb_id"))
+            .and(query_param("api_key", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "movie_results": [{ "id": tmdb_id }] })))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/3/movie/{}", tmdb_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "runtime": tmdb_id,
+                "backdrop_path": format!("/{}.jpg", tmdb_id),
+                "spoken_languages": [{ "english_name": "English" }, { "english_name": "Spanish" }],
+            })))
+            .mount(server)
+            .await;
+    }
+
+    async fn lookups(server: &MockServer) -> Vec<String> {
+        let requests = server.received_requests().await.unwrap();
+        requests.iter().map(|r| r.url.path().to_string()).filter(|p| p.starts_with("/3/find/")).collect()
+    }
+
+    #[tokio::test]
+    async fn tmdb_details_are_stored() {
+        let server = MockServer::start().await;
+        tmdb_movie(&server, "tt0000002", 170).await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995)]);
+
+        enrich_tmdb_command(&storage, &tmdb_api(&server), false).await.unwrap();
+
+        let heat = storage.load().unwrap().remove(0);
+        assert_eq!(heat.runtime_minutes, Some(170));
+        assert_eq!(heat.backdrop_url.as_deref(), Some("https://image.tmdb.org/t/p/original/170.jpg"));
+        assert_eq!(heat.spoken_languages, ["English", "Spanish"]);
+    }
+
+    #[tokio::test]
+    async fn enriched_movies_are_only_looked_up_again_with_force() {
+        let server = MockServer::start().await;
+        tmdb_movie(&server, "tt0000002", 170).await;
+        tmdb_movie(&server, "tt0000001", 117).await;
+        let enriched = Movie { runtime_minutes: Some(1), ..movie(1, "Alien", 1979) };
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995), enriched]);
+
+        enrich_tmdb_command(&storage, &tmdb_api(&server), false).await.unwrap();
+        assert_eq!(lookups(&server).await, ["/3/find/tt0000002"]);
+        assert_eq!(storage.load().unwrap()[1].runtime_minutes, Some(1));
+
+        enrich_tmdb_command(&storage, &tmdb_api(&server), false).await.unwrap();
+        assert_eq!(lookups(&server).await.len(), 1);
+
+        enrich_tmdb_command(&storage, &tmdb_api(&server), true).await.unwrap();
+        assert_eq!(lookups(&server).await.len(), 3);
+        let runtimes: Vec<Option<u32>> = storage.load().unwrap().iter().map(|m| m.runtime_minutes).collect();
+        assert_eq!(runtimes, [Some(170), Some(117)]);
+    }
+
+    #[tokio::test]
+    async fn movies_tmdb_does_not_know_are_left_alone() {
+        let server = MockServer::start().await;
+        Mock::given(path("/3/find/tt0000002"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "movie_results": [] })))
+            .mount(&server)
+            .await;
+        let mut movies = [movie(2, "Heat", 1995)];
+
+        let summary = enrich_with_tmdb(&tmdb_api(&server), &mut movies, false).await.unwrap();
+        assert_eq!((summary.enriched, summary.not_found, summary.failed), (0, 1, 0));
+        assert!(!is_tmdb_enriched(&movies[0]));
+    }
+}
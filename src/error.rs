@@ -0,0 +1,175 @@
+//! Errors returned by the library.
+
+use std::fmt;
+use std::io;
+
+use crate::ListField;
+
+/// Errors returned by the library.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::YtsError;
+///
+/// assert_eq!(YtsError::NotFound(42).to_string(), "movie 42 not found in database");
+/// assert_eq!(YtsError::ApiError("bad status".into()).to_string(), "API error: bad status");
+/// assert_eq!(YtsError::RateLimit.to_string(), "rate limited by the API, try again later");
+/// ```
+#[derive(Debug)]
+pub enum YtsError {
+    /// The request could not be sent or its response body not read.
+    HttpError(reqwest::Error),
+    IoError(io::Error),
+    /// A JSON file or API response could not be (de)serialized.
+    ParseError(serde_json::Error),
+    DatabaseError(rusqlite::Error),
+    CsvError(csv::Error),
+    /// The config file is invalid or its location is unknown.
+    ConfigError(String),
+    /// The API answered, but not with something usable.
+    ApiError(String),
+    /// No movie with this ID exists in the local database.
+    NotFound(u32),
+    /// No movie with this IMDb code exists in the local database.
+    ImdbNotFound(String),
+    /// The API kept answering 429 Too Many Requests after every retry.
+    RateLimit,
+    /// A size such as `1.5GB` could not be parsed.
+    InvalidSize(String),
+    /// A .torrent file is not valid bencode or lacks required fields.
+    TorrentError(bt_bencode::Error),
+    /// A tag is empty or contains a comma.
+    InvalidTag(String),
+    /// A request took longer than the configured timeout.
+    Timeout(reqwest::Error),
+    /// The HTML report template could not be rendered.
+    TemplateError(tera::Error),
+    /// A `list --fields` name is not one of [`ListField::ALL`].
+    UnknownField(String),
+    /// `--imdb-link` would open this many pages without `--all`.
+    TooManyMatches(usize),
+    /// The database uses local IDs from `fix-ids --compact-ids`, which
+    /// cannot be compared with YTS IDs.
+    CompactedIds,
+    /// Not a torrent info hash of 40 or 64 hex characters.
+    InvalidHash(String),
+    /// The movies to save list this ID more than once.
+    DuplicateId(u32),
+}
+
+impl fmt::Display for YtsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            YtsError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            YtsError::IoError(e) => write!(f, "I/O error: {}", e),
+            YtsError::ParseError(e) => write!(f, "JSON error: {}", e),
+            YtsError::DatabaseError(e) => write!(f, "database error: {}", e),
+            YtsError::CsvError(e) => write!(f, "CSV error: {}", e),
+            YtsError::ConfigError(msg) => write!(f, "config error: {}", msg),
+            YtsError::ApiError(msg) => write!(f, "API error: {}", msg),
+            YtsError::NotFound(id) => write!(f, "movie {} not found in database", id),
+            YtsError::ImdbNotFound(code) => write!(f, "movie {} not found in database", code),
+            YtsError::RateLimit => write!(f, "rate limited by the API, try again later"),
+            YtsError::InvalidSize(s) => {
+                write!(f, "invalid size '{}', expected a number with an optional B, KB, MB, GB or TB unit", s)
+            }
+            YtsError::TorrentError(e) => write!(f, "torrent error: {}", e),
+            YtsError::Timeout(e) => match e.url() {
+                Some(url) => write!(f, "request to {} timed out; try a larger --timeout", url),
+                None => write!(f, "request timed out; try a larger --timeout"),
+            },
+            YtsError::TemplateError(e) => write!(f, "template error: {}", e),
+            YtsError::TooManyMatches(count) => {
+                write!(f, "too many matches: {} movies shown; narrow the filters or pass --all", count)
+            }
+            YtsError::InvalidHash(hash) => {
+                write!(f, "invalid info hash '{}', expected 40 or 64 hex characters", hash)
+            }
+            YtsError::DuplicateId(id) => write!(f, "movie {} appears more than once; run 'dedup' first", id),
+            YtsError::CompactedIds => {
+                write!(f, "the database has compacted IDs; run 'fix-ids --restore' before syncing with YTS")
+            }
+            YtsError::UnknownField(name) => {
+                let names: Vec<&str> = ListField::ALL.iter().map(|field| field.name()).collect();
+                write!(f, "unknown field '{}', expected one of: {}", name, names.join(", "))
+            }
+            YtsError::InvalidTag(tag) => write!(f, "invalid tag '{}', tags must be non-empty and contain no commas", tag),
+        }
+    }
+}
+
+impl std::error::Error for YtsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            YtsError::HttpError(e) => Some(e),
+            YtsError::IoError(e) => Some(e),
+            YtsError::ParseError(e) => Some(e),
+            YtsError::DatabaseError(e) => Some(e),
+            YtsError::CsvError(e) => Some(e),
+            YtsError::TorrentError(e) => Some(e),
+            YtsError::Timeout(e) => Some(e),
+            YtsError::TemplateError(e) => Some(e),
+            YtsError::ConfigError(_)
+            | YtsError::ApiError(_)
+            | YtsError::NotFound(_)
+            | YtsError::ImdbNotFound(_)
+            | YtsError::RateLimit
+            | YtsError::InvalidSize(_)
+            | YtsError::InvalidTag(_)
+            | YtsError::UnknownField(_)
+            | YtsError::TooManyMatches(_)
+            | YtsError::CompactedIds
+            | YtsError::InvalidHash(_)
+            | YtsError::DuplicateId(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for YtsError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            YtsError::Timeout(e)
+        } else {
+            YtsError::HttpError(e)
+        }
+    }
+}
+
+impl From<io::Error> for YtsError {
+    fn from(e: io::Error) -> Self {
+        YtsError::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for YtsError {
+    fn from(e: serde_json::Error) -> Self {
+        YtsError::ParseError(e)
+    }
+}
+
+impl From<rusqlite::Error> for YtsError {
+    fn from(e: rusqlite::Error) -> Self {
+        YtsError::DatabaseError(e)
+    }
+}
+
+impl From<csv::Error> for YtsError {
+    fn from(e: csv::Error) -> Self {
+        YtsError::CsvError(e)
+    }
+}
+
+impl From<bt_bencode::Error> for YtsError {
+    fn from(e: bt_bencode::Error) -> Self {
+        YtsError::TorrentError(e)
+    }
+}
+
+impl From<tera::Error> for YtsError {
+    fn from(e: tera::Error) -> Self {
+        YtsError::TemplateError(e)
+    }
+}
+
+pub type Result<T, E = YtsError> = std::result::Result<T, E>;
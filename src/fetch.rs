@@ -0,0 +1,1695 @@
+//! Fetching the YTS catalogue into the local database: `fetch`, `retry`, `check` and `clean`.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    compute_total_pages, create_magnet_url, enrich_with_omdb, ensure_yts_ids, fetch_page, fetch_page_batch,
+    fetch_pages_tolerant, fetch_pages_with_workers, format_elapsed, genre_matches, load_meta, parse_size_str,
+    save_meta, ApiMovie, ApiSortBy, FetchParams, Movie, OmdbApi, OmdbUsage, Printer, Result, SortOrder, Storage,
+    Torrent, YtsApi, YtsError,
+};
+
+pub const DEFAULT_CONCURRENCY: u32 = 4;
+
+pub const CHECKPOINT_FILE: &str = ".yts_fetch_progress.json";
+/// How many pages to fetch between checkpoint writes.
+pub const CHECKPOINT_INTERVAL_PAGES: u32 = 20;
+/// Pages that failed during `fetch`, kept next to the database for `retry`.
+pub const RETRY_QUEUE_FILE: &str = "yts_fetch_errors.json";
+
+/// Partial state of an interrupted fetch: the last page whose movies (and
+/// every page before it) are in `movies_so_far`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FetchCheckpoint {
+    pub page: u32,
+    /// Page size the checkpoint was written with; page numbers only line up
+    /// when resuming with the same size.
+    #[serde(default)]
+    pub page_size: u32,
+    /// `--min-api-rating` the checkpoint was written with.
+    #[serde(default)]
+    pub minimum_rating: Option<u8>,
+    /// `--genre` of the fetch the checkpoint belongs to.
+    #[serde(default)]
+    pub genre: Option<String>,
+    pub movies_so_far: Vec<Movie>,
+}
+
+/// Page numbers that could not be fetched, saved in [`RETRY_QUEUE_FILE`].
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::RetryQueue;
+///
+/// let mut queue = RetryQueue::new(50, Some(7), Some("comedy"));
+/// queue.push(12);
+/// queue.push(3);
+/// queue.push(12);
+///
+/// let json = serde_json::to_string(&queue).unwrap();
+/// assert_eq!(json, r#"{"page_size":50,"minimum_rating":7,"genre":"comedy","pages":[3,12]}"#);
+///
+/// let mut restored: RetryQueue = serde_json::from_str(&json).unwrap();
+/// assert_eq!(restored.check_pending(), [3, 12]);
+/// assert_eq!(restored.pending_report().unwrap(), "2 pages failed during the last fetch: 3, 12");
+/// assert_eq!(restored.pop_all(), [3, 12]);
+/// assert!(restored.is_empty());
+/// assert_eq!(restored.pending_report(), None);
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RetryQueue {
+    /// Page size the pages were requested with; page numbers only mean the
+    /// same movies at the same size.
+    pub page_size: u32,
+    /// `--min-api-rating` the pages were requested with.
+    #[serde(default)]
+    pub minimum_rating: Option<u8>,
+    /// `--genre` the pages were requested with.
+    #[serde(default)]
+    pub genre: Option<String>,
+    /// Sorted, without duplicates.
+    pages: Vec<u32>,
+}
+
+impl RetryQueue {
+    pub fn new(page_size: u32, minimum_rating: Option<u8>, genre: Option<&str>) -> Self {
+        RetryQueue {
+            page_size,
+            minimum_rating,
+            genre: genre.map(str::to_string),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Reads the queue, returning `None` if the file does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    /// Writes the queue, or removes the file once the queue is empty.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn push(&mut self, page: u32) {
+        if let Err(index) = self.pages.binary_search(&page) {
+            self.pages.insert(index, page);
+        }
+    }
+
+    /// Every queued page, lowest first, leaving the queue as it is.
+    pub fn check_pending(&self) -> Vec<u32> {
+        self.pages.clone()
+    }
+
+    /// The line `check` prints about the queued pages, or `None` without any.
+    /// At most 10 page numbers are listed.
+    pub fn pending_report(&self) -> Option<String> {
+        let pages = self.check_pending();
+        if pages.is_empty() {
+            return None;
+        }
+
+        let mut listed: Vec<String> = pages.iter().take(10).map(u32::to_string).collect();
+        if pages.len() > listed.len() {
+            listed.push("...".to_string());
+        }
+        let noun = if pages.len() == 1 { "page" } else { "pages" };
+        Some(format!("{} {} failed during the last fetch: {}", pages.len(), noun, listed.join(", ")))
+    }
+
+    /// Removes and returns every queued page, lowest first.
+    pub fn pop_all(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.pages)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// One line of the `--progress-file` side channel, written per fetched page.
+#[derive(Serialize)]
+struct ProgressEvent {
+    page: u32,
+    movies_found: usize,
+    elapsed_ms: u128,
+    total_estimated: u32,
+}
+
+/// Decides which API movies an incremental fetch should keep and where
+/// pagination can stop. Pages are ordered newest first.
+enum FetchCutoff<'a> {
+    /// Keep movies newer than the highest id already stored.
+    LatestId(u32),
+    /// Keep movies uploaded at or after `timestamp` that are not stored yet.
+    Since { timestamp: i64, known_ids: &'a HashSet<u32> },
+    /// Keep every movie that is not stored yet. Used when pages are not
+    /// newest first, so there is no point where the fetch can stop.
+    Missing(&'a HashSet<u32>),
+}
+
+impl FetchCutoff<'_> {
+    fn is_new(&self, movie: &ApiMovie) -> bool {
+        match self {
+            FetchCutoff::LatestId(latest_id) => movie.id > *latest_id,
+            FetchCutoff::Since { timestamp, known_ids } => {
+                movie.date_uploaded_unix >= *timestamp && !known_ids.contains(&movie.id)
+            }
+            FetchCutoff::Missing(known_ids) => !known_ids.contains(&movie.id),
+        }
+    }
+
+    /// True once a movie is older than anything this fetch cares about.
+    fn is_past(&self, movie: &ApiMovie) -> bool {
+        match self {
+            FetchCutoff::LatestId(latest_id) => movie.id <= *latest_id,
+            FetchCutoff::Since { timestamp, .. } => movie.date_uploaded_unix < *timestamp,
+            FetchCutoff::Missing(_) => false,
+        }
+    }
+}
+
+/// Scans pages in batches until reaching one that contains a movie past the
+/// cutoff, or until `max_pages` pages have been scanned. Returns the ids and
+/// titles of the newer movies and the last page scanned.
+async fn scan_new_movies(
+    api: &YtsApi,
+    params: &FetchParams,
+    cutoff: &FetchCutoff<'_>,
+    concurrency: u32,
+    max_pages: Option<u32>,
+) -> Result<(Vec<(u32, String)>, u32)> {
+    let mut new_titles = Vec::new();
+    let mut last_page = 0;
+    let mut next_page = 1;
+
+    'scan: while max_pages.is_none_or(|max| next_page <= max) {
+        let batch_end = match max_pages {
+            Some(max) => (next_page + concurrency).min(max + 1),
+            None => next_page + concurrency,
+        };
+        let pages: Vec<u32> = (next_page..batch_end).collect();
+        let batch = fetch_page_batch(api, params, pages, concurrency, |_, _| Ok(())).await?;
+
+        for (page, movies) in batch {
+            last_page = page;
+            if movies.is_empty() {
+                break 'scan;
+            }
+
+            new_titles.extend(movies.iter().filter(|m| cutoff.is_new(m)).map(|m| (m.id, m.title.clone())));
+            if movies.iter().any(|m| cutoff.is_past(m)) {
+                break 'scan;
+            }
+        }
+
+        next_page = batch_end;
+    }
+
+    Ok((new_titles, last_page))
+}
+
+/// Number of pages that can hold `delta` new movies, always at least one so
+/// the ID check still runs when the totals happen to match.
+fn estimated_scan_pages(delta: u32, limit: u32) -> u32 {
+    compute_total_pages(delta, limit).max(1)
+}
+
+/// Converts an API movie, building magnet links from `trackers`. With `None`
+/// the magnet URLs are left empty.
+pub(crate) fn movie_from_api(api_movie: &ApiMovie, trackers: Option<&[String]>) -> Movie {
+    let torrents = api_movie
+        .torrents
+        .iter()
+        .map(|t| Torrent {
+            quality: t.quality.clone(),
+            torrent_type: t.torrent_type.clone(),
+            hash: t.hash.clone(),
+            size_bytes: match t.size_bytes {
+                0 => parse_size_str(&t.size).unwrap_or(0),
+                size_bytes => size_bytes,
+            },
+            magnet_url: trackers
+                .map(|trackers| create_magnet_url(&t.hash, &api_movie.title, trackers))
+                .unwrap_or_default(),
+            url: t.url.clone(),
+            seeds: t.seeds,
+            peers: t.peers,
+            dht_peers: None,
+        })
+        .collect();
+
+    Movie {
+        id: api_movie.id,
+        title: api_movie.title.clone(),
+        year: api_movie.year,
+        imdb_code: api_movie.imdb_code.clone(),
+        rating: api_movie.rating,
+        mpa_rating: api_movie.mpa_rating.clone(),
+        genres: api_movie.genres.clone(),
+        large_cover_image: api_movie.large_cover_image.clone(),
+        medium_cover_image: api_movie.medium_cover_image.clone(),
+        small_cover_image: api_movie.small_cover_image.clone(),
+        date_uploaded_unix: api_movie.date_uploaded_unix,
+        torrents,
+        ..Default::default()
+    }
+}
+
+/// The checkpoint lives next to the database so separate databases resume
+/// independently.
+pub fn checkpoint_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(CHECKPOINT_FILE)
+}
+
+pub fn retry_queue_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(RETRY_QUEUE_FILE)
+}
+
+/// Returns the saved checkpoint, or `None` if there is none or it cannot be
+/// parsed (in which case the fetch starts from scratch).
+pub fn load_checkpoint(path: &Path) -> Option<FetchCheckpoint> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(checkpoint) => Some(checkpoint),
+        Err(e) => {
+            say_err!("⚠️  Ignoring corrupted checkpoint {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Writes to a temporary file first so an interruption mid-write leaves the
+/// previous checkpoint intact.
+pub fn save_checkpoint(path: &Path, checkpoint: &FetchCheckpoint) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string(checkpoint)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Asks whether to resume from `checkpoint`. Without a terminal to ask on,
+/// the fetch always resumes.
+fn confirm_resume(checkpoint: &FetchCheckpoint) -> Result<bool> {
+    say!(
+        "💾 Found an interrupted fetch at page {} with {} movies.",
+        checkpoint.page,
+        checkpoint.movies_so_far.len()
+    );
+
+    if !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+
+    print!("Resume it? [Y/n] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(!matches!(answer.trim().to_lowercase().as_str(), "n" | "no"))
+}
+
+/// Header carrying the HMAC of a webhook body when a secret is configured.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-YTS-Signature";
+
+/// How long to wait before the single retry of a failed webhook delivery.
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Endpoint that `fetch --webhook-url` posts newly added movies to.
+pub struct Webhook {
+    pub client: reqwest::Client,
+    pub url: Url,
+    /// Signs each body in the [`WEBHOOK_SIGNATURE_HEADER`] header.
+    pub secret: Option<String>,
+}
+
+/// Body of a webhook delivery.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    pub new_movies: &'a [Movie],
+    /// Movies in the database after the fetch.
+    pub total_count: usize,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Lowercase hex HMAC-SHA256 of `body` keyed with `secret`, as sent after
+/// `sha256=` in the [`WEBHOOK_SIGNATURE_HEADER`] header.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::sign_payload;
+///
+/// // RFC 4231, test case 2.
+/// assert_eq!(
+///     sign_payload(b"what do ya want for nothing?", "Jefe"),
+///     "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+/// );
+///
+/// let body = br#"{"new_movies":[],"total_count":0}"#;
+/// assert_eq!(sign_payload(body, "secret"), sign_payload(body, "secret"));
+/// assert_ne!(sign_payload(body, "secret"), sign_payload(body, "other"));
+/// assert_ne!(sign_payload(body, "secret"), sign_payload(b"{}", "secret"));
+/// assert_eq!(sign_payload(b"", "").len(), 64);
+/// ```
+pub fn sign_payload(body: &[u8], secret: &str) -> String {
+    use hmac::{Hmac, Mac};
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Webhook {
+    async fn post(&self, body: &[u8]) -> Result<()> {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_vec());
+        if let Some(secret) = &self.secret {
+            request = request.header(WEBHOOK_SIGNATURE_HEADER, format!("sha256={}", sign_payload(body, secret)));
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Posts `payload`, retrying once. Delivery is best effort: a failure is
+    /// reported but never fails the fetch that triggered it.
+    pub async fn deliver(&self, payload: &WebhookPayload<'_>) {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                say_err!("⚠️  Could not encode webhook payload: {}", e);
+                return;
+            }
+        };
+
+        let mut result = self.post(&body).await;
+        if let Err(e) = &result {
+            tracing::info!("webhook delivery to {} failed ({}), retrying", self.url, e);
+            tokio::time::sleep(WEBHOOK_RETRY_DELAY).await;
+            result = self.post(&body).await;
+        }
+
+        match result {
+            Ok(()) => say!("📡 Sent {} new movies to {}", payload.new_movies.len(), self.url),
+            Err(e) => say_err!("⚠️  Webhook delivery to {} failed: {}", self.url, e),
+        }
+    }
+}
+
+/// Per-run settings for [`fetch_movies`].
+pub struct FetchOptions<'a> {
+    /// Number of pages requested in parallel.
+    pub concurrency: u32,
+    /// Above 1, pages are fetched by a rayon pool of this many threads
+    /// instead; `concurrency` then only sets how many pages the scan for new
+    /// movies requests at once.
+    pub workers: u32,
+    /// Only fetch movies uploaded on or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only fetch movies with an ID above this one. The newest stored ID is
+    /// used instead when it is higher, so an existing database never refetches.
+    pub since_id: Option<u32>,
+    /// Minimum IMDb rating (1-9) passed to the API. `None` reuses the rating
+    /// the database was fetched with, if any.
+    pub minimum_rating: Option<u8>,
+    /// Append one JSON line per fetched page to this file.
+    pub progress_file: Option<&'a Path>,
+    /// Trackers for magnet links; `None` stores empty magnet URLs.
+    pub trackers: Option<&'a [String]>,
+    /// Look up plot, director and awards of new movies on OMDb.
+    pub omdb: Option<&'a OmdbApi>,
+    /// Keep only the newest this many new movies (the first ones in the
+    /// requested order).
+    pub limit: Option<u32>,
+    /// Order the API lists movies in. Anything but `date_added desc` scans
+    /// every page instead of stopping at the newest stored movie.
+    pub sort_by: ApiSortBy,
+    pub order_by: SortOrder,
+    /// First page to fetch; earlier pages are skipped.
+    pub page_start: u32,
+    /// Last page to fetch.
+    pub page_end: Option<u32>,
+    /// Post the newly added movies here once they are saved.
+    pub webhook: Option<&'a Webhook>,
+    /// Genres passed to the API, each fetched separately and merged into the
+    /// database. Empty reuses the genres the database was fetched with, if any.
+    pub genres: &'a [String],
+}
+
+impl<'a> FetchOptions<'a> {
+    /// A plain full or incremental sync building magnets from `trackers`.
+    pub fn new(concurrency: u32, trackers: &'a [String]) -> Self {
+        FetchOptions {
+            concurrency,
+            workers: 1,
+            since: None,
+            since_id: None,
+            minimum_rating: None,
+            progress_file: None,
+            trackers: Some(trackers),
+            omdb: None,
+            limit: None,
+            sort_by: ApiSortBy::default(),
+            order_by: SortOrder::default(),
+            page_start: 1,
+            page_end: None,
+            webhook: None,
+            genres: &[],
+        }
+    }
+}
+
+/// Pages a fetch requests: from `page_start`, or the page after the
+/// checkpointed page `done` if that is later, up to `last_page` or
+/// `page_end`, whichever comes first.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::fetch_page_range;
+///
+/// // A database with 10 pages.
+/// assert_eq!(fetch_page_range(0, 10, 1, None), 1..=10);
+/// assert_eq!(fetch_page_range(0, 10, 4, None), 4..=10);
+/// assert_eq!(fetch_page_range(0, 10, 4, Some(6)), 4..=6);
+/// assert_eq!(fetch_page_range(0, 10, 1, Some(20)), 1..=10);
+/// assert_eq!(fetch_page_range(0, 10, 7, Some(7)), 7..=7);
+///
+/// // Resuming from a checkpoint after page 5.
+/// assert_eq!(fetch_page_range(5, 10, 1, None), 6..=10);
+/// assert_eq!(fetch_page_range(5, 10, 8, None), 8..=10);
+///
+/// // Nothing to fetch.
+/// assert!(fetch_page_range(0, 10, 11, None).is_empty());
+/// assert!(fetch_page_range(10, 10, 1, None).is_empty());
+/// assert!(fetch_page_range(0, 0, 1, None).is_empty());
+/// ```
+pub fn fetch_page_range(done: u32, last_page: u32, page_start: u32, page_end: Option<u32>) -> RangeInclusive<u32> {
+    let first = (done + 1).max(page_start);
+    let last = page_end.map_or(last_page, |end| end.min(last_page));
+    first..=last
+}
+
+pub async fn fetch_movies(storage: &dyn Storage, api: &YtsApi, opts: &FetchOptions<'_>) -> Result<()> {
+    say!("🎬 YTS Movie Grabber Starting...\n");
+
+    let stored_genres = load_meta(storage.path())?.map(|m| m.genres).unwrap_or_default();
+    let genres = if opts.genres.is_empty() { stored_genres.as_slice() } else { opts.genres };
+    if !stored_genres.is_empty() && genres != stored_genres {
+        say!(
+            "⚠️  The database was fetched with --genre {}; older movies will not match the new genres\n",
+            stored_genres.join(", ")
+        );
+    }
+
+    if genres.is_empty() {
+        return fetch_genre(storage, api, opts, None, genres).await;
+    }
+
+    say!("⚠️  Only fetching movies of the genres {}.", genres.join(", "));
+    say!("   Keep genre-filtered fetches in their own --output file; mixed into an unfiltered");
+    say!("   database, later fetches miss movies of other genres.\n");
+    for genre in genres {
+        say!("🎭 Fetching genre {}\n", genre);
+        fetch_genre(storage, api, opts, Some(genre), genres).await?;
+    }
+
+    Ok(())
+}
+
+/// One fetch pass, limited to `genre` if set. `genres` is every genre of the
+/// run, recorded in the meta file.
+async fn fetch_genre(
+    storage: &dyn Storage,
+    api: &YtsApi,
+    opts: &FetchOptions<'_>,
+    genre: Option<&str>,
+    genres: &[String],
+) -> Result<()> {
+    let FetchOptions {
+        concurrency,
+        since,
+        progress_file,
+        trackers,
+        omdb,
+        limit,
+        webhook,
+        ..
+    } = *opts;
+    let started = Instant::now();
+    let mut progress_writer = match progress_file {
+        Some(path) => Some(BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?)),
+        None => None,
+    };
+
+    let existing_movies = storage.load()?;
+    ensure_yts_ids(&existing_movies)?;
+    let meta = load_meta(storage.path())?;
+    let omdb_usage = meta.as_ref().and_then(|m| m.omdb_usage);
+    let stored_rating = meta.as_ref().and_then(|m| m.minimum_rating);
+    let minimum_rating = opts.minimum_rating.or(stored_rating);
+    // Movies of the other genres say nothing about how far this one got.
+    let stored_latest_id = existing_movies
+        .iter()
+        .filter(|m| genre.is_none_or(|g| genre_matches(&m.genres, g)))
+        .map(|m| m.id)
+        .max()
+        .unwrap_or(0);
+    let latest_id = stored_latest_id.max(opts.since_id.unwrap_or(0));
+    let known_ids: HashSet<u32> = existing_movies.iter().map(|m| m.id).collect();
+    let params = FetchParams {
+        sort_by: opts.sort_by,
+        order_by: opts.order_by,
+        minimum_rating,
+        genre: genre.map(str::to_string),
+        ..Default::default()
+    };
+    let cutoff = match since {
+        _ if !params.is_newest_first() => FetchCutoff::Missing(&known_ids),
+        Some(since) => FetchCutoff::Since {
+            timestamp: since.timestamp(),
+            known_ids: &known_ids,
+        },
+        None => FetchCutoff::LatestId(latest_id),
+    };
+    let incremental = (latest_id > 0 || since.is_some()) && params.is_newest_first();
+
+    say!("📊 Fetching movie count...");
+    let first_response = fetch_page(api, &params, 1).await?;
+    let total_count = first_response.data.movie_count;
+
+    say!("Total movies in YTS: {}\n", total_count);
+
+    if stored_latest_id > 0 {
+        say!("📁 Found existing database with {} movies", existing_movies.len());
+        say!("🔍 Latest movie ID in database: {}\n", stored_latest_id);
+        if opts.minimum_rating.is_some() && opts.minimum_rating != stored_rating {
+            say!("⚠️  The database was fetched with a different --min-api-rating; older movies will not match it\n");
+        }
+        if !params.is_newest_first() {
+            say!("⚠️  Movies are not sorted by date_added desc, so the fetch cannot stop at the newest stored movie;");
+            say!("   every page is scanned and only movies missing from the database are added\n");
+        }
+    }
+    if latest_id > stored_latest_id {
+        say!("🔢 Only fetching movies with an ID above {}\n", latest_id);
+    }
+    if let Some(rating) = minimum_rating {
+        say!("⭐ Only fetching movies rated {} or higher\n", rating);
+    }
+    if let Some(since) = since {
+        say!("📅 Only fetching movies uploaded since {}\n", since.format("%Y-%m-%d"));
+    }
+
+    let mut new_movie_count = 0;
+    let mut last_page = compute_total_pages(total_count, api.page_size);
+    if incremental {
+        let (new_movies, scanned) = scan_new_movies(api, &params, &cutoff, concurrency, None).await?;
+        new_movie_count = new_movies.len() as u32;
+        last_page = scanned;
+
+        if new_movie_count == 0 {
+            say!("✅ Database is up to date! No new movies to fetch.\n");
+            save_meta(storage.path(), existing_movies.len() as u32, total_count, omdb_usage, minimum_rating, genres)?;
+            return Ok(());
+        }
+
+        say!("🆕 Found {} new movies to fetch\n", new_movie_count);
+    }
+
+    let checkpoint_path = checkpoint_path(storage.path());
+    let mut checkpoint = FetchCheckpoint {
+        page_size: api.page_size,
+        minimum_rating,
+        genre: params.genre.clone(),
+        ..Default::default()
+    };
+    if let Some(saved) = load_checkpoint(&checkpoint_path) {
+        if saved.page_size != api.page_size {
+            say!("⚠️  Ignoring checkpoint written with a different --page-size ({})\n", saved.page_size);
+        } else if saved.minimum_rating != minimum_rating {
+            say!("⚠️  Ignoring checkpoint written with a different --min-api-rating\n");
+        } else if saved.genre != params.genre {
+            say!("⚠️  Ignoring checkpoint written for a different --genre\n");
+        } else if confirm_resume(&saved)? {
+            say!("⏩ Resuming from page {} with {} movies\n", saved.page + 1, saved.movies_so_far.len());
+            checkpoint = saved;
+        } else {
+            fs::remove_file(&checkpoint_path)?;
+        }
+    }
+
+    if opts.page_start > 1 {
+        say!("⚠️  Starting at page {}; movies on earlier pages are not fetched", opts.page_start);
+        say!("   and later incremental fetches will not pick them up either.\n");
+    }
+
+    let available = if incremental { new_movie_count } else { total_count };
+    let progress_total = limit.map_or(available, |limit| available.min(limit));
+    if let Some(limit) = limit {
+        // The newest movies come first, so later pages are not needed.
+        last_page = last_page.min(opts.page_start - 1 + compute_total_pages(limit, api.page_size));
+    }
+    let pb = Printer::global().progress_bar(Some(progress_total as u64), "{pos}/{len} movies");
+    pb.set_position(checkpoint.movies_so_far.len() as u64);
+
+    // Pages finish out of order; only pages contiguous with the checkpoint
+    // are moved into it so a resume never skips a page.
+    let mut pending: BTreeMap<u32, Vec<Movie>> = BTreeMap::new();
+    let pages = fetch_page_range(checkpoint.page, last_page, opts.page_start, opts.page_end);
+    // Pages before the range count as done, so the checkpoint starts there.
+    checkpoint.page = pages.start() - 1;
+    let mut last_saved_page = checkpoint.page;
+    let pages: Vec<u32> = pages.collect();
+
+    // Failed pages are queued for 'retry' and otherwise treated as empty, so
+    // the pages after them are still saved.
+    let retry_path = retry_queue_path(storage.path());
+    let mut retry_queue = RetryQueue::load(&retry_path)?;
+    let mut failed_pages = 0;
+    let mut last_error = None;
+
+    let on_page = |page, result: Result<Vec<ApiMovie>>| {
+        let movies = match result {
+            Ok(movies) => movies,
+            Err(e) => {
+                tracing::warn!("page {} failed: {}", page, e);
+                let queue = retry_queue.get_or_insert_with(|| RetryQueue::new(api.page_size, minimum_rating, genre));
+                if queue.page_size != api.page_size
+                    || queue.minimum_rating != minimum_rating
+                    || queue.genre.as_deref() != genre
+                {
+                    tracing::warn!("replacing {} failed pages queued with other fetch settings", queue.len());
+                    *queue = RetryQueue::new(api.page_size, minimum_rating, genre);
+                }
+                queue.push(page);
+                queue.save(&retry_path)?;
+                failed_pages += 1;
+                last_error = Some(e);
+                Vec::new()
+            }
+        };
+        let new_movies: Vec<Movie> = movies
+            .iter()
+            .filter(|m| cutoff.is_new(m))
+            .map(|m| movie_from_api(m, trackers))
+            .collect();
+        pb.set_position((pb.position() + new_movies.len() as u64).min(progress_total as u64));
+        if !Printer::global().shows_progress() {
+            say_err!("Fetched page {} ({} new movies)", page, new_movies.len());
+        }
+        pending.insert(page, new_movies);
+
+        while let Some(movies) = pending.remove(&(checkpoint.page + 1)) {
+            checkpoint.movies_so_far.extend(movies);
+            checkpoint.page += 1;
+        }
+        if checkpoint.page - last_saved_page >= CHECKPOINT_INTERVAL_PAGES {
+            save_checkpoint(&checkpoint_path, &checkpoint)?;
+            last_saved_page = checkpoint.page;
+        }
+
+        if let Some(writer) = progress_writer.as_mut() {
+            let event = ProgressEvent {
+                page,
+                movies_found: movies.len(),
+                elapsed_ms: started.elapsed().as_millis(),
+                total_estimated: progress_total,
+            };
+            serde_json::to_writer(&mut *writer, &event)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    };
+    if opts.workers > 1 {
+        fetch_pages_with_workers(api, &params, pages, opts.workers, on_page).await?;
+    } else {
+        fetch_pages_tolerant(api, &params, pages, concurrency, on_page).await?;
+    }
+
+    // Pages can shift while fetching, so the same movie may show up twice.
+    // --limit applies in API order, which need not be newest first.
+    let mut all_new_movies = checkpoint.movies_so_far;
+    let mut seen = HashSet::new();
+    all_new_movies.retain(|m| seen.insert(m.id));
+    if let Some(limit) = limit {
+        all_new_movies.truncate(limit as usize);
+    }
+    all_new_movies.sort_by_key(|m| Reverse(m.id));
+
+    pb.finish_with_message("✅ Fetching complete");
+    if let Some(e) = last_error {
+        say!("\n⚠️  {} pages failed (last error: {})", failed_pages, e);
+        say!("   They were saved to {}; run 'retry' to fetch them again.", retry_path.display());
+    }
+    if available > progress_total {
+        say!(
+            "\n✂️  Stopped after {} movies because of --limit; {} more were not fetched",
+            progress_total,
+            available - progress_total
+        );
+        say!("   Later fetches only pick up movies newer than these, so the skipped ones stay missing.");
+    }
+
+    let omdb_usage = match omdb {
+        Some(omdb) => {
+            let mut usage = OmdbUsage::today(omdb_usage);
+            usage.requests += enrich_with_omdb(omdb, &mut all_new_movies, usage.remaining(), concurrency).await;
+            Some(usage)
+        }
+        None => omdb_usage,
+    };
+
+    say!("\n💾 Saving to {}...", storage.path().display());
+
+    let new_count = all_new_movies.len();
+    let added = webhook.map(|_| all_new_movies.clone()).unwrap_or_default();
+    all_new_movies.extend(existing_movies);
+    all_new_movies.sort_by_key(|m| Reverse(m.id));
+
+    storage.save(&all_new_movies)?;
+    save_meta(storage.path(), all_new_movies.len() as u32, total_count, omdb_usage, minimum_rating, genres)?;
+    if checkpoint_path.exists() {
+        fs::remove_file(&checkpoint_path)?;
+    }
+
+    say!("✅ Successfully saved {} total movies!", all_new_movies.len());
+    say!("📝 File: {}", storage.path().display());
+    tracing::info!(
+        new = new_count,
+        total = all_new_movies.len(),
+        failed_pages,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "fetch complete"
+    );
+
+    if let Some(webhook) = webhook.filter(|_| !added.is_empty()) {
+        let payload = WebhookPayload {
+            new_movies: &added,
+            total_count: all_new_movies.len(),
+            fetched_at: Utc::now(),
+        };
+        webhook.deliver(&payload).await;
+    }
+
+    Ok(())
+}
+
+/// Adds the movies from `fetched` that are not in `movies` yet, keeping the
+/// newest-first order. Returns how many were added.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{merge_new_movies, Movie};
+///
+/// let movie = |id| Movie { id, ..Default::default() };
+/// let mut movies = vec![movie(30), movie(10)];
+///
+/// assert_eq!(merge_new_movies(&mut movies, vec![movie(20), movie(10), movie(20)]), 1);
+/// assert_eq!(movies.iter().map(|m| m.id).collect::<Vec<_>>(), [30, 20, 10]);
+/// ```
+pub fn merge_new_movies(movies: &mut Vec<Movie>, fetched: Vec<Movie>) -> usize {
+    let mut known: HashSet<u32> = movies.iter().map(|m| m.id).collect();
+    let before = movies.len();
+
+    movies.extend(fetched.into_iter().filter(|m| known.insert(m.id)));
+    movies.sort_by_key(|m| Reverse(m.id));
+
+    movies.len() - before
+}
+
+/// Fetches the pages recorded in [`RETRY_QUEUE_FILE`] again and adds the
+/// movies missing from the database. Pages that fail again stay queued.
+///
+/// YTS pages shift as movies are added, so a retry long after the failed
+/// fetch may no longer cover exactly the movies that were missed.
+pub async fn retry_command(storage: &dyn Storage, api: &YtsApi, trackers: &[String], concurrency: u32) -> Result<()> {
+    let retry_path = retry_queue_path(storage.path());
+    let Some(mut queue) = RetryQueue::load(&retry_path)?.filter(|q| !q.is_empty()) else {
+        say!("✅ No failed pages to retry.");
+        return Ok(());
+    };
+    let mut movies = storage.load()?;
+    ensure_yts_ids(&movies)?;
+
+    let pages = queue.pop_all();
+    say!("🔁 Retrying {} failed pages...\n", pages.len());
+
+    // Page numbers refer to the page size of the failed fetch.
+    let retry_api = YtsApi {
+        client: api.client.clone(),
+        base_url: api.base_url.clone(),
+        version: api.version,
+        max_retries: api.max_retries,
+        page_size: queue.page_size,
+        api_key: api.api_key.clone(),
+        page_cache: api.page_cache.clone(),
+    };
+    let params = FetchParams {
+        minimum_rating: queue.minimum_rating,
+        genre: queue.genre.clone(),
+        ..Default::default()
+    };
+
+    let mut fetched = Vec::new();
+    let mut last_error = None;
+    fetch_pages_tolerant(&retry_api, &params, pages, concurrency, |page, result| {
+        match result {
+            Ok(movies) => fetched.extend(movies.iter().map(|m| movie_from_api(m, Some(trackers)))),
+            Err(e) => {
+                tracing::warn!("page {} failed again: {}", page, e);
+                queue.push(page);
+                last_error = Some(e);
+            }
+        }
+        Ok(())
+    })
+    .await?;
+
+    let added = merge_new_movies(&mut movies, fetched);
+    if added > 0 {
+        storage.save(&movies)?;
+    }
+    queue.save(&retry_path)?;
+
+    say!("✅ Added {} missing movies ({} total)", added, movies.len());
+    if let Some(e) = last_error {
+        say!("⚠️  {} pages failed again (last error: {}); run 'retry' later", queue.len(), e);
+    }
+
+    Ok(())
+}
+
+/// The genres to query one at a time, or a single unfiltered pass without any.
+fn genre_passes(genres: &[String]) -> Vec<Option<&str>> {
+    if genres.is_empty() {
+        vec![None]
+    } else {
+        genres.iter().map(|g| Some(g.as_str())).collect()
+    }
+}
+
+/// What `check` found on YTS.
+#[derive(Debug, Default)]
+pub struct NewMovies {
+    pub count: u32,
+    /// Titles of the new movies, newest first. Movies of a genre with nothing
+    /// stored yet (or all movies, for an empty database) are counted but not
+    /// listed, as then every movie of it on YTS is new.
+    pub titles: Vec<String>,
+}
+
+/// Counts movies on YTS that are newer than the local database. Without
+/// `minimum_rating` the rating the database was fetched with is used. A
+/// database fetched with `--genre` is checked one genre at a time against
+/// the newest stored movie of that genre, counting movies in several genres
+/// once.
+pub async fn check_new_movies(storage: &dyn Storage, api: &YtsApi, minimum_rating: Option<u8>) -> Result<NewMovies> {
+    say!("🔍 Checking for new movies...\n");
+
+    let meta = load_meta(storage.path())?;
+    let minimum_rating = minimum_rating.or(meta.as_ref().and_then(|m| m.minimum_rating));
+    let stored_genres = meta.as_ref().map(|m| m.genres.as_slice()).unwrap_or_default();
+    let genres = genre_passes(stored_genres);
+    if let Some(meta) = &meta {
+        say!("🕒 Last sync: {} ({})", format_elapsed(meta.last_fetched_at), meta.last_fetched_at.format("%Y-%m-%d %H:%M UTC"));
+        say!("📦 Movies at last sync: {}\n", meta.movie_count);
+    }
+    if !stored_genres.is_empty() {
+        say!("🎭 Checking the genres {}\n", stored_genres.join(", "));
+    }
+
+    let existing_movies = storage.load()?;
+    ensure_yts_ids(&existing_movies)?;
+    // The stored API total is that of the last genre fetched, so it only
+    // predicts the scan when there is a single one.
+    let stored_total = meta.as_ref().and_then(|m| m.total_count).filter(|_| genres.len() == 1);
+
+    let mut found = Vec::new();
+    let mut unlisted = 0;
+    for genre in genres {
+        let params = FetchParams {
+            minimum_rating,
+            genre: genre.map(str::to_string),
+            ..Default::default()
+        };
+        let latest_id = existing_movies
+            .iter()
+            .filter(|m| genre.is_none_or(|g| genre_matches(&m.genres, g)))
+            .map(|m| m.id)
+            .max()
+            .unwrap_or(0);
+        let api_total = fetch_page(api, &params, 1).await?.data.movie_count;
+
+        if latest_id == 0 {
+            unlisted += api_total;
+            continue;
+        }
+        // With a stored API total we know roughly how many movies were added,
+        // so only that many pages need scanning.
+        let max_pages =
+            stored_total.map(|stored| estimated_scan_pages(api_total.saturating_sub(stored), api.page_size));
+        let cutoff = FetchCutoff::LatestId(latest_id);
+        found.extend(scan_new_movies(api, &params, &cutoff, DEFAULT_CONCURRENCY, max_pages).await?.0);
+    }
+
+    let mut seen = HashSet::new();
+    found.retain(|(id, _)| seen.insert(*id));
+    found.sort_by_key(|(id, _)| Reverse(*id));
+    let new_movies = NewMovies {
+        count: found.len() as u32 + unlisted,
+        titles: found.into_iter().map(|(_, title)| title).collect(),
+    };
+
+    say!("📁 Movies in local database: {}", existing_movies.len());
+    say!("🆕 New movies available: {}", new_movies.count);
+    if let Some(report) = RetryQueue::load(&retry_queue_path(storage.path()))?.and_then(|q| q.pending_report()) {
+        say!("⚠️  {}", report);
+    }
+
+    Ok(new_movies)
+}
+
+/// Whether `existing` already has the newest movie on YTS, so a fetch with
+/// `minimum_rating` and `genres` would find nothing new. Each genre is
+/// compared with the newest stored movie of that genre. Costs one request per
+/// genre; an empty database is never current.
+pub async fn is_database_current(
+    api: &YtsApi,
+    existing: &[Movie],
+    minimum_rating: Option<u8>,
+    genres: &[String],
+) -> Result<bool> {
+    ensure_yts_ids(existing)?;
+    for genre in genre_passes(genres) {
+        let latest_id = existing
+            .iter()
+            .filter(|m| genre.is_none_or(|g| genre_matches(&m.genres, g)))
+            .map(|m| m.id)
+            .max();
+        let Some(latest_id) = latest_id else {
+            return Ok(false);
+        };
+
+        let params = FetchParams {
+            minimum_rating,
+            genre: genre.map(str::to_string),
+            ..Default::default()
+        };
+        let page = fetch_page(api, &params, 1).await?;
+        let newest_id = page.data.movies.unwrap_or_default().iter().map(|m| m.id).max().unwrap_or(0);
+        if newest_id > latest_id {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// `fetch --check-before-fetch`: returns whether the fetch should go ahead,
+/// after saying so when the database is already current. Like the fetch, it
+/// falls back to the rating and genres stored in the meta file.
+pub async fn needs_fetch(
+    storage: &dyn Storage,
+    api: &YtsApi,
+    minimum_rating: Option<u8>,
+    genres: &[String],
+) -> Result<bool> {
+    let meta = load_meta(storage.path())?;
+    let minimum_rating = minimum_rating.or(meta.as_ref().and_then(|m| m.minimum_rating));
+    let stored_genres = meta.map(|m| m.genres).unwrap_or_default();
+    let genres = if genres.is_empty() { stored_genres.as_slice() } else { genres };
+
+    if is_database_current(api, &storage.load()?, minimum_rating, genres).await? {
+        say!("✅ Database is up to date! Nothing to fetch.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+pub async fn clean_command(storage: &dyn Storage, api: &YtsApi, dry_run: bool, concurrency: u32) -> Result<()> {
+    let movies = storage.load()?;
+    ensure_yts_ids(&movies)?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database.");
+        return Ok(());
+    }
+
+    say!("📊 Fetching movie count...");
+    let params = FetchParams::default();
+    let total_count = fetch_page(api, &params, 1).await?.data.movie_count;
+    let last_page = compute_total_pages(total_count, api.page_size);
+
+    say!("🔍 Collecting {} movie IDs from {} pages\n", total_count, last_page);
+
+    let pb = Printer::global().progress_bar(Some(last_page as u64), "{pos}/{len} pages");
+
+    let batch = fetch_page_batch(api, &params, (1..=last_page).collect(), concurrency, |page, _| {
+        pb.inc(1);
+        if !Printer::global().shows_progress() {
+            say_err!("Fetched page {}", page);
+        }
+        Ok(())
+    })
+    .await?;
+
+    pb.finish_and_clear();
+
+    let live_ids: HashSet<u32> = batch.iter().flat_map(|(_, movies)| movies.iter().map(|m| m.id)).collect();
+    if live_ids.is_empty() {
+        return Err(YtsError::ApiError("YTS returned no movies; refusing to clean the database".to_string()));
+    }
+
+    let (kept, removed): (Vec<Movie>, Vec<Movie>) = movies.into_iter().partition(|m| live_ids.contains(&m.id));
+
+    if removed.is_empty() {
+        say!("✅ Every movie in the database is still listed on YTS ({} movies).", kept.len());
+        return Ok(());
+    }
+
+    say!("🗑️  {} movies are no longer listed on YTS:", removed.len());
+    for movie in &removed {
+        say!("  - [ID: {}] {} ({})", movie.id, movie.title, movie.year);
+    }
+
+    if dry_run {
+        say!("\n🔎 Dry run: database left unchanged.");
+        return Ok(());
+    }
+
+    storage.save(&kept)?;
+
+    say!("\n🧹 Removed {} movies", removed.len());
+    say!("✅ {} movies remaining in {}", kept.len(), storage.path().display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{json_storage, mock_genres, movie, omdb_api, yts_api, Catalogue};
+    use crate::{JsonStorage, OMDB_DAILY_LIMIT};
+
+    fn ids(movies: &[Movie]) -> Vec<u32> {
+        movies.iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn scan_pages_cover_the_estimated_new_movies() {
+        assert_eq!(estimated_scan_pages(0, 50), 1);
+        assert_eq!(estimated_scan_pages(1, 50), 1);
+        assert_eq!(estimated_scan_pages(50, 50), 1);
+        assert_eq!(estimated_scan_pages(51, 50), 2);
+        assert_eq!(estimated_scan_pages(500, 50), 10);
+        assert_eq!(estimated_scan_pages(501, 50), 11);
+        assert_eq!(estimated_scan_pages(7, 2), 4);
+        assert_eq!(estimated_scan_pages(u32::MAX, 50), u32::MAX.div_ceil(50));
+    }
+
+    fn stored(count: u32) -> Vec<Movie> {
+        (1..=count).rev().map(|id| movie(id, "Stored", 2000)).collect()
+    }
+
+    fn sorted(mut pages: Vec<u32>) -> Vec<u32> {
+        pages.sort_unstable();
+        pages
+    }
+
+    #[tokio::test]
+    async fn check_scans_only_the_pages_the_stored_total_predicts() {
+        let catalogue = Catalogue::new(200);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(50));
+        // YTS listed 150 movies at the last sync, so about 50 are new: one page.
+        save_meta(storage.path(), 50, 150, None, None, &[]).unwrap();
+
+        let new = check_new_movies(&storage, &yts_api(&server, 50), None).await.unwrap();
+
+        assert_eq!(new.count, 50);
+        assert_eq!(new.titles.first().unwrap(), "Movie 200");
+        assert_eq!(new.titles.last().unwrap(), "Movie 151");
+        assert_eq!(catalogue.pages(), [1, 1]);
+    }
+
+    #[tokio::test]
+    async fn check_without_a_stored_total_scans_until_a_stored_movie() {
+        let catalogue = Catalogue::new(200);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(50));
+
+        let new = check_new_movies(&storage, &yts_api(&server, 50), None).await.unwrap();
+
+        assert_eq!(new.count, 150);
+        assert_eq!(sorted(catalogue.pages()), [1, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn out_of_order_pages_are_deduplicated_and_sorted() {
+        // Later pages answer first.
+        let catalogue = Catalogue::new(330).delayed(|page| Duration::from_millis(40 * (8 - page.min(8)) as u64));
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(100));
+
+        fetch_movies(&storage, &yts_api(&server, 50), &FetchOptions::new(4, &[])).await.unwrap();
+
+        let movies = storage.load().unwrap();
+        assert_eq!(ids(&movies), (1..=330).rev().collect::<Vec<_>>());
+        assert_eq!(movies[0].title, "Movie 330");
+        assert_eq!(movies[229].title, "Movie 101");
+        assert_eq!(movies[230].title, "Stored");
+        assert!(catalogue.max_in_flight() <= 4);
+        // Page 5 (movies 130-81) reaches the stored movies, so the scan stops in the batch of pages 5-8.
+        assert!(catalogue.pages().iter().all(|&page| page <= 8));
+    }
+
+    #[tokio::test]
+    async fn fetch_appends_one_progress_line_per_page() {
+        let catalogue = Catalogue::new(120);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+        let progress_path = dir.path().join("progress.ndjson");
+        fs::write(&progress_path, "{\"earlier\":true}\n").unwrap();
+
+        let opts = FetchOptions { progress_file: Some(&progress_path), ..FetchOptions::new(1, &[]) };
+        fetch_movies(&storage, &yts_api(&server, 50), &opts).await.unwrap();
+
+        let text = fs::read_to_string(&progress_path).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], serde_json::json!({ "earlier": true }));
+        for (line, (page, movies_found)) in lines[1..].iter().zip([(1, 50), (2, 50), (3, 20)]) {
+            let mut fields: Vec<&str> = line.as_object().unwrap().keys().map(String::as_str).collect();
+            fields.sort_unstable();
+            assert_eq!(fields, ["elapsed_ms", "movies_found", "page", "total_estimated"]);
+            assert_eq!(line["page"], page);
+            assert_eq!(line["movies_found"], movies_found);
+            assert_eq!(line["total_estimated"], 120);
+            assert!(line["elapsed_ms"].is_u64());
+        }
+    }
+
+    #[tokio::test]
+    async fn clean_removes_movies_no_longer_listed() {
+        // YTS dropped movies 3 and 7 but still lists 1-10 otherwise.
+        let catalogue = Catalogue::with_ids(vec![1, 2, 4, 5, 6, 8, 9, 10]);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(8));
+
+        clean_command(&storage, &yts_api(&server, 3), true, 2).await.unwrap();
+        assert_eq!(storage.load().unwrap().len(), 8);
+
+        clean_command(&storage, &yts_api(&server, 3), false, 2).await.unwrap();
+        assert_eq!(ids(&storage.load().unwrap()), [8, 6, 5, 4, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn clean_refuses_an_empty_listing() {
+        let server = Catalogue::with_ids(Vec::new()).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(3));
+
+        let err = clean_command(&storage, &yts_api(&server, 3), false, 2).await.unwrap_err();
+        assert!(matches!(err, YtsError::ApiError(_)));
+        assert_eq!(storage.load().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = checkpoint_path(&dir.path().join("yts_movies.json"));
+        assert!(load_checkpoint(&path).is_none());
+
+        let checkpoint = FetchCheckpoint {
+            page: 2,
+            page_size: 50,
+            minimum_rating: Some(7),
+            genre: Some("Action".to_string()),
+            movies_so_far: stored(3),
+        };
+        save_checkpoint(&path, &checkpoint).unwrap();
+
+        let loaded = load_checkpoint(&path).unwrap();
+        assert_eq!(loaded.page, 2);
+        assert_eq!(loaded.page_size, 50);
+        assert_eq!(loaded.minimum_rating, Some(7));
+        assert_eq!(loaded.genre.as_deref(), Some("Action"));
+        assert_eq!(ids(&loaded.movies_so_far), [3, 2, 1]);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1, "the temporary file is renamed away");
+    }
+
+    #[test]
+    fn corrupted_checkpoints_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = checkpoint_path(&dir.path().join("yts_movies.json"));
+        fs::write(&path, "{\"page\": 2, \"movies_so_far\": [").unwrap();
+
+        assert!(load_checkpoint(&path).is_none());
+    }
+
+    #[tokio::test]
+    async fn a_finished_fetch_removes_the_checkpoint() {
+        let server = Catalogue::new(60).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+        let path = checkpoint_path(storage.path());
+        // Written with another page size, so it is skipped without asking to resume.
+        let checkpoint = FetchCheckpoint { page: 1, page_size: 20, movies_so_far: stored(20), ..Default::default() };
+        save_checkpoint(&path, &checkpoint).unwrap();
+
+        fetch_movies(&storage, &yts_api(&server, 50), &FetchOptions::new(1, &[])).await.unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(storage.load().unwrap().len(), 60);
+    }
+
+    #[tokio::test]
+    async fn page_size_sets_how_many_pages_are_requested() {
+        let catalogue = Catalogue::new(7);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+
+        fetch_movies(&storage, &yts_api(&server, 2), &FetchOptions::new(2, &[])).await.unwrap();
+
+        // The movie count request, then 7 movies at 2 per page.
+        assert_eq!(sorted(catalogue.pages()), [1, 1, 2, 3, 4]);
+        assert_eq!(ids(&storage.load().unwrap()), (1..=7).rev().collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn a_successful_fetch_logs_its_requests_and_a_summary() {
+        let server = Catalogue::new(30).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(10));
+
+        fetch_movies(&storage, &yts_api(&server, 50), &FetchOptions::new(1, &[])).await.unwrap();
+
+        assert!(logs_contain("GET "));
+        assert!(logs_contain("200 OK from page 1"));
+        assert!(logs_contain("fetch complete new=20 total=30 failed_pages=0"));
+        assert!(!logs_contain("WARN"));
+    }
+
+    #[test]
+    fn retry_queues_persist_until_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = retry_queue_path(&dir.path().join("yts_movies.json"));
+        assert!(RetryQueue::load(&path).unwrap().is_none());
+
+        let mut queue = RetryQueue::new(20, None, Some("drama"));
+        queue.push(9);
+        queue.push(4);
+        queue.save(&path).unwrap();
+
+        let mut loaded = RetryQueue::load(&path).unwrap().unwrap();
+        assert_eq!((loaded.page_size, loaded.minimum_rating), (20, None));
+        assert_eq!(loaded.genre.as_deref(), Some("drama"));
+        assert_eq!(loaded.pop_all(), [4, 9]);
+
+        loaded.save(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    /// A database of the movies 1-10 except 5, 6 and 7, whose page (at 3 per
+    /// page) failed along with the next one.
+    fn database_with_failed_pages(dir: &Path) -> JsonStorage {
+        let movies: Vec<Movie> = [10, 9, 8, 4, 3, 2, 1].iter().map(|&id| movie(id, "Stored", 2000)).collect();
+        let storage = json_storage(dir, &movies);
+        let mut queue = RetryQueue::new(3, None, None);
+        queue.push(2);
+        queue.push(3);
+        queue.save(&retry_queue_path(storage.path())).unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn retry_merges_the_failed_pages_into_the_database() {
+        let catalogue = Catalogue::new(10);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = database_with_failed_pages(dir.path());
+
+        // The queue's page size applies, not the client's.
+        retry_command(&storage, &yts_api(&server, 50), &[], 2).await.unwrap();
+
+        let movies = storage.load().unwrap();
+        assert_eq!(ids(&movies), (1..=10).rev().collect::<Vec<_>>());
+        assert_eq!(movies[3].title, "Movie 7");
+        assert_eq!(movies[6].title, "Stored");
+        assert_eq!(sorted(catalogue.pages()), [2, 3]);
+        assert!(!retry_queue_path(storage.path()).exists());
+    }
+
+    #[tokio::test]
+    async fn pages_failing_again_stay_queued() {
+        let server = Catalogue::new(10).serve().await;
+        Mock::given(query_param("page", "3"))
+            .respond_with(ResponseTemplate::new(500))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = database_with_failed_pages(dir.path());
+
+        retry_command(&storage, &yts_api(&server, 50), &[], 2).await.unwrap();
+
+        assert_eq!(ids(&storage.load().unwrap()), (1..=10).rev().collect::<Vec<_>>());
+        let mut queue = RetryQueue::load(&retry_queue_path(storage.path())).unwrap().unwrap();
+        assert_eq!(queue.pop_all(), [3]);
+    }
+
+    #[tokio::test]
+    async fn since_id_stops_at_the_page_holding_that_id() {
+        let catalogue = Catalogue::new(200);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+
+        // Page 3 lists movies 160-141, so it is the last one needed.
+        let opts = FetchOptions { since_id: Some(150), ..FetchOptions::new(1, &[]) };
+        fetch_movies(&storage, &yts_api(&server, 20), &opts).await.unwrap();
+
+        assert_eq!(ids(&storage.load().unwrap()), (151..=200).rev().collect::<Vec<_>>());
+        assert_eq!(catalogue.pages().iter().max(), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn since_id_below_the_stored_movies_is_ignored() {
+        let catalogue = Catalogue::new(200);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(190));
+
+        let opts = FetchOptions { since_id: Some(150), ..FetchOptions::new(1, &[]) };
+        fetch_movies(&storage, &yts_api(&server, 20), &opts).await.unwrap();
+
+        assert_eq!(storage.load().unwrap().len(), 200);
+        assert_eq!(catalogue.pages().iter().max(), Some(&1));
+    }
+
+    /// The movies `ids` as fetched, with their genres.
+    fn stored_with_genres(ids: impl Iterator<Item = u32>) -> Vec<Movie> {
+        let with_genres = |id| Movie {
+            genres: mock_genres(id).iter().map(|g| g.to_string()).collect(),
+            ..movie(id, "Stored", 2000)
+        };
+        let mut movies: Vec<Movie> = ids.map(with_genres).collect();
+        movies.sort_by_key(|m| Reverse(m.id));
+        movies
+    }
+
+    fn genre_params(requests: &[wiremock::Request]) -> Vec<Option<String>> {
+        requests
+            .iter()
+            .map(|r| r.url.query_pairs().find(|(k, _)| k == "genre").map(|(_, v)| v.into_owned()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn check_scans_each_stored_genre_from_its_newest_movie() {
+        let server = Catalogue::new(40).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        // Action movies up to 20 and dramas up to 31 are stored.
+        let storage = json_storage(dir.path(), &stored_with_genres((1..=20).chain((21..=31).step_by(2))));
+        let genres = ["action".to_string(), "drama".to_string()];
+        save_meta(storage.path(), 26, 40, None, None, &genres).unwrap();
+
+        let new = check_new_movies(&storage, &yts_api(&server, 5), None).await.unwrap();
+
+        assert_eq!(new.count, 14);
+        assert_eq!(new.titles[..3], ["Movie 40", "Movie 39", "Movie 38"]);
+        assert_eq!(new.titles.last().unwrap(), "Movie 22");
+        let genres = genre_params(&server.received_requests().await.unwrap());
+        assert!(genres.contains(&Some("action".to_string())));
+        assert!(genres.contains(&Some("drama".to_string())));
+        assert!(genres.iter().all(Option::is_some));
+    }
+
+    #[tokio::test]
+    async fn check_counts_movies_in_several_genres_once() {
+        let server = Catalogue::new(40).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored_with_genres((2..=20).step_by(2)));
+        save_meta(storage.path(), 10, 20, None, None, &["action".to_string(), "thriller".to_string()]).unwrap();
+
+        let new = check_new_movies(&storage, &yts_api(&server, 5), None).await.unwrap();
+
+        // Movies 22-40 are both action movies and thrillers.
+        assert_eq!(new.count, 10);
+        assert_eq!(new.titles.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn genre_fetches_pass_the_genre_to_the_api() {
+        let server = Catalogue::new(10).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+        let genres = ["action".to_string()];
+
+        let opts = FetchOptions { genres: &genres, ..FetchOptions::new(1, &[]) };
+        fetch_movies(&storage, &yts_api(&server, 50), &opts).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.iter().all(|r| r.url.query().unwrap().contains("genre=action")));
+        assert_eq!(ids(&storage.load().unwrap()), [10, 8, 6, 4, 2]);
+        assert_eq!(load_meta(storage.path()).unwrap().unwrap().genres, genres);
+    }
+
+    #[tokio::test]
+    async fn database_is_current_once_the_newest_movie_is_stored() {
+        let server = Catalogue::new(3).serve().await;
+        let api = yts_api(&server, 1);
+
+        assert!(is_database_current(&api, &stored(3), None, &[]).await.unwrap());
+        assert!(!is_database_current(&api, &stored(2), None, &[]).await.unwrap());
+        assert!(!is_database_current(&api, &[], None, &[]).await.unwrap());
+        // The empty database is decided without a request.
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn database_currency_is_checked_per_genre_and_rating() {
+        let server = Catalogue::new(25).serve().await;
+        let api = yts_api(&server, 1);
+        let (action, drama) = (["action".to_string()], ["drama".to_string()]);
+        // Every action movie is stored, but of the dramas only 19 (rated 9).
+        let existing = stored_with_genres((2..=24).step_by(2).chain([19]));
+
+        assert!(is_database_current(&api, &existing, None, &action).await.unwrap());
+        assert!(!is_database_current(&api, &existing, None, &[action[0].clone(), drama[0].clone()]).await.unwrap());
+        // Dramas 21-25 are rated below 7.
+        assert!(is_database_current(&api, &existing, Some(7), &drama).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn needs_fetch_uses_the_stored_rating_and_genres() {
+        let server = Catalogue::new(25).serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored_with_genres((2..=24).step_by(2).chain([19])));
+        save_meta(storage.path(), 13, 3, None, Some(7), &["drama".to_string()]).unwrap();
+
+        assert!(!needs_fetch(&storage, &yts_api(&server, 1), None, &[]).await.unwrap());
+        assert!(needs_fetch(&storage, &yts_api(&server, 1), Some(1), &[]).await.unwrap());
+
+        let query = |r: &wiremock::Request| r.url.query().unwrap().to_string();
+        let queries: Vec<String> = server.received_requests().await.unwrap().iter().map(query).collect();
+        assert!(queries[0].contains("minimum_rating=7") && queries[0].contains("genre=drama"), "{}", queries[0]);
+        assert!(queries[1].contains("minimum_rating=1") && queries[1].contains("genre=drama"), "{}", queries[1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn workers_store_the_same_movies_as_a_sequential_fetch() {
+        let mut results = Vec::new();
+        for workers in [1, 4] {
+            // Later pages answer first, so parallel pages finish out of order.
+            let catalogue = Catalogue::new(230).delayed(|page| Duration::from_millis(20 * (6 - page.min(6)) as u64));
+            let server = catalogue.serve().await;
+            let dir = tempfile::tempdir().unwrap();
+            let storage = json_storage(dir.path(), &[]);
+            let opts = FetchOptions { workers, ..FetchOptions::new(1, &[]) };
+
+            fetch_movies(&storage, &yts_api(&server, 20), &opts).await.unwrap();
+
+            results.push((catalogue.max_in_flight(), serde_json::to_value(storage.load().unwrap()).unwrap()));
+        }
+
+        let (sequential, parallel) = (&results[0], &results[1]);
+        assert_eq!(sequential.0, 1);
+        assert_eq!(parallel.0, 4);
+        assert_eq!(sequential.1.as_array().unwrap().len(), 230);
+        assert_eq!(sequential.1, parallel.1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn workers_hand_pages_on_in_page_order() {
+        let catalogue = Catalogue::new(100).delayed(|page| Duration::from_millis(20 * (6 - page.min(6)) as u64));
+        let server = catalogue.serve().await;
+        let mut seen = Vec::new();
+
+        fetch_pages_with_workers(&yts_api(&server, 20), &FetchParams::default(), (1..=5).collect(), 3, |page, r| {
+            seen.push((page, r?.len()));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(seen, [(1, 20), (2, 20), (3, 20), (4, 20), (5, 20)]);
+        assert_eq!(catalogue.max_in_flight(), 3);
+    }
+
+    #[test]
+    fn pending_reports_list_at_most_ten_pages() {
+        let mut queue = RetryQueue::new(50, None, None);
+        queue.push(4);
+        assert_eq!(queue.pending_report().unwrap(), "1 page failed during the last fetch: 4");
+
+        for page in (1..=12).rev() {
+            queue.push(page);
+        }
+        assert_eq!(
+            queue.pending_report().unwrap(),
+            "12 pages failed during the last fetch: 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, ..."
+        );
+    }
+
+    #[test]
+    fn check_pending_reads_the_saved_queue_without_emptying_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = retry_queue_path(&dir.path().join("yts_movies.json"));
+        fs::write(&path, r#"{"page_size": 50, "pages": [2, 7]}"#).unwrap();
+
+        let queue = RetryQueue::load(&path).unwrap().unwrap();
+        assert_eq!(queue.check_pending(), [2, 7]);
+        assert_eq!(queue.check_pending(), [2, 7]);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn check_reports_the_pages_a_fetch_failed_on() {
+        let server = Catalogue::new(30).serve().await;
+        Mock::given(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(500))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+
+        fetch_movies(&storage, &yts_api(&server, 10), &FetchOptions::new(1, &[])).await.unwrap();
+        let queue = RetryQueue::load(&retry_queue_path(storage.path())).unwrap().unwrap();
+        assert_eq!(queue.pending_report().unwrap(), "1 page failed during the last fetch: 2");
+
+        // Movies 11-20 are missing from the database, but the check only
+        // looks for movies newer than the newest stored one.
+        let new = check_new_movies(&storage, &yts_api(&server, 10), None).await.unwrap();
+        assert_eq!(new.count, 0);
+        assert_eq!(queue.check_pending(), [2]);
+    }
+
+    #[tokio::test]
+    async fn omdb_lookups_stop_at_the_daily_budget_stored_in_meta() {
+        let catalogue = Catalogue::new(8);
+        let server = catalogue.serve().await;
+        Mock::given(wiremock::matchers::path("/omdb/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "Response": "True" })))
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &stored(5));
+        let usage = OmdbUsage { date: Utc::now().date_naive(), requests: OMDB_DAILY_LIMIT - 1 };
+        save_meta(storage.path(), 5, 5, Some(usage), None, &[]).unwrap();
+        let omdb = omdb_api(&server);
+        let opts = FetchOptions { omdb: Some(&omdb), ..FetchOptions::new(1, &[]) };
+        let omdb_requests = || async {
+            let requests = server.received_requests().await.unwrap();
+            requests.iter().filter(|r| r.url.path() == "/omdb/").count()
+        };
+
+        fetch_movies(&storage, &yts_api(&server, 20), &opts).await.unwrap();
+        assert_eq!(omdb_requests().await, 1);
+        let usage = load_meta(storage.path()).unwrap().unwrap().omdb_usage.unwrap();
+        assert_eq!(usage.requests, OMDB_DAILY_LIMIT);
+
+        // With the budget used up, later fetches look nothing up.
+        catalogue.add([9, 10]);
+        fetch_movies(&storage, &yts_api(&server, 20), &opts).await.unwrap();
+        assert_eq!(ids(&storage.load().unwrap())[..2], [10, 9]);
+        assert_eq!(omdb_requests().await, 1);
+        assert_eq!(load_meta(storage.path()).unwrap().unwrap().omdb_usage.unwrap().requests, OMDB_DAILY_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn limits_stop_fetching_within_the_page_they_end_on() {
+        let catalogue = Catalogue::new(30);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+        let opts = FetchOptions { limit: Some(15), ..FetchOptions::new(1, &[]) };
+
+        fetch_movies(&storage, &yts_api(&server, 10), &opts).await.unwrap();
+
+        assert_eq!(ids(&storage.load().unwrap()), (16..=30).rev().collect::<Vec<_>>());
+        assert!(!catalogue.pages().contains(&3));
+    }
+
+    #[tokio::test]
+    async fn since_stops_at_the_first_page_with_older_movies() {
+        let catalogue = Catalogue::new(30);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+        // The mock uploads movie `id` on day `id` after 1_600_000_000.
+        let since = DateTime::from_timestamp(1_600_000_000 + 15 * 86_400, 0).unwrap();
+        let opts = FetchOptions { since: Some(since), ..FetchOptions::new(1, &[]) };
+
+        fetch_movies(&storage, &yts_api(&server, 10), &opts).await.unwrap();
+
+        assert_eq!(ids(&storage.load().unwrap()), (15..=30).rev().collect::<Vec<_>>());
+        assert!(!catalogue.pages().contains(&3), "{:?}", catalogue.pages());
+    }
+}
@@ -0,0 +1,307 @@
+//! Predicates and orderings shared by the commands that select movies.
+
+use std::cmp::Reverse;
+
+use clap::{Args, ValueEnum};
+
+use crate::{has_tag, Movie, Torrent};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SortField {
+    Id,
+    Title,
+    Year,
+    /// Size of the largest torrent
+    Size,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct FilterOpts {
+    /// Torrent quality to match, e.g. "2160p" or "1080p-bluray"
+    #[arg(short, long)]
+    pub quality: Option<String>,
+
+    /// Earliest release year (inclusive)
+    #[arg(long)]
+    pub min_year: Option<u32>,
+
+    /// Latest release year (inclusive)
+    #[arg(long)]
+    pub max_year: Option<u32>,
+
+    /// Minimum torrent size in GB
+    #[arg(long)]
+    pub min_size_gb: Option<f64>,
+
+    /// Maximum torrent size in GB
+    #[arg(long)]
+    pub max_size_gb: Option<f64>,
+
+    /// Minimum IMDb rating
+    #[arg(long)]
+    pub min_rating: Option<f32>,
+
+    /// Genre the movie must be listed under, e.g. "Horror"
+    #[arg(short, long)]
+    pub genre: Option<String>,
+
+    /// Torrent type to match, e.g. "web" or "bluray"
+    #[arg(short, long)]
+    pub torrent_type: Option<String>,
+
+    /// Tag the movie must have
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+impl FilterOpts {
+    pub fn matches(&self, movie: &Movie) -> bool {
+        if self.min_year.is_some_and(|y| movie.year < y) || self.max_year.is_some_and(|y| movie.year > y) {
+            return false;
+        }
+
+        if self.min_rating.is_some_and(|r| movie.rating < r) {
+            return false;
+        }
+
+        if self.genre.as_deref().is_some_and(|g| !genre_matches(&movie.genres, g)) {
+            return false;
+        }
+
+        if self.tag.as_deref().is_some_and(|t| !has_tag(movie, t)) {
+            return false;
+        }
+
+        movie.torrents.iter().any(|t| self.torrent_matches(t))
+    }
+
+    pub fn torrent_matches(&self, torrent: &Torrent) -> bool {
+        const GB: f64 = (1024 * 1024 * 1024) as f64;
+        let size_gb = torrent.size_bytes as f64 / GB;
+
+        self.quality.as_deref().is_none_or(|q| quality_matches(&torrent.label(), q))
+            && self.torrent_type.as_deref().is_none_or(|t| torrent.torrent_type.eq_ignore_ascii_case(t))
+            && self.min_size_gb.is_none_or(|min| size_gb >= min)
+            && self.max_size_gb.is_none_or(|max| size_gb <= max)
+    }
+}
+
+/// Matches a stored "{quality}-{type}" string against either a bare quality
+/// ("1080p") or the full combined form ("1080p-web"), case-insensitively.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::quality_matches;
+///
+/// assert!(quality_matches("1080p-web", "1080p"));
+/// assert!(quality_matches("1080p-bluray", "1080P-BluRay"));
+/// assert!(!quality_matches("1080p-web", "1080p-bluray"));
+/// ```
+pub fn quality_matches(torrent_quality: &str, wanted: &str) -> bool {
+    let torrent_quality = torrent_quality.to_lowercase();
+    let wanted = wanted.to_lowercase();
+
+    torrent_quality == wanted || torrent_quality.starts_with(&format!("{}-", wanted))
+}
+
+/// True if any of the movie's genres equals `wanted`, ignoring case.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::genre_matches;
+///
+/// let genres = vec!["Action".to_string(), "Sci-Fi".to_string()];
+/// assert!(genre_matches(&genres, "sci-fi"));
+/// assert!(!genre_matches(&genres, "Horror"));
+/// ```
+pub fn genre_matches(genres: &[String], wanted: &str) -> bool {
+    genres.iter().any(|g| g.eq_ignore_ascii_case(wanted))
+}
+
+/// Returns the movies matching every constraint set in `opts`.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{filter_movies, FilterOpts, Movie, Torrent};
+///
+/// let movies = vec![
+///     Movie {
+///         id: 2,
+///         title: "Dune".into(),
+///         year: 2021,
+///         torrents: vec![Torrent { quality: "2160p".into(), torrent_type: "web".into(), ..Default::default() }],
+///         ..Default::default()
+///     },
+///     Movie {
+///         id: 1,
+///         title: "Heat".into(),
+///         year: 1995,
+///         torrents: vec![Torrent { quality: "1080p".into(), torrent_type: "bluray".into(), ..Default::default() }],
+///         ..Default::default()
+///     },
+/// ];
+///
+/// let opts = FilterOpts { quality: Some("2160p".into()), ..Default::default() };
+/// let matches = filter_movies(&movies, &opts);
+///
+/// assert_eq!(matches.len(), 1);
+/// assert_eq!(matches[0].title, "Dune");
+/// ```
+pub fn filter_movies<'a>(movies: &'a [Movie], opts: &FilterOpts) -> Vec<&'a Movie> {
+    movies.iter().filter(|m| opts.matches(m)).collect()
+}
+
+/// Stable sort, so movies with equal keys keep their existing order.
+pub fn sort_movies(movies: &mut [Movie], by: SortField, asc: bool) {
+    movies.sort_by(|a, b| {
+        let ordering = match by {
+            SortField::Id => a.id.cmp(&b.id),
+            SortField::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortField::Year => a.year.cmp(&b.year),
+            SortField::Size => largest_torrent_size(a).cmp(&largest_torrent_size(b)),
+        };
+
+        if asc {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    });
+}
+
+/// Case-insensitive title match, either on the whole title or a substring.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::title_matches;
+///
+/// assert!(title_matches("The Matrix Reloaded", "matrix", false));
+/// assert!(!title_matches("The Matrix Reloaded", "the matrix", true));
+/// assert!(title_matches("The Matrix", "THE MATRIX", true));
+/// ```
+pub fn title_matches(title: &str, query: &str, exact: bool) -> bool {
+    let title_lower = title.to_lowercase();
+    let query_lower = query.to_lowercase();
+
+    if exact {
+        title_lower == query_lower
+    } else {
+        title_lower.contains(&query_lower)
+    }
+}
+
+/// Movies without any torrent matching `quality` (see [`quality_matches`]).
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{movies_missing_quality, Movie, Torrent};
+///
+/// let movie = |id, labels: &[(&str, &str)]| Movie {
+///     id,
+///     torrents: labels
+///         .iter()
+///         .map(|(q, t)| Torrent { quality: q.to_string(), torrent_type: t.to_string(), ..Default::default() })
+///         .collect(),
+///     ..Default::default()
+/// };
+/// let movies = vec![
+///     movie(1, &[("720p", "bluray")]),
+///     movie(2, &[("720p", "web"), ("1080p", "web")]),
+///     movie(3, &[("1080p", "bluray"), ("2160p", "web")]),
+///     movie(4, &[]),
+/// ];
+///
+/// let ids = |found: Vec<&Movie>| found.iter().map(|m| m.id).collect::<Vec<_>>();
+/// assert_eq!(ids(movies_missing_quality(&movies, "1080p")), [1, 4]);
+/// assert_eq!(ids(movies_missing_quality(&movies, "1080p-bluray")), [1, 2, 4]);
+/// assert_eq!(ids(movies_missing_quality(&movies, "720p")), [3, 4]);
+/// ```
+pub fn movies_missing_quality<'a>(movies: &'a [Movie], quality: &str) -> Vec<&'a Movie> {
+    movies
+        .iter()
+        .filter(|m| !m.torrents.iter().any(|t| quality_matches(&t.label(), quality)))
+        .collect()
+}
+
+pub(crate) fn largest_torrent_size(movie: &Movie) -> u64 {
+    movie.torrents.iter().map(|t| t.size_bytes).max().unwrap_or(0)
+}
+
+/// Sorts by id descending and keeps the first occurrence of each id.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{dedup_movies, Movie};
+///
+/// let movies = vec![
+///     Movie { id: 1, title: "Heat".into(), ..Default::default() },
+///     Movie { id: 2, title: "Dune".into(), ..Default::default() },
+///     Movie { id: 1, title: "Heat (copy)".into(), ..Default::default() },
+/// ];
+///
+/// let ids: Vec<u32> = dedup_movies(movies).iter().map(|m| m.id).collect();
+/// assert_eq!(ids, [2, 1]);
+/// ```
+pub fn dedup_movies(mut movies: Vec<Movie>) -> Vec<Movie> {
+    movies.sort_by_key(|m| Reverse(m.id));
+    movies.dedup_by_key(|m| m.id);
+    movies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{movie, torrent};
+
+    fn movies() -> Vec<Movie> {
+        let mut heat = movie(2, "heat", 1995);
+        heat.torrents.push(torrent("2160p", 9_000_000_000));
+        vec![movie(3, "Ronin", 1998), heat, movie(5, "Alien", 1979), movie(4, "Casino", 1995)]
+    }
+
+    fn sorted_ids(by: SortField, asc: bool) -> Vec<u32> {
+        let mut movies = movies();
+        sort_movies(&mut movies, by, asc);
+        movies.iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn sort_by_id() {
+        assert_eq!(sorted_ids(SortField::Id, true), [2, 3, 4, 5]);
+        assert_eq!(sorted_ids(SortField::Id, false), [5, 4, 3, 2]);
+    }
+
+    #[test]
+    fn sort_by_title_ignores_case() {
+        assert_eq!(sorted_ids(SortField::Title, true), [5, 4, 2, 3]);
+        assert_eq!(sorted_ids(SortField::Title, false), [3, 2, 4, 5]);
+    }
+
+    #[test]
+    fn sort_by_year_keeps_ties_in_order() {
+        // Heat (2) comes before Casino (4) in the input, both from 1995.
+        assert_eq!(sorted_ids(SortField::Year, true), [5, 2, 4, 3]);
+        assert_eq!(sorted_ids(SortField::Year, false), [3, 2, 4, 5]);
+    }
+
+    #[test]
+    fn sort_by_size_uses_the_largest_torrent() {
+        // The fixture torrents grow with the id; Heat also has a 9 GB one.
+        assert_eq!(sorted_ids(SortField::Size, true), [3, 4, 5, 2]);
+        assert_eq!(sorted_ids(SortField::Size, false), [2, 5, 4, 3]);
+    }
+
+    #[test]
+    fn sort_by_size_puts_movies_without_torrents_first() {
+        let mut movies = movies();
+        movies[0].torrents.clear();
+        sort_movies(&mut movies, SortField::Size, true);
+        assert_eq!(movies[0].id, 3);
+    }
+}
@@ -0,0 +1,53 @@
+//! Fetching, storage, and query helpers for a local YTS movie database.
+//!
+//! The `yts-movie-scraper` binary is a thin CLI over this crate; everything it
+//! does is available here for use from other Rust programs.
+
+/// `println!` through the global [`Printer`].
+macro_rules! say {
+    () => {
+        $crate::Printer::global().println("")
+    };
+    ($($arg:tt)*) => {
+        $crate::Printer::global().println(&format!($($arg)*))
+    };
+}
+
+/// `eprintln!` through the global [`Printer`].
+macro_rules! say_err {
+    ($($arg:tt)*) => {
+        $crate::Printer::global().eprintln(&format!($($arg)*))
+    };
+}
+
+mod api;
+mod commands;
+mod config;
+mod dht;
+mod enrich;
+mod error;
+mod fetch;
+mod filters;
+mod model;
+mod output;
+pub mod report;
+mod storage;
+#[cfg(test)]
+mod test_support;
+mod torrent;
+pub mod tui;
+mod watch;
+
+pub use api::*;
+pub use commands::*;
+pub use config::*;
+pub use dht::*;
+pub use enrich::*;
+pub use error::*;
+pub use fetch::*;
+pub use filters::*;
+pub use model::*;
+pub use output::*;
+pub use storage::*;
+pub use torrent::*;
+pub use watch::*;
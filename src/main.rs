@@ -1,18 +1,107 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use indicatif::{ProgressBar, ProgressStyle};
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::Path;
-
-const API_BASE: &str = "https://yts.bz/api/v2/list_movies.json";
-const OUTPUT_FILE: &str = "yts_movies.json";
-const FETCH_LIMIT: u32 = 50;
+use chrono::{DateTime, Utc};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+use yts_movie_scraper::{
+    backup_command, build_client, calculate_size, check_new_movies, clean_command, config_init, config_show,
+    count_movies, dedup_command, diff_command, download_all_command, download_images, download_torrent_file,
+    enrich_tmdb_command, export_movies, fetch_movies, filter_command, fix_ids_command, hash_check_command,
+    hash_lookup_command, info_command, lint_command, list_movies, load_config, magnet_batch_command, merge_command,
+    needs_fetch, notify_new_movies, page_cache_path, peer_discovery_command, plot_command, print_magnets, prune_command,
+    quality_report_command, random_command, recent_command, regenerate_magnets_command, remote_search, report,
+    retry_command, schema_command, search_movies, seeding_command, send_command, show_stats, size_by_quality_command,
+    strip_command, summary_command, sync_command, tag_add, tag_list, tag_remove, top_command, top_seeders_command,
+    torrent_info_command, validate_command, watch_movies, watch_new_command, watchlist_add, watchlist_remove,
+    watchlist_show, ApiSortBy, ApiVersion, ConfigOverrides, CountOpts, CoverGridOpts, CoverSize, DownloadAllOpts,
+    ExportFormat, FetchOptions, FetchParams, FilterOpts, HttpTimeouts, ListArgs, LogLevel, MagnetBatchOpts, MovieKey,
+    NotifyOpts, OmdbApi, PageCache, PlotField, Printer, PruneCutoff, QualitySelector, RandomOpts, RecentOpts, SortOrder,
+    StorageBackend, StripField, TmdbApi, TopOpts, TransmissionOpts, WatchFormat, WatchOptions, Webhook, YtsApi,
+    DEFAULT_BACKUP_KEEP, DEFAULT_CONNECT_TIMEOUT_SECS, DEFAULT_MAX_RETRIES, DEFAULT_SUMMARY_FORMAT,
+    DEFAULT_TIMEOUT_SECS, MAX_PAGE_SIZE, OMDB_API_BASE, PROXY_SCHEMES, TMDB_API_BASE,
+};
 
 #[derive(Parser)]
 #[command(name = "YTS Movie Scraper")]
 #[command(about = "A toolkit for managing YTS movie database", long_about = None)]
 struct Cli {
+    /// Path of the local movie database file [default: yts_movies.json, or yts_movies.db for sqlite]
+    #[arg(short, long, global = true, env = "YTS_OUTPUT_FILE")]
+    output: Option<PathBuf>,
+
+    /// Storage backend for the local database
+    #[arg(long, global = true, value_enum, default_value_t = StorageBackend::Json)]
+    backend: StorageBackend,
+
+    /// Base URL of the YTS list_movies endpoint, e.g. for a mirror [default: https://yts.bz/api/v2/list_movies.json]
+    #[arg(long, global = true, env = "YTS_API_URL", value_parser = parse_api_url)]
+    api_url: Option<Url>,
+
+    /// YTS API version, which picks the default --api-url and the response format
+    #[arg(long, global = true, value_enum, default_value_t = ApiVersion::V2)]
+    api_version: ApiVersion,
+
+    /// API key for mirrors that require one, sent in the X-API-Key header
+    #[arg(long, global = true, env = "YTS_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Route requests through a proxy, e.g. socks5://127.0.0.1:1080
+    #[arg(long, global = true, env = "YTS_PROXY", hide_env_values = true, value_parser = parse_proxy_url)]
+    proxy: Option<Url>,
+
+    /// Ignore --proxy, YTS_PROXY and system proxy settings and connect directly
+    #[arg(long, global = true)]
+    no_proxy: bool,
+
+    /// Seconds a single HTTP request may take, including reading the response
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_TIMEOUT_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    timeout: u64,
+
+    /// Seconds to wait for a connection to be established
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_CONNECT_TIMEOUT_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    connect_timeout: u64,
+
+    /// Retries per page when YTS responds with 429 or a server error
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_RETRIES)]
+    max_retries: u8,
+
+    /// Movies requested per API page (1-50); larger pages mean fewer HTTP round-trips [default: 50]
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..=MAX_PAGE_SIZE as i64))]
+    page_size: Option<u32>,
+
+    /// Log HTTP requests and timings (set RUST_LOG=trace to also log response bodies)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Diagnostic log level, written to stderr [default: warn, or debug with --verbose]
+    #[arg(long, global = true, value_enum)]
+    log_level: Option<LogLevel>,
+
+    /// Plain output without emoji or colours (also enabled by the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Hide progress bars, printing a line per fetched page to stderr instead (for scripts and CI)
+    #[arg(long, global = true)]
+    no_progress: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -20,375 +109,1062 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Fetch new movies from YTS (default action)
-    Fetch,
+    Fetch {
+        /// Number of pages to request in parallel [default: 4, or concurrency from the config file]
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: Option<u32>,
+
+        /// Fetch pages on this many rayon threads instead; 1 keeps the
+        /// --concurrency setting
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        workers: u32,
+
+        /// Only fetch movies uploaded on or after this date (e.g. 2024-01-01)
+        #[arg(long, value_parser = parse_since_date)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only fetch movies with an ID above this one, e.g. for a new or swapped
+        /// database; the newest stored ID still wins if it is higher
+        #[arg(long, value_name = "ID", conflicts_with_all = ["since", "sort_by", "order_by"])]
+        since_id: Option<u32>,
+
+        /// Append one JSON line per fetched page to this file
+        #[arg(long)]
+        progress_file: Option<PathBuf>,
+
+        /// Store empty magnet URLs (rebuild them later with 'regenerate-magnets')
+        #[arg(long)]
+        no_magnet: bool,
+
+        /// Add plot, director and awards from OMDb using this API key
+        #[arg(long, value_name = "API_KEY")]
+        enrich_omdb: Option<String>,
+
+        /// OMDb API endpoint
+        #[arg(long, default_value = OMDB_API_BASE, value_parser = parse_api_url)]
+        omdb_url: Url,
+
+        /// Back up the existing database before fetching
+        #[arg(long)]
+        auto_backup: bool,
+
+        /// Exit without writing anything when the newest movie on YTS is already stored (one request)
+        #[arg(long, alias = "skip-if-current")]
+        check_before_fetch: bool,
+
+        /// Only fetch movies with at least this IMDb rating (1-9); remembered for later fetches
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=9))]
+        min_api_rating: Option<u8>,
+
+        /// Only fetch movies of this genre, e.g. "comedy"; repeat for several; remembered for later fetches
+        #[arg(long)]
+        genre: Vec<String>,
+
+        /// Stop after adding this many new movies (the newest ones)
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+        limit: Option<u32>,
+
+        /// Order YTS lists movies in [default: date_added]
+        #[arg(long, value_enum, conflicts_with = "since")]
+        sort_by: Option<ApiSortBy>,
+
+        /// Sort direction [default: desc]
+        #[arg(long, value_enum, conflicts_with = "since")]
+        order_by: Option<SortOrder>,
+
+        /// Start at this page, e.g. to pick up where a failed fetch stopped
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        page_start: u32,
+
+        /// Stop after this page
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        page_end: Option<u32>,
+
+        /// POST the newly added movies as JSON to this URL after saving
+        #[arg(long, value_name = "URL", value_parser = parse_api_url)]
+        webhook_url: Option<Url>,
+
+        /// Sign webhook bodies with HMAC-SHA256 in the X-YTS-Signature header
+        #[arg(
+            long,
+            value_name = "SECRET",
+            requires = "webhook_url",
+            env = "YTS_WEBHOOK_SECRET",
+            hide_env_values = true
+        )]
+        webhook_secret: Option<String>,
+
+        /// Keep page responses in .yts_page_cache.json and revalidate them with ETag / Last-Modified
+        #[arg(long)]
+        page_cache: bool,
+    },
+
+    /// Fetch the pages that failed during the last 'fetch' again
+    Retry {
+        /// Number of pages to request in parallel [default: 4, or concurrency from the config file]
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: Option<u32>,
+
+        /// Keep page responses in .yts_page_cache.json and revalidate them with ETag / Last-Modified
+        #[arg(long)]
+        page_cache: bool,
+    },
     
     /// List movies from the local database
-    List {
+    List(ListArgs),
+    
+    /// Search the local database by title keyword
+    Search {
+        /// Title keyword to search for (case-insensitive)
+        #[arg(short, long)]
+        query: String,
+
+        /// Only show movies released in this year
+        #[arg(short, long)]
+        year: Option<u32>,
+
+        /// Require the whole title to match instead of a substring
+        #[arg(long)]
+        exact: bool,
+    },
+    
+    /// Search YTS directly without touching the local database
+    RemoteSearch {
+        /// Title keyword to search for
+        #[arg(short, long)]
+        query: String,
+
+        /// Only movies listed under this genre, e.g. "Horror"
+        #[arg(short, long)]
+        genre: Option<String>,
+
+        /// Minimum IMDb rating (0-9)
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=9))]
+        min_rating: Option<u8>,
+
+        /// Number of results to show (0 = all)
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Print magnet links for movies matching a title query, one per line
+    Magnet {
+        /// Title keyword to search for (case-insensitive)
+        #[arg(short, long)]
+        query: String,
+
+        /// Torrent quality to pick, e.g. "1080p" or "2160p-bluray"
+        #[arg(long, default_value = "1080p")]
+        quality: String,
+
+        /// Torrent type to pick, e.g. "web" or "bluray"
+        #[arg(short, long)]
+        torrent_type: Option<String>,
+
+        /// Print every torrent matching the quality instead of just the first
+        #[arg(short, long)]
+        all: bool,
+    },
+
+    /// Write the magnet links of matching movies to a batch file for torrent clients
+    MagnetBatch(MagnetBatchOpts),
+
+    /// Run a command, e.g. a torrent client, with the magnet link of every matching movie
+    DownloadAll(DownloadAllOpts),
+
+    /// Add a movie's magnet link to Transmission over its RPC interface
+    Send {
+        /// ID of the movie
+        #[arg(short, long)]
+        id: u32,
+
+        /// Torrent quality to send, e.g. "1080p" or "2160p-bluray"
+        #[arg(long, default_value = "1080p")]
+        quality: String,
+
+        #[command(flatten)]
+        transmission: TransmissionOpts,
+    },
+
+    /// Show every stored detail of a single movie, including full magnet links
+    Info {
+        /// ID of the movie
+        #[arg(short, long, required_unless_present = "imdb", conflicts_with = "imdb")]
+        id: Option<u32>,
+
+        /// IMDb code of the movie, e.g. "tt0113277"
+        #[arg(long)]
+        imdb: Option<String>,
+
+        /// Also open the movie's IMDb page in the browser (prints the URL without a desktop)
+        #[arg(long)]
+        imdb_link: bool,
+    },
+    
+    /// List movies that lack a torrent quality, optionally only those having another one
+    #[command(group(clap::ArgGroup::new("wanted").required(true).args(["missing", "want"])))]
+    QualityReport {
+        /// Quality the movies should have, e.g. "1080p-bluray"
+        #[arg(long, requires = "have")]
+        want: Option<String>,
+
+        /// Only list movies that do have this quality, e.g. "720p"
+        #[arg(long, requires = "want")]
+        have: Option<String>,
+
+        /// List every movie without this quality
+        #[arg(long, conflicts_with_all = ["want", "have"])]
+        missing: Option<String>,
+
+        /// Number of movies to display (0 = all)
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Filter the local database by quality, size range, and year range
+    Filter {
+        #[command(flatten)]
+        opts: FilterOpts,
+
         /// Number of movies to display (0 = all)
         #[arg(short, long, default_value_t = 10)]
         limit: usize,
     },
     
-    /// Count movies in the database
-    Count,
+    /// Check how many new movies are available on YTS without downloading them
+    Check {
+        /// Only count movies with at least this IMDb rating (1-9) [default: the rating used by 'fetch']
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=9))]
+        min_api_rating: Option<u8>,
+
+        /// Also fetch the pages that failed during the last fetch again, like 'retry'
+        #[arg(long)]
+        retry_failed: bool,
+
+        #[command(flatten)]
+        notify: NotifyOpts,
+    },
     
-    /// Calculate total size of all movies (uses largest torrent per movie)
-    Size,
+    /// Poll YTS for new movies on a schedule
+    Watch {
+        /// Minutes to wait between polls
+        #[arg(short, long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        /// Fetch new movies as soon as they are found
+        #[arg(long)]
+        auto_fetch: bool,
+
+        /// Number of pages to request in parallel when fetching [default: 4, or concurrency from the config file]
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: Option<u32>,
+
+        #[command(flatten)]
+        notify: NotifyOpts,
+    },
     
+    /// Print each new movie on YTS as it appears, like `tail -f`
+    WatchNew {
+        /// Seconds to wait between polls
+        #[arg(short, long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        /// How to print the movies
+        #[arg(short, long, value_enum, default_value_t = WatchFormat::Info)]
+        format: WatchFormat,
+    },
+
+    /// Print the number of matching movies, and nothing else
+    Count(CountOpts),
+
+    /// Print a one-line summary from the last sync, for status bars
+    Summary {
+        /// Template with {count}, {last_sync} and {new_estimate} placeholders
+        #[arg(long, default_value = DEFAULT_SUMMARY_FORMAT)]
+        format: String,
+
+        /// Print a JSON object instead
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    
+    /// Calculate total size of all movies at a given quality
+    Size {
+        /// Torrent quality to count for each movie
+        #[arg(short, long, default_value = "1080p")]
+        quality: String,
+
+        /// Count the largest torrent for movies without the requested quality
+        #[arg(short, long)]
+        fallback_largest: bool,
+
+        /// Count the largest torrent of every movie, whatever its quality
+        #[arg(long, conflicts_with_all = ["quality", "fallback_largest"])]
+        largest: bool,
+    },
+    
+    /// Show the combined size of every movie in each torrent quality, largest first
+    SizeByQuality,
+
     /// Show statistics about the database
     Stats,
-}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Torrent {
-    quality: String,
-    hash: String,
-    size_bytes: u64,
-    magnet_url: String,
-}
+    /// Write a shareable HTML page with stats, charts and a searchable table of all movies
+    Report {
+        /// Path of the HTML file to write (-o/--output names the database)
+        #[arg(default_value = "report.html")]
+        path: PathBuf,
+    },
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Movie {
-    id: u32,
-    title: String,
-    year: u32,
-    imdb_code: String,
-    torrents: Vec<Torrent>,
-}
+    /// Write an HTML page with a grid of movie posters linking to IMDb
+    CoverGrid(CoverGridOpts),
 
-#[derive(Debug, Deserialize)]
-struct ApiMovie {
-    id: u32,
-    title: String,
-    year: u32,
-    imdb_code: String,
-    torrents: Vec<ApiTorrent>,
-}
+    /// Draw a bar chart of movies per year, genre, quality or rating
+    Plot {
+        /// What to count movies by
+        #[arg(short, long, value_enum, default_value_t = PlotField::Year)]
+        field: PlotField,
 
-#[derive(Debug, Deserialize)]
-struct ApiTorrent {
-    quality: String,
-    #[serde(rename = "type")]
-    torrent_type: String,
-    hash: String,
-    size_bytes: u64,
-}
+        /// Length of the longest bar in characters
+        #[arg(short, long, default_value_t = 50, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        width: usize,
+    },
 
-#[derive(Debug, Deserialize)]
-struct ApiData {
-    movie_count: u32,
-    movies: Option<Vec<ApiMovie>>,
-}
+    /// Remove duplicate movie IDs from the local database
+    #[command(alias = "deduplicate")]
+    Dedup {
+        /// Keep a timestamped copy of the original file before rewriting it
+        #[arg(short, long)]
+        backup: bool,
+    },
 
-#[derive(Debug, Deserialize)]
-struct ApiResponse {
-    data: ApiData,
-}
+    /// Remove movies released or uploaded before a cut-off from the local database
+    #[command(group = clap::ArgGroup::new("cutoff").required(true))]
+    Prune {
+        /// Remove movies released before this year
+        #[arg(long, alias = "older-than", value_name = "YEAR", group = "cutoff")]
+        before_year: Option<u32>,
+
+        /// Remove movies uploaded to YTS before this date (e.g. 2020-01-01); movies
+        /// without an upload time are kept
+        #[arg(long, value_name = "DATE", value_parser = parse_since_date, group = "cutoff")]
+        before_date: Option<DateTime<Utc>>,
+
+        /// Show what would be removed without changing the database
+        #[arg(long)]
+        dry_run: bool,
+    },
 
-fn create_magnet_url(hash: &str, title: &str) -> String {
-    let encoded_title = title.replace(' ', "+");
-    format!(
-        "magnet:?xt=urn:btih:{}&dn={}&tr=udp://open.demonii.com:1337/announce&tr=udp://tracker.openbittorrent.com:80&tr=udp://tracker.coppersurfer.tk:6969&tr=udp://glotorrents.pw:6969/announce&tr=udp://tracker.opentrackr.org:1337/announce&tr=udp://torrent.gresille.org:80/announce&tr=udp://p4p.arenabg.com:1337&tr=udp://tracker.leechers-paradise.org:6969",
-        hash, encoded_title
-    )
-}
+    /// Copy the database to a timestamped backup file
+    Backup {
+        /// Directory to write backups to (default: next to the database)
+        #[arg(long)]
+        backup_dir: Option<PathBuf>,
+
+        /// Number of most recent backups to keep
+        #[arg(
+            short,
+            long,
+            default_value_t = DEFAULT_BACKUP_KEEP,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        keep: usize,
+    },
 
-fn fetch_page(page: u32) -> Result<ApiResponse> {
-    let url = format!(
-        "{}?limit={}&page={}&sort_by=date_added&order_by=desc",
-        API_BASE, FETCH_LIMIT, page
-    );
+    /// Show the highest-rated movies in the local database
+    Top(TopOpts),
 
-    let response = reqwest::blocking::get(&url)?.json::<ApiResponse>()?;
+    /// Show movies by the seed count of their best-seeded torrent
+    Seeding {
+        /// Only show movies with a torrent that has at least this many seeds
+        #[arg(long, default_value_t = 1)]
+        min_seeds: u32,
 
-    Ok(response)
-}
+        /// Number of movies to show (0 = all)
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
 
-fn load_existing_movies() -> Result<Vec<Movie>> {
-    if !Path::new(OUTPUT_FILE).exists() {
-        return Ok(Vec::new());
-    }
+    /// Show the most seeded movies with the seeds and peers of their best torrent
+    TopSeeders {
+        /// Number of movies to show (0 = all)
+        #[arg(short, long, default_value_t = 20)]
+        count: usize,
 
-    let content = fs::read_to_string(OUTPUT_FILE)?;
-    let movies: Vec<Movie> = serde_json::from_str(&content)?;
-    Ok(movies)
-}
+        /// Only rank torrents of this quality, e.g. "1080p" or "2160p-bluray"
+        #[arg(short, long)]
+        quality: Option<String>,
 
-fn save_movies(movies: &[Movie]) -> Result<()> {
-    let json = serde_json::to_string_pretty(movies)?;
-    fs::write(OUTPUT_FILE, json)?;
-    Ok(())
-}
+        /// Only movies listed under this genre, e.g. "Horror"
+        #[arg(short, long)]
+        genre: Option<String>,
+    },
 
-fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    const TB: u64 = GB * 1024;
-
-    if bytes >= TB {
-        format!("{:.2} TB", bytes as f64 / TB as f64)
-    } else if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} bytes", bytes)
-    }
-}
+    /// Show movies uploaded to YTS in the last few days
+    Recent(RecentOpts),
 
-fn fetch_movies() -> Result<()> {
-    println!("🎬 YTS Movie Grabber Starting...\n");
+    /// Pick random movies from the local database for a recommendation
+    Random(RandomOpts),
 
-    let existing_movies = load_existing_movies()?;
-    let latest_id = existing_movies.iter().map(|m| m.id).max().unwrap_or(0);
+    /// Check the local database for corrupted entries
+    Validate,
 
-    println!("📊 Fetching movie count...");
-    let first_response = fetch_page(1)?;
-    let total_count = first_response.data.movie_count;
+    /// Flag torrent hashes that are not 40 lowercase hex characters
+    HashCheck {
+        /// Also accept uppercase hex, as sent by YTS
+        #[arg(long)]
+        ignore_case: bool,
+    },
 
-    println!("Total movies in YTS: {}\n", total_count);
+    /// Find the movie a torrent info hash belongs to (exits with 1 if none does)
+    HashLookup {
+        /// Info hash as shown by a torrent client, 40 or 64 hex characters in any case
+        hash: String,
+    },
 
-    if latest_id > 0 {
-        println!("📁 Found existing database with {} movies", existing_movies.len());
-        println!("🔍 Latest movie ID in database: {}\n", latest_id);
-    }
+    /// Look up the peers of a torrent on the BitTorrent DHT and print their addresses
+    PeerDiscovery {
+        /// Info hash of the torrent, 40 or 64 hex characters in any case
+        hash: String,
 
-    let mut all_new_movies: Vec<Movie> = Vec::new();
-    let mut page = 1;
-    let mut found_existing = false;
-
-    let mut new_movie_count = 0;
-    if latest_id > 0 {
-        let mut temp_page = 1;
-        loop {
-            let response = fetch_page(temp_page)?;
-            if let Some(movies) = response.data.movies {
-                for movie in movies {
-                    if movie.id <= latest_id {
-                        found_existing = true;
-                        break;
-                    }
-                    new_movie_count += 1;
-                }
-                if found_existing {
-                    break;
-                }
-                temp_page += 1;
-            } else {
-                break;
-            }
-        }
+        /// Stop looking after this many seconds
+        #[arg(long, default_value_t = 10)]
+        timeout_secs: u64,
 
-        if new_movie_count == 0 {
-            println!("✅ Database is up to date! No new movies to fetch.\n");
-            return Ok(());
-        }
+        /// Store the number of peers found on the torrent in the database
+        #[arg(long)]
+        save: bool,
+    },
 
-        println!("🆕 Found {} new movies to fetch\n", new_movie_count);
-        found_existing = false;
-    }
+    /// Rebuild all magnet links from stored hashes and the configured trackers
+    RegenerateMagnets,
 
-    let progress_total = if latest_id > 0 { new_movie_count } else { total_count };
-    let pb = ProgressBar::new(progress_total as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} movies ({eta})")
-            .unwrap()
-            .progress_chars("#>-"),
-    );
+    /// Renumber movies 1, 2, 3... for tools that expect sequential IDs, or undo it
+    FixIds {
+        /// Give movies sequential IDs from 1, oldest first, keeping the YTS ID as yts_id
+        #[arg(long, required_unless_present = "restore", conflicts_with = "restore")]
+        compact_ids: bool,
 
-    loop {
-        let response = fetch_page(page)?;
-
-        if let Some(movies) = response.data.movies {
-            for api_movie in movies {
-                if api_movie.id <= latest_id {
-                    found_existing = true;
-                    break;
-                }
-
-                let torrents: Vec<Torrent> = api_movie
-                    .torrents
-                    .iter()
-                    .map(|t| {
-                        let magnet = create_magnet_url(&t.hash, &api_movie.title);
-                        let quality_with_type = format!("{}-{}", t.quality, t.torrent_type);
-
-                        Torrent {
-                            quality: quality_with_type,
-                            hash: t.hash.clone(),
-                            size_bytes: t.size_bytes,
-                            magnet_url: magnet,
-                        }
-                    })
-                    .collect();
-
-                let movie = Movie {
-                    id: api_movie.id,
-                    title: api_movie.title,
-                    year: api_movie.year,
-                    imdb_code: api_movie.imdb_code,
-                    torrents,
-                };
-
-                all_new_movies.push(movie);
-                pb.inc(1);
-            }
+        /// Make the YTS IDs the movie IDs again (needed before fetching)
+        #[arg(long)]
+        restore: bool,
+    },
 
-            if found_existing {
-                break;
-            }
+    /// Shrink the database by dropping magnet links, cover URLs or plots
+    Strip {
+        /// What to remove; repeat or separate with commas
+        #[arg(short, long, value_enum, required = true, value_delimiter = ',')]
+        field: Vec<StripField>,
 
-            page += 1;
-        } else {
-            break;
-        }
-    }
+        /// Write the stripped database here instead of replacing the original
+        #[arg(long)]
+        dest: Option<PathBuf>,
+    },
 
-    pb.finish_with_message("✅ Fetching complete");
+    /// Find magnet links that use dead trackers
+    Lint {
+        /// Rebuild the affected magnets from the healthy configured trackers
+        #[arg(long)]
+        fix: bool,
 
-    println!("\n💾 Saving to {}...", OUTPUT_FILE);
+        /// Number of affected movies to list (0 = all)
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
 
-    all_new_movies.extend(existing_movies);
-    all_new_movies.sort_by(|a, b| b.id.cmp(&a.id));
+    /// Remove movies that are no longer listed on YTS
+    Clean {
+        /// Show what would be removed without changing the database
+        #[arg(long)]
+        dry_run: bool,
 
-    save_movies(&all_new_movies)?;
+        /// Number of pages to request in parallel [default: 4, or concurrency from the config file]
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: Option<u32>,
+    },
 
-    println!("✅ Successfully saved {} total movies!", all_new_movies.len());
-    println!("📝 File: {}", OUTPUT_FILE);
+    /// Download cover images for movies in the local database
+    Images {
+        /// Directory to save images into
+        #[arg(long, default_value = "covers")]
+        output_dir: PathBuf,
 
-    Ok(())
-}
+        /// Cover size to download
+        #[arg(short, long, value_enum, default_value_t = CoverSize::Medium)]
+        quality: CoverSize,
 
-fn list_movies(limit: usize) -> Result<()> {
-    let movies = load_existing_movies()?;
+        /// Number of movies to download covers for (0 = all)
+        #[arg(short, long, default_value_t = 0)]
+        limit: usize,
 
-    if movies.is_empty() {
-        println!("❌ No movies found in database. Run 'fetch' first.");
-        return Ok(());
-    }
+        /// Number of images to download in parallel [default: 4, or concurrency from the config file]
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: Option<u32>,
+    },
 
-    let display_count = if limit == 0 { movies.len() } else { limit.min(movies.len()) };
-
-    println!("📽️  Showing {} of {} movies:\n", display_count, movies.len());
-    println!("{:<8} {:<50} {:<6} {:<12} {:<10}", "ID", "Title", "Year", "IMDb", "Torrents");
-    println!("{}", "=".repeat(100));
-
-    for movie in movies.iter().take(display_count) {
-        let title_truncated = if movie.title.len() > 47 {
-            format!("{}...", &movie.title[..47])
-        } else {
-            movie.title.clone()
-        };
-
-        println!(
-            "{:<8} {:<50} {:<6} {:<12} {:<10}",
-            movie.id,
-            title_truncated,
-            movie.year,
-            movie.imdb_code,
-            movie.torrents.len()
-        );
-
-        // Show torrent qualities
-        let qualities: Vec<String> = movie.torrents.iter().map(|t| t.quality.clone()).collect();
-        println!("         └─ Qualities: {}\n", qualities.join(", "));
-    }
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
 
-    Ok(())
-}
+    /// Add metadata from other services to the movies in the local database
+    Enrich {
+        #[command(subcommand)]
+        source: EnrichCommand,
+    },
 
-fn count_movies() -> Result<()> {
-    let movies = load_existing_movies()?;
+    /// Keep a list of movies you want to watch
+    Watchlist {
+        #[command(subcommand)]
+        action: WatchlistCommand,
+    },
 
-    if movies.is_empty() {
-        println!("❌ No movies found in database.");
-        return Ok(());
-    }
+    /// Attach your own labels to movies
+    Tag {
+        #[command(subcommand)]
+        action: TagCommand,
+    },
 
-    println!("📊 Movie Database Statistics\n");
-    println!("Total movies: {}", movies.len());
-    println!("Latest movie ID: {}", movies.iter().map(|m| m.id).max().unwrap_or(0));
-    println!("Oldest movie ID: {}", movies.iter().map(|m| m.id).min().unwrap_or(0));
+    /// Download the .torrent file for a movie in the local database
+    TorrentFile {
+        /// ID of the movie
+        #[arg(short, long)]
+        id: u32,
 
-    Ok(())
-}
+        /// Torrent quality to download, e.g. "1080p" or "2160p-bluray"
+        #[arg(short, long, default_value = "1080p")]
+        quality: String,
 
-fn calculate_size() -> Result<()> {
-    let movies = load_existing_movies()?;
+        /// Directory to save the .torrent file into
+        #[arg(long, default_value = ".")]
+        output_dir: PathBuf,
+    },
 
-    if movies.is_empty() {
-        println!("❌ No movies found in database.");
-        return Ok(());
-    }
+    /// Show the name, size, files and trackers stored in a .torrent file
+    TorrentInfo {
+        /// Path of the .torrent file
+        #[arg(short, long)]
+        file: PathBuf,
+    },
 
-    let mut total_size: u64 = 0;
+    /// Compare two database files and report added and removed movies
+    Diff {
+        /// Older database file
+        #[arg(long)]
+        before: PathBuf,
 
-    for movie in &movies {
-        if let Some(largest) = movie.torrents.iter().max_by_key(|t| t.size_bytes) {
-            total_size += largest.size_bytes;
-        }
-    }
+        /// Newer database file
+        #[arg(long)]
+        after: PathBuf,
+    },
 
-    println!("💾 Total Database Size (largest torrent per movie)\n");
-    println!("Total movies: {}", movies.len());
-    println!("Combined size: {}", format_size(total_size));
-    println!("Average size per movie: {}", format_size(total_size / movies.len() as u64));
+    /// Combine several database files into one, dropping duplicate IDs
+    Merge {
+        /// Database files to merge
+        #[arg(short, long, required = true, num_args = 1..)]
+        input: Vec<PathBuf>,
 
-    Ok(())
+        /// File to write the merged database to
+        #[arg(short, long)]
+        dest: PathBuf,
+    },
+
+    /// Two-way sync two database files so each has every movie in either
+    Sync {
+        /// First database file
+        file_a: PathBuf,
+
+        /// Second database file
+        file_b: PathBuf,
+
+        /// Show what each file would gain without writing either
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Export the local database to a CSV or TSV file (one row per torrent)
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// Destination file (defaults to the database path with a .csv/.tsv extension)
+        #[arg(long)]
+        dest: Option<PathBuf>,
+    },
+
+    /// Print the JSON Schema of the movie database format
+    Schema {
+        /// Write the schema to this file instead of stdout
+        #[arg(long)]
+        dest: Option<PathBuf>,
+    },
+
+    /// Print a shell completion script to stdout
+    #[command(after_long_help = COMPLETIONS_HELP)]
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
-fn show_stats() -> Result<()> {
-    let movies = load_existing_movies()?;
+const COMPLETIONS_HELP: &str = "\
+Installing:
+  bash:        yts-movie-scraper completions bash > ~/.local/share/bash-completion/completions/yts-movie-scraper
+  zsh:         yts-movie-scraper completions zsh > ~/.zfunc/_yts-movie-scraper
+               (with `fpath+=~/.zfunc; autoload -U compinit; compinit` in ~/.zshrc)
+  fish:        yts-movie-scraper completions fish > ~/.config/fish/completions/yts-movie-scraper.fish
+  powershell:  yts-movie-scraper completions powershell >> $PROFILE
+  elvish:      yts-movie-scraper completions elvish >> ~/.config/elvish/rc.elv";
 
-    if movies.is_empty() {
-        println!("❌ No movies found in database.");
-        return Ok(());
-    }
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Write the default configuration file
+    Init {
+        /// Overwrite an existing configuration file
+        #[arg(short, long)]
+        force: bool,
+    },
 
-    let total_torrents: usize = movies.iter().map(|m| m.torrents.len()).sum();
-    let mut total_size: u64 = 0;
+    /// Print the settings in effect after applying the config file, environment and flags
+    Show,
+}
 
-    for movie in &movies {
-        if let Some(largest) = movie.torrents.iter().max_by_key(|t| t.size_bytes) {
-            total_size += largest.size_bytes;
-        }
+#[derive(Subcommand)]
+enum WatchlistCommand {
+    /// Add a movie to the watchlist
+    Add {
+        /// ID of the movie
+        #[arg(short, long)]
+        id: u32,
+    },
+    /// Remove a movie from the watchlist
+    Remove {
+        /// ID of the movie
+        #[arg(short, long)]
+        id: u32,
+    },
+    /// Show all movies on the watchlist
+    Show,
+}
+
+#[derive(Subcommand)]
+enum TagCommand {
+    /// Tag a movie; tags are stored lowercase
+    Add {
+        /// ID of the movie
+        #[arg(short, long)]
+        id: u32,
+
+        /// Label to attach, e.g. "rewatch"
+        #[arg(short, long)]
+        tag: String,
+    },
+    /// Remove a tag from a movie
+    Remove {
+        /// ID of the movie
+        #[arg(short, long)]
+        id: u32,
+
+        /// Label to remove
+        #[arg(short, long)]
+        tag: String,
+    },
+    /// Show the movies with a tag, or every tag in use
+    List {
+        /// Only show movies with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnrichCommand {
+    /// Add runtime, backdrop image and spoken languages from TMDB
+    Tmdb {
+        /// TMDB API key (v3 auth)
+        #[arg(long, value_name = "API_KEY", env = "TMDB_API_KEY", hide_env_values = true)]
+        key: String,
+
+        /// TMDB API endpoint
+        #[arg(long, default_value = TMDB_API_BASE, value_parser = parse_api_url)]
+        tmdb_url: Url,
+
+        /// Look up movies that already have TMDB details again
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+fn parse_since_date(s: &str) -> std::result::Result<DateTime<Utc>, String> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
     }
 
-    let year_range = (
-        movies.iter().map(|m| m.year).min().unwrap_or(0),
-        movies.iter().map(|m| m.year).max().unwrap_or(0),
-    );
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD or RFC 3339", s))
+}
 
-    println!("📊 YTS Database Statistics\n");
-    println!("Movies:           {}", movies.len());
-    println!("Total torrents:   {}", total_torrents);
-    println!("Avg torrents/movie: {:.1}", total_torrents as f64 / movies.len() as f64);
-    println!("\nYear range:       {} - {}", year_range.0, year_range.1);
-    println!("\nMovie IDs:        {} to {}", 
-        movies.iter().map(|m| m.id).min().unwrap_or(0),
-        movies.iter().map(|m| m.id).max().unwrap_or(0)
-    );
-    println!("\nTotal size (largest/movie): {}", format_size(total_size));
-    println!("Average size per movie:     {}", format_size(total_size / movies.len() as u64));
+fn parse_api_url(s: &str) -> std::result::Result<Url, String> {
+    let url = Url::parse(s).map_err(|e| format!("'{}' is not a valid URL: {}", s, e))?;
 
-    Ok(())
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        scheme => Err(format!("unsupported URL scheme '{}', expected http or https", scheme)),
+    }
+}
+
+fn parse_proxy_url(s: &str) -> std::result::Result<Url, String> {
+    let url = Url::parse(s).map_err(|e| format!("'{}' is not a valid proxy URL: {}", s, e))?;
+
+    if PROXY_SCHEMES.contains(&url.scheme()) {
+        Ok(url)
+    } else {
+        Err(format!("unsupported proxy scheme '{}', expected http, https, socks5 or socks5h", url.scheme()))
+    }
 }
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     let cli = Cli::parse();
+    Printer::from_env(cli.no_color).with_progress(!cli.no_progress).install();
+
+    // RUST_LOG always wins; --log-level and --verbose only change the default.
+    let log_level = cli.log_level.unwrap_or(if cli.verbose { LogLevel::Debug } else { LogLevel::Warn });
+    let printer = Printer::global();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(log_level.filter())))
+        .with_writer(std::io::stderr)
+        .with_ansi(printer.decorates() && std::io::stderr().is_terminal())
+        .init();
+    let overrides = ConfigOverrides {
+        api_url: cli.api_url,
+        output_file: cli.output,
+        page_size: cli.page_size,
+        concurrency: None,
+        proxy: cli.proxy,
+        no_proxy: cli.no_proxy,
+        api_version: cli.api_version,
+    };
+    let config = load_config()?.resolve(overrides, Path::new(cli.backend.default_path()))?;
+    let db_path = config.output_file.clone();
+    let storage = cli.backend.open(&db_path);
+    let storage = storage.as_ref();
+    let uses_page_cache = matches!(
+        cli.command,
+        Some(Commands::Fetch { page_cache: true, .. } | Commands::Retry { page_cache: true, .. })
+    );
+    let page_cache_file = page_cache_path(&db_path);
+    let timeouts = HttpTimeouts {
+        total: Duration::from_secs(cli.timeout),
+        connect: Duration::from_secs(cli.connect_timeout),
+    };
+    let api = YtsApi {
+        client: build_client(config.proxy.as_ref(), cli.no_proxy, timeouts)?,
+        base_url: config.api_url.clone(),
+        version: cli.api_version,
+        max_retries: cli.max_retries,
+        page_size: config.page_size,
+        api_key: cli.api_key,
+        page_cache: uses_page_cache.then(|| Arc::new(Mutex::new(PageCache::load(&page_cache_file)))),
+    };
 
     match cli.command {
-        Some(Commands::Fetch) => fetch_movies()?,
-        Some(Commands::List { limit }) => list_movies(limit)?,
-        Some(Commands::Count) => count_movies()?,
-        Some(Commands::Size) => calculate_size()?,
-        Some(Commands::Stats) => show_stats()?,
-        None => fetch_movies()?, // Default action
+        Some(Commands::Fetch {
+            concurrency,
+            workers,
+            since,
+            since_id,
+            progress_file,
+            no_magnet,
+            enrich_omdb,
+            omdb_url,
+            auto_backup,
+            check_before_fetch,
+            min_api_rating,
+            genre,
+            limit,
+            sort_by,
+            order_by,
+            page_start,
+            page_end,
+            webhook_url,
+            webhook_secret,
+            page_cache: _,
+        }) => {
+            anyhow::ensure!(
+                page_end.is_none_or(|end| end >= page_start),
+                "--page-end must not be before --page-start"
+            );
+            // Returning here also skips saving the page cache.
+            if check_before_fetch && !needs_fetch(storage, &api, min_api_rating, &genre).await? {
+                return Ok(());
+            }
+            if auto_backup && storage.path().exists() {
+                backup_command(storage, None, DEFAULT_BACKUP_KEEP)?;
+            }
+            let trackers = (!no_magnet).then_some(config.trackers.as_slice());
+            let omdb = enrich_omdb.map(|api_key| OmdbApi {
+                client: api.client.clone(),
+                base_url: omdb_url,
+                api_key,
+            });
+            let webhook = webhook_url.map(|url| Webhook {
+                client: api.client.clone(),
+                url,
+                secret: webhook_secret,
+            });
+            let opts = FetchOptions {
+                concurrency: concurrency.unwrap_or(config.concurrency),
+                workers,
+                since,
+                since_id,
+                minimum_rating: min_api_rating,
+                progress_file: progress_file.as_deref(),
+                trackers,
+                omdb: omdb.as_ref(),
+                limit,
+                sort_by: sort_by.unwrap_or_default(),
+                order_by: order_by.unwrap_or_default(),
+                page_start,
+                page_end,
+                webhook: webhook.as_ref(),
+                genres: &genre,
+            };
+            let fetched = fetch_movies(storage, &api, &opts).await;
+            save_page_cache(&api, &page_cache_file)?;
+            fetched?
+        }
+        Some(Commands::Retry { concurrency, .. }) => {
+            let concurrency = concurrency.unwrap_or(config.concurrency);
+            let retried = retry_command(storage, &api, &config.trackers, concurrency).await;
+            save_page_cache(&api, &page_cache_file)?;
+            retried?
+        }
+        Some(Commands::List(args)) => list_movies(storage, &args)?,
+        Some(Commands::Search { query, year, exact }) => {
+            search_movies(storage, &query, year, exact)?;
+        }
+        Some(Commands::RemoteSearch {
+            query,
+            genre,
+            min_rating,
+            limit,
+        }) => {
+            let params = FetchParams {
+                query_term: Some(query),
+                genre,
+                minimum_rating: min_rating,
+                ..Default::default()
+            };
+            let mut out = io::stdout();
+            remote_search(&api, &params, limit, config.concurrency, &config.trackers, &mut out).await?;
+        }
+        Some(Commands::MagnetBatch(opts)) => magnet_batch_command(storage, &opts)?,
+        Some(Commands::DownloadAll(opts)) => {
+            if download_all_command(storage, &opts).await? > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Magnet {
+            query,
+            quality,
+            torrent_type,
+            all,
+        }) => print_magnets(storage, &query, &quality, torrent_type.as_deref(), all)?,
+        Some(Commands::Send { id, quality, transmission }) => {
+            send_command(storage, id, &quality, &transmission).await?
+        }
+        Some(Commands::Info { id, imdb, imdb_link }) => {
+            let key = match (id, imdb.as_deref()) {
+                (Some(id), _) => MovieKey::Id(id),
+                (None, Some(code)) => MovieKey::Imdb(code),
+                (None, None) => unreachable!("clap requires --id or --imdb"),
+            };
+            info_command(storage, key, imdb_link)?
+        }
+        Some(Commands::QualityReport {
+            want,
+            have,
+            missing,
+            limit,
+        }) => {
+            let want = want.or(missing).expect("clap requires --want or --missing");
+            quality_report_command(storage, &want, have.as_deref(), limit)?
+        }
+        Some(Commands::Filter { opts, limit }) => filter_command(storage, &opts, limit)?,
+        Some(Commands::Check {
+            min_api_rating,
+            retry_failed,
+            notify,
+        }) => {
+            let new_movies = check_new_movies(storage, &api, min_api_rating).await?;
+            notify_new_movies(notify.notifier().as_deref(), &new_movies);
+            if retry_failed {
+                retry_command(storage, &api, &config.trackers, config.concurrency).await?;
+            }
+        }
+        Some(Commands::Watch {
+            interval,
+            auto_fetch,
+            concurrency,
+            notify,
+        }) => {
+            let notifier = notify.notifier();
+            let opts = WatchOptions {
+                interval: Duration::from_secs(interval * 60),
+                auto_fetch,
+                concurrency: concurrency.unwrap_or(config.concurrency),
+                trackers: &config.trackers,
+                notifier: notifier.as_deref(),
+            };
+            watch_movies(storage, &api, &opts).await?
+        }
+        Some(Commands::WatchNew { interval, format }) => {
+            watch_new_command(storage, &api, interval, format, &config.trackers).await?
+        }
+        Some(Commands::Count(opts)) => count_movies(storage, &opts)?,
+        Some(Commands::Summary { format, json }) => summary_command(storage, &format, json)?,
+        Some(Commands::Size {
+            quality,
+            fallback_largest,
+            largest,
+        }) => {
+            let selector = if largest {
+                QualitySelector::Largest
+            } else if fallback_largest {
+                QualitySelector::SpecificOrLargest(quality)
+            } else {
+                QualitySelector::Specific(quality)
+            };
+            calculate_size(storage, &selector)?
+        }
+        Some(Commands::Report { path }) => report::report_command(storage, &path)?,
+        Some(Commands::CoverGrid(opts)) => report::cover_grid_command(storage, &opts)?,
+        Some(Commands::Plot { field, width }) => plot_command(storage, field, width)?,
+        Some(Commands::SizeByQuality) => size_by_quality_command(storage)?,
+        Some(Commands::Stats) => show_stats(storage)?,
+        Some(Commands::Dedup { backup }) => dedup_command(storage, backup)?,
+        Some(Commands::Prune {
+            before_year,
+            before_date,
+            dry_run,
+        }) => {
+            let cutoff = match (before_year, before_date) {
+                (Some(year), _) => PruneCutoff::Year(year),
+                (None, Some(date)) => PruneCutoff::Uploaded(date),
+                (None, None) => unreachable!("clap requires a cut-off"),
+            };
+            prune_command(storage, cutoff, dry_run)?
+        }
+        Some(Commands::Backup { backup_dir, keep }) => backup_command(storage, backup_dir.as_deref(), keep)?,
+        Some(Commands::Seeding { min_seeds, limit }) => seeding_command(storage, min_seeds, limit)?,
+        Some(Commands::TopSeeders { count, quality, genre }) => {
+            top_seeders_command(storage, count, quality.as_deref(), genre.as_deref())?
+        }
+        Some(Commands::Top(opts)) => top_command(storage, &opts)?,
+        Some(Commands::Recent(opts)) => recent_command(storage, &opts)?,
+        Some(Commands::Random(opts)) => random_command(storage, &opts)?,
+        Some(Commands::HashCheck { ignore_case }) => {
+            if hash_check_command(storage, ignore_case)? > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::HashLookup { hash }) => {
+            if !hash_lookup_command(storage, &hash)? {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Validate) => {
+            if validate_command(storage)? > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::PeerDiscovery { hash, timeout_secs, save }) => {
+            peer_discovery_command(storage, &hash, Duration::from_secs(timeout_secs), save).await?;
+        }
+        Some(Commands::RegenerateMagnets) => {
+            regenerate_magnets_command(storage, &config.trackers)?;
+        }
+        Some(Commands::FixIds { compact_ids, .. }) => fix_ids_command(storage, compact_ids)?,
+        Some(Commands::Strip { field, dest }) => {
+            let dest = dest.map(|path| cli.backend.open(&path));
+            strip_command(storage, &field, dest.as_deref())?
+        }
+        Some(Commands::Lint { fix, limit }) => lint_command(storage, &config.trackers, fix, limit)?,
+        Some(Commands::Clean { dry_run, concurrency }) => {
+            clean_command(storage, &api, dry_run, concurrency.unwrap_or(config.concurrency)).await?
+        }
+        Some(Commands::Images {
+            output_dir,
+            quality,
+            limit,
+            concurrency,
+        }) => {
+            let concurrency = concurrency.unwrap_or(config.concurrency);
+            download_images(storage, &api, &output_dir, quality, limit, concurrency).await?
+        }
+        Some(Commands::TorrentInfo { file }) => torrent_info_command(&file)?,
+        Some(Commands::TorrentFile { id, quality, output_dir }) => {
+            download_torrent_file(storage, &api, id, &quality, &output_dir).await?
+        }
+        Some(Commands::Config {
+            action: ConfigCommand::Init { force },
+        }) => config_init(force)?,
+        Some(Commands::Config {
+            action: ConfigCommand::Show,
+        }) => config_show(&config)?,
+        Some(Commands::Enrich {
+            source: EnrichCommand::Tmdb { key, tmdb_url, force },
+        }) => {
+            let tmdb = TmdbApi {
+                client: api.client.clone(),
+                base_url: tmdb_url,
+                api_key: key,
+            };
+            enrich_tmdb_command(storage, &tmdb, force).await?
+        }
+        Some(Commands::Watchlist { action }) => match action {
+            WatchlistCommand::Add { id } => watchlist_add(storage, id)?,
+            WatchlistCommand::Remove { id } => watchlist_remove(storage, id)?,
+            WatchlistCommand::Show => {
+                watchlist_show(storage)?;
+            }
+        },
+        Some(Commands::Tag { action }) => match action {
+            TagCommand::Add { id, tag } => tag_add(storage, id, &tag)?,
+            TagCommand::Remove { id, tag } => tag_remove(storage, id, &tag)?,
+            TagCommand::List { tag } => tag_list(storage, tag.as_deref())?,
+        },
+        Some(Commands::Diff { before, after }) => {
+            diff_command(cli.backend.open(&before).as_ref(), cli.backend.open(&after).as_ref())?
+        }
+        Some(Commands::Merge { input, dest }) => {
+            let inputs: Vec<_> = input.iter().map(|path| cli.backend.open(path)).collect();
+            merge_command(&inputs, cli.backend.open(&dest).as_ref())?
+        }
+        Some(Commands::Sync { file_a, file_b, dry_run }) => {
+            sync_command(cli.backend.open(&file_a).as_ref(), cli.backend.open(&file_b).as_ref(), dry_run)?
+        }
+        Some(Commands::Export { format, dest }) => export_movies(storage, format, dest)?,
+        Some(Commands::Schema { dest }) => schema_command(dest.as_deref())?,
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "yts-movie-scraper", &mut std::io::stdout())
+        }
+        None => fetch_movies(storage, &api, &FetchOptions::new(config.concurrency, &config.trackers)).await?, // Default action
     }
 
+    Ok(())
+}
+
+/// Saves the responses cached with `--page-cache`, also after a fetch that
+/// failed partway, so the pages it got are not downloaded again.
+fn save_page_cache(api: &YtsApi, path: &Path) -> Result<()> {
+    if let Some(cache) = &api.page_cache {
+        cache.lock().expect("page cache lock poisoned").save(path)?;
+    }
     Ok(())
 }
\ No newline at end of file
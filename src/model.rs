@@ -0,0 +1,171 @@
+//! The movie and torrent records kept in the local database.
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One downloadable release of a movie.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Torrent {
+    /// Resolution, e.g. "1080p" or "2160p".
+    pub quality: String,
+    /// Source of the release, e.g. "web" or "bluray". Empty for old databases.
+    #[serde(default)]
+    pub torrent_type: String,
+    /// BitTorrent info hash.
+    pub hash: String,
+    pub size_bytes: u64,
+    /// Magnet link built from the hash and the configured trackers. Empty when
+    /// fetched with `--no-magnet`.
+    pub magnet_url: String,
+    /// Download link for the `.torrent` file.
+    #[serde(default)]
+    pub url: String,
+    /// Seeders reported by YTS at fetch time; 0 for old databases.
+    #[serde(default)]
+    pub seeds: u32,
+    /// Leechers reported by YTS at fetch time; 0 for old databases.
+    #[serde(default)]
+    pub peers: u32,
+    /// Peers found on the DHT by the last `peer-discovery --save`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dht_peers: Option<u32>,
+}
+
+impl Torrent {
+    /// Quality and type combined, e.g. "1080p-web", as shown in listings.
+    pub fn label(&self) -> String {
+        if self.torrent_type.is_empty() {
+            self.quality.clone()
+        } else {
+            format!("{}-{}", self.quality, self.torrent_type)
+        }
+    }
+}
+
+/// A movie in the local database, as stored in `yts_movies.json`.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Movie {
+    /// YTS movie ID.
+    pub id: u32,
+    pub title: String,
+    /// Release year.
+    pub year: u32,
+    /// IMDb ID, e.g. "tt0133093".
+    pub imdb_code: String,
+    /// IMDb rating, 0-10.
+    #[serde(default)]
+    pub rating: f32,
+    /// MPA rating, e.g. "PG-13".
+    #[serde(default)]
+    pub mpa_rating: String,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub large_cover_image: String,
+    #[serde(default)]
+    pub medium_cover_image: String,
+    #[serde(default)]
+    pub small_cover_image: String,
+    /// When the movie was added to YTS, as a Unix timestamp.
+    #[serde(default)]
+    pub date_uploaded_unix: i64,
+    pub torrents: Vec<Torrent>,
+    /// Plot summary from OMDb, set by `fetch --enrich-omdb`.
+    #[serde(default)]
+    pub plot: Option<String>,
+    /// Director from OMDb.
+    #[serde(default)]
+    pub director: Option<String>,
+    /// Awards summary from OMDb.
+    #[serde(default)]
+    pub awards: Option<String>,
+    /// Marked as "want to watch" with `watchlist add`.
+    #[serde(default)]
+    pub watchlisted: bool,
+    /// Runtime from TMDB, set by `enrich tmdb`.
+    #[serde(default)]
+    pub runtime_minutes: Option<u32>,
+    /// Full-size backdrop image from TMDB.
+    #[serde(default)]
+    pub backdrop_url: Option<String>,
+    /// English names of the spoken languages from TMDB, e.g. "French".
+    #[serde(default)]
+    pub spoken_languages: Vec<String>,
+    /// Lowercase labels attached with `tag add`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Original YTS movie ID once `fix-ids --compact-ids` has made `id` a
+    /// local sequence number; 0 while `id` is the YTS ID.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub yts_id: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// Movie count and combined size for a single release year.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct YearStat {
+    /// Number of movies released in the year.
+    pub count: usize,
+    /// Combined size of the largest torrent of each movie.
+    pub total_size: u64,
+}
+
+impl YearStat {
+    pub fn average_size(&self) -> u64 {
+        if self.count == 0 {
+            0
+        } else {
+            self.total_size / self.count as u64
+        }
+    }
+}
+
+/// Aggregated statistics for a movie database.
+#[derive(Debug, Default)]
+pub struct DatabaseStats {
+    /// Number of movies in the database.
+    pub total_movies: usize,
+    /// Number of torrents across all movies.
+    pub total_torrents: usize,
+    /// Combined size using the largest torrent of each movie.
+    pub total_size: u64,
+    /// Lowest and highest release year.
+    pub year_range: (u32, u32),
+    /// Lowest and highest movie id.
+    pub id_range: (u32, u32),
+    /// Per-year counts and sizes, sorted by year.
+    pub per_year: BTreeMap<u32, YearStat>,
+    /// Number of torrents for each quality string, e.g. "1080p-web".
+    pub per_quality: BTreeMap<String, usize>,
+    /// Number of movies listed under each genre; a movie counts once per genre.
+    pub per_genre: BTreeMap<String, usize>,
+    /// Movies that have no torrents at all.
+    pub movies_without_torrents: usize,
+    /// Up to ten largest movies as (id, title, size), biggest first.
+    pub largest_movies: Vec<(u32, String, u64)>,
+}
+
+/// Splits a legacy combined "{quality}-{type}" string into its two parts.
+/// "{quality}.{type}" is split the same way. Strings without a type are
+/// returned with an empty type.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::split_quality_type;
+///
+/// assert_eq!(split_quality_type("1080p-bluray"), ("1080p".to_string(), "bluray".to_string()));
+/// assert_eq!(split_quality_type("1080p.web"), ("1080p".to_string(), "web".to_string()));
+/// assert_eq!(split_quality_type("2160p"), ("2160p".to_string(), String::new()));
+/// ```
+pub fn split_quality_type(combined: &str) -> (String, String) {
+    match combined.split_once(['-', '.']) {
+        Some((quality, torrent_type)) => (quality.to_string(), torrent_type.to_string()),
+        None => (combined.to_string(), String::new()),
+    }
+}
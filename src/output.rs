@@ -0,0 +1,817 @@
+//! Terminal output: the global [`Printer`], progress bars, and the movie list formatters.
+
+use std::fmt;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::{ExportRow, Movie, Result, Torrent, YtsError, EXPORT_COLUMNS};
+
+/// Writes command output, dropping emoji and other decorations when they
+/// are turned off with `--no-color` or the `NO_COLOR` environment variable,
+/// and creates the progress bars unless `--no-progress` hides them.
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    decorate: bool,
+    progress: bool,
+}
+
+static PRINTER: OnceLock<Printer> = OnceLock::new();
+
+/// Emoji, symbols and box-drawing characters used as decoration.
+fn is_decoration(c: char) -> bool {
+    matches!(c,
+        '\u{2139}'                    // ℹ
+        | '\u{2190}'..='\u{21FF}'      // arrows
+        | '\u{2300}'..='\u{23FF}'      // ⏩ ⏰
+        | '\u{2500}'..='\u{27BF}'      // box drawing, ✅ ❌ ⚠
+        | '\u{2B00}'..='\u{2BFF}'      // ⭐ ⬇
+        | '\u{FE0F}'                   // emoji presentation selector
+        | '\u{200D}'                   // zero-width joiner
+        | '\u{1F000}'..='\u{1FAFF}')
+}
+
+impl Printer {
+    pub fn new(decorate: bool) -> Self {
+        Printer { decorate, progress: true }
+    }
+
+    /// Shows progress bars if `progress`, otherwise only a line per fetched page.
+    pub fn with_progress(self, progress: bool) -> Self {
+        Printer { progress, ..self }
+    }
+
+    /// Decorated unless `no_color` is set or `NO_COLOR` is set to a
+    /// non-empty value (see <https://no-color.org>).
+    pub fn from_env(no_color: bool) -> Self {
+        let env_no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Printer::new(!no_color && !env_no_color)
+    }
+
+    /// Makes this the printer used by all library output and turns off
+    /// progress bar colours if undecorated. Only the first call has an effect.
+    pub fn install(self) {
+        if !self.decorate {
+            console::set_colors_enabled(false);
+            console::set_colors_enabled_stderr(false);
+        }
+        let _ = PRINTER.set(self);
+    }
+
+    /// The installed printer, or a decorated one if none was installed.
+    pub fn global() -> Printer {
+        PRINTER.get().copied().unwrap_or(Printer::new(true))
+    }
+
+    /// Returns `text` as it should be printed. Undecorated, each decoration
+    /// is removed along with the space that follows it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yts_movie_scraper::Printer;
+    ///
+    /// let plain = Printer::new(false);
+    /// assert_eq!(plain.render("✅ Saved 3 movies"), "Saved 3 movies");
+    /// assert_eq!(plain.render("⚠️  No 2160p torrent"), "No 2160p torrent");
+    /// assert_eq!(plain.render("  1. ⭐ 8.5  Heat"), "  1. 8.5  Heat");
+    /// assert_eq!(Printer::new(true).render("✅ Done"), "✅ Done");
+    /// ```
+    pub fn render<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.decorate || !text.chars().any(is_decoration) {
+            return text.into();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let mut chars = line.chars().peekable();
+            let mut line_start = true;
+            while let Some(c) = chars.next() {
+                if !is_decoration(c) {
+                    line_start &= c == ' ';
+                    out.push(c);
+                    continue;
+                }
+                while chars.next_if(|&c| is_decoration(c)).is_some() {}
+                // Leading decorations take all their padding with them.
+                if line_start {
+                    while chars.next_if_eq(&' ').is_some() {}
+                } else {
+                    chars.next_if_eq(&' ');
+                }
+            }
+        }
+        out.into()
+    }
+
+    /// Whether emoji and colours are shown.
+    pub fn decorates(&self) -> bool {
+        self.decorate
+    }
+
+    /// Whether progress bars are shown.
+    pub fn shows_progress(&self) -> bool {
+        self.progress
+    }
+
+    /// A progress bar counting to `len`, or a spinner without it. `counter`
+    /// is the part of the template after the bar, e.g. `"{pos}/{len} pages"`.
+    /// With progress turned off the bar is hidden, so updating it draws nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yts_movie_scraper::Printer;
+    ///
+    /// let pb = Printer::new(true).with_progress(false).progress_bar(Some(10), "{pos}/{len} pages");
+    /// pb.inc(1);
+    /// assert!(pb.is_hidden());
+    /// assert_eq!(pb.position(), 1);
+    /// ```
+    pub fn progress_bar(&self, len: Option<u64>, counter: &str) -> ProgressBar {
+        if !self.progress {
+            return ProgressBar::hidden();
+        }
+
+        let pb = match len {
+            Some(len) => ProgressBar::new(len),
+            None => ProgressBar::new_spinner(),
+        };
+        let template = format!("{{spinner:.green}} [{{elapsed_precise}}] [{{bar:40.cyan/blue}}] {} ({{eta}})", counter);
+        pb.set_style(ProgressStyle::default_bar().template(&template).unwrap().progress_chars("#>-"));
+        pb
+    }
+
+    pub fn println(&self, text: &str) {
+        println!("{}", self.render(text));
+    }
+
+    pub fn eprintln(&self, text: &str) {
+        eprintln!("{}", self.render(text));
+    }
+}
+
+/// Verbosity of the diagnostic log written to stderr.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    /// Filter directive logging this crate at this level. Dependencies stay
+    /// at `warn` unless the level is stricter than that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yts_movie_scraper::LogLevel;
+    ///
+    /// assert_eq!(LogLevel::Debug.filter(), "warn,yts_movie_scraper=debug");
+    /// assert_eq!(LogLevel::Warn.filter(), "warn,yts_movie_scraper=warn");
+    /// assert_eq!(LogLevel::Error.filter(), "error,yts_movie_scraper=error");
+    /// ```
+    pub fn filter(self) -> String {
+        format!("{},yts_movie_scraper={}", self.min(LogLevel::Warn).as_str(), self.as_str())
+    }
+}
+
+/// How the `list` table colours torrent qualities.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorScheme {
+    /// Plain text
+    #[default]
+    None,
+    /// 2160p green, 1080p cyan, 720p yellow, lower qualities red
+    QualityColor,
+}
+
+/// Colours of the qualities YTS uses, for [`ColorScheme::QualityColor`].
+const QUALITY_COLORS: [(&str, console::Color); 7] = [
+    ("2160p", console::Color::Green),
+    ("4k", console::Color::Green),
+    ("1080p", console::Color::Cyan),
+    ("720p", console::Color::Yellow),
+    ("480p", console::Color::Red),
+    ("360p", console::Color::Red),
+    ("240p", console::Color::Red),
+];
+
+/// The colour of a torrent quality such as "1080p", ignoring case, or `None`
+/// for a quality without a tier like "3D".
+///
+/// # Examples
+///
+/// ```
+/// use console::Color;
+/// use yts_movie_scraper::quality_color;
+///
+/// assert_eq!(quality_color("2160p"), Some(Color::Green));
+/// assert_eq!(quality_color("4K"), Some(Color::Green));
+/// assert_eq!(quality_color("1080p"), Some(Color::Cyan));
+/// assert_eq!(quality_color("720p"), Some(Color::Yellow));
+/// assert_eq!(quality_color("480p"), Some(Color::Red));
+/// assert_eq!(quality_color("3D"), None);
+/// ```
+pub fn quality_color(quality: &str) -> Option<console::Color> {
+    QUALITY_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(quality))
+        .map(|&(_, color)| color)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable table
+    Table,
+    /// Pretty-printed JSON array
+    Json,
+    /// One row per torrent, with the same columns as `export`
+    Csv,
+    /// One JSON movie object per line
+    Ndjson,
+}
+
+pub(crate) fn truncate_chars(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+pub fn format_elapsed(since: DateTime<Utc>) -> String {
+    format_elapsed_at(since, Utc::now())
+}
+
+/// Like [`format_elapsed`], measured up to `now` instead of the current time.
+pub fn format_elapsed_at(since: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now.signed_duration_since(since);
+
+    if elapsed.num_days() > 0 {
+        format!("{} days ago", elapsed.num_days())
+    } else if elapsed.num_hours() > 0 {
+        format!("{}h {}m ago", elapsed.num_hours(), elapsed.num_minutes() % 60)
+    } else if elapsed.num_minutes() > 0 {
+        format!("{}m ago", elapsed.num_minutes())
+    } else {
+        "just now".to_string()
+    }
+}
+
+/// Binary (1024-based) size units, as used by YTS for `size` strings like
+/// "1.84 GB".
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::SizeUnit;
+///
+/// assert_eq!(SizeUnit::from(0), SizeUnit::B);
+/// assert_eq!(SizeUnit::from(1023), SizeUnit::B);
+/// assert_eq!(SizeUnit::from(1024), SizeUnit::KB);
+/// assert_eq!(SizeUnit::from(5 * 1024 * 1024), SizeUnit::MB);
+/// assert_eq!(SizeUnit::from(1024u64.pow(3) - 1), SizeUnit::MB);
+/// assert_eq!(SizeUnit::from(u64::MAX), SizeUnit::TB);
+/// assert_eq!(SizeUnit::GB.to_string(), "GB");
+/// assert_eq!("bytes".parse::<SizeUnit>().unwrap(), SizeUnit::B);
+/// assert_eq!("mb".parse::<SizeUnit>().unwrap(), SizeUnit::MB);
+/// assert!("GiB".parse::<SizeUnit>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SizeUnit {
+    B,
+    KB,
+    MB,
+    GB,
+    TB,
+}
+
+impl SizeUnit {
+    const ALL: [SizeUnit; 5] = [SizeUnit::B, SizeUnit::KB, SizeUnit::MB, SizeUnit::GB, SizeUnit::TB];
+
+    /// Bytes in one of this unit.
+    pub fn bytes(self) -> u64 {
+        1024u64.pow(self as u32)
+    }
+}
+
+/// The largest unit that `bytes` is at least one of.
+impl From<u64> for SizeUnit {
+    fn from(bytes: u64) -> Self {
+        SizeUnit::ALL.into_iter().rev().find(|unit| bytes >= unit.bytes()).unwrap_or(SizeUnit::B)
+    }
+}
+
+impl fmt::Display for SizeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SizeUnit::B => "B",
+            SizeUnit::KB => "KB",
+            SizeUnit::MB => "MB",
+            SizeUnit::GB => "GB",
+            SizeUnit::TB => "TB",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Case-insensitive; "bytes" is accepted for [`SizeUnit::B`] since that is
+/// what [`format_size`] prints.
+impl std::str::FromStr for SizeUnit {
+    type Err = YtsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "B" | "BYTE" | "BYTES" => Ok(SizeUnit::B),
+            "KB" => Ok(SizeUnit::KB),
+            "MB" => Ok(SizeUnit::MB),
+            "GB" => Ok(SizeUnit::GB),
+            "TB" => Ok(SizeUnit::TB),
+            _ => Err(YtsError::InvalidSize(s.to_string())),
+        }
+    }
+}
+
+/// Formats a byte count using binary (1024-based) units. The inverse is
+/// [`parse_size_str`].
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::format_size;
+///
+/// assert_eq!(format_size(512), "512 bytes");
+/// assert_eq!(format_size(1536), "1.50 KB");
+/// assert_eq!(format_size(2 * 1024 * 1024 * 1024), "2.00 GB");
+/// assert_eq!(format_size(1024 * 1024 - 1), "1.00 MB");
+/// ```
+pub fn format_size(bytes: u64) -> String {
+    let mut unit = SizeUnit::from(bytes);
+    // 1023.999 KB would print as "1024.00 KB"; show "1.00 MB" instead.
+    if unit != SizeUnit::B && unit != SizeUnit::TB && bytes as f64 / unit.bytes() as f64 >= 1023.995 {
+        unit = SizeUnit::ALL[unit as usize + 1];
+    }
+
+    match unit {
+        SizeUnit::B => format!("{} bytes", bytes),
+        unit => format!("{:.2} {}", bytes as f64 / unit.bytes() as f64, unit),
+    }
+}
+
+/// Parses a size like `700MB`, `1.84 GB` (as in YTS `size` strings) or the
+/// output of [`format_size`] into bytes. Units are case-insensitive and a
+/// bare number is a byte count.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{format_size, parse_size_str};
+///
+/// assert_eq!(parse_size_str("512").unwrap(), 512);
+/// assert_eq!(parse_size_str("512b").unwrap(), 512);
+/// assert_eq!(parse_size_str("512 bytes").unwrap(), 512);
+/// assert_eq!(parse_size_str("700MB").unwrap(), 700 * 1024 * 1024);
+/// assert_eq!(parse_size_str("1.5gb").unwrap(), 1536 * 1024 * 1024);
+/// assert_eq!(parse_size_str("1.84 GB").unwrap(), 1_975_684_956);
+/// assert_eq!(parse_size_str(" 2 Tb ").unwrap(), 2 * 1024u64.pow(4));
+/// assert_eq!(parse_size_str(".5KB").unwrap(), 512);
+/// assert_eq!(parse_size_str("0").unwrap(), 0);
+/// assert!(parse_size_str("").is_err());
+/// assert!(parse_size_str("GB").is_err());
+/// assert!(parse_size_str("-1GB").is_err());
+/// assert!(parse_size_str("1.2.3 MB").is_err());
+/// assert!(parse_size_str("1.5 GiB").is_err());
+///
+/// // Formatting what was parsed from a formatted size gives the same text,
+/// // across every unit and its boundaries.
+/// let mut bytes = 1u64;
+/// while bytes < 64 * 1024u64.pow(4) {
+///     for n in [bytes - 1, bytes, bytes + 1, bytes * 3 / 2] {
+///         let formatted = format_size(n);
+///         assert_eq!(format_size(parse_size_str(&formatted).unwrap()), formatted, "{} bytes", n);
+///     }
+///     bytes *= 2;
+/// }
+/// for unit_bytes in [1u64, 1 << 10, 1 << 20, 1 << 30, 1 << 40] {
+///     assert_eq!(parse_size_str(&format_size(unit_bytes)).unwrap(), unit_bytes);
+/// }
+/// ```
+pub fn parse_size_str(s: &str) -> Result<u64> {
+    let invalid = || YtsError::InvalidSize(s.to_string());
+    let trimmed = s.trim();
+    let unit_start = trimmed.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(unit_start);
+
+    let unit = match unit.trim() {
+        "" => SizeUnit::B,
+        unit => unit.parse().map_err(|_| invalid())?,
+    };
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+
+    Ok((number * unit.bytes() as f64).round() as u64)
+}
+
+/// Writes a list of movies in one output format. [`write_movies`] calls
+/// `write_header` once, `write_movie` for each movie, then `write_footer`.
+pub trait Formatter {
+    fn write_header(&self, _w: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_movie(&self, movie: &Movie, w: &mut dyn Write) -> Result<()>;
+
+    fn write_footer(&self, _w: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes `movies` to `w` with `formatter` and flushes it.
+pub fn write_movies(formatter: &dyn Formatter, movies: &[&Movie], w: &mut dyn Write) -> Result<()> {
+    formatter.write_header(w)?;
+    for movie in movies {
+        formatter.write_movie(movie, w)?;
+    }
+    formatter.write_footer(w)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// The table printed by `list`, `search` and friends, run through the
+/// installed [`Printer`].
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{write_movies, ColorScheme, Movie, TableFormatter};
+///
+/// let movie = Movie { id: 7, title: "Heat".into(), year: 1995, ..Default::default() };
+/// let mut out = Vec::new();
+/// let formatter = TableFormatter { shown: 1, total: 3, colors: ColorScheme::None };
+/// write_movies(&formatter, &[&movie], &mut out).unwrap();
+///
+/// let out = String::from_utf8(out).unwrap();
+/// let lines: Vec<&str> = out.lines().collect();
+/// assert_eq!(lines[0], "📽️  Showing 1 of 3 movies:");
+/// assert!(lines[2].starts_with("ID       Title"));
+/// assert!(lines[4].starts_with("7        Heat"));
+/// assert_eq!(lines[5], "         └─ Qualities: ");
+/// ```
+pub struct TableFormatter {
+    /// Movies that will be written.
+    pub shown: usize,
+    /// Movies that matched, of which `shown` are written.
+    pub total: usize,
+    pub colors: ColorScheme,
+}
+
+impl TableFormatter {
+    fn quality_label(&self, torrent: &Torrent) -> String {
+        match (self.colors, quality_color(&torrent.quality)) {
+            (ColorScheme::QualityColor, Some(color)) => {
+                console::style(torrent.label()).fg(color).force_styling(true).to_string()
+            }
+            _ => torrent.label(),
+        }
+    }
+
+    fn line(&self, w: &mut dyn Write, text: &str) -> Result<()> {
+        writeln!(w, "{}", Printer::global().render(text))?;
+        Ok(())
+    }
+}
+
+impl Formatter for TableFormatter {
+    fn write_header(&self, w: &mut dyn Write) -> Result<()> {
+        self.line(w, &format!("📽️  Showing {} of {} movies:\n", self.shown, self.total))?;
+        self.line(w, &format!("{:<8} {:<50} {:<6} {:<12} {:<7} {:<10}", "ID", "Title", "Year", "IMDb", "Rating", "Torrents"))?;
+        self.line(w, &"=".repeat(108))
+    }
+
+    fn write_movie(&self, movie: &Movie, w: &mut dyn Write) -> Result<()> {
+        let title_truncated = if movie.title.chars().count() > 47 {
+            format!("{}...", truncate_chars(&movie.title, 47))
+        } else {
+            movie.title.clone()
+        };
+
+        self.line(
+            w,
+            &format!(
+                "{:<8} {:<50} {:<6} {:<12} {:<7.1} {:<10}",
+                movie.id,
+                title_truncated,
+                movie.year,
+                movie.imdb_code,
+                movie.rating,
+                movie.torrents.len()
+            ),
+        )?;
+
+        // Show torrent qualities
+        let qualities: Vec<String> = movie.torrents.iter().map(|t| self.quality_label(t)).collect();
+        self.line(w, &format!("         └─ Qualities: {}\n", qualities.join(", ")))
+    }
+}
+
+/// A pretty-printed JSON array of movies. The array is valid JSON even when
+/// no movies are written.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{write_movies, JsonFormatter, Movie};
+///
+/// let movie = Movie { id: 7, title: "Heat".into(), ..Default::default() };
+/// let mut out = Vec::new();
+/// write_movies(&JsonFormatter::default(), &[&movie], &mut out).unwrap();
+///
+/// let out = String::from_utf8(out).unwrap();
+/// assert!(out.starts_with("[\n  {\n    \"id\": 7,"));
+/// let parsed: Vec<Movie> = serde_json::from_str(&out).unwrap();
+/// assert_eq!(parsed[0].title, "Heat");
+/// ```
+#[derive(Default)]
+pub struct JsonFormatter {
+    written: std::cell::Cell<bool>,
+}
+
+impl Formatter for JsonFormatter {
+    fn write_header(&self, w: &mut dyn Write) -> Result<()> {
+        w.write_all(b"[")?;
+        Ok(())
+    }
+
+    fn write_movie(&self, movie: &Movie, w: &mut dyn Write) -> Result<()> {
+        w.write_all(if self.written.replace(true) { b",\n" } else { b"\n" })?;
+        for (i, line) in serde_json::to_string_pretty(movie)?.lines().enumerate() {
+            if i > 0 {
+                w.write_all(b"\n")?;
+            }
+            write!(w, "  {}", line)?;
+        }
+        Ok(())
+    }
+
+    fn write_footer(&self, w: &mut dyn Write) -> Result<()> {
+        w.write_all(if self.written.get() { b"\n]\n" } else { b"]\n" })?;
+        Ok(())
+    }
+}
+
+/// CSV with the same columns and one row per torrent as `export --format csv`.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{write_movies, CsvFormatter, Movie, Torrent};
+///
+/// let torrent = Torrent { quality: "1080p".into(), hash: "ABC".into(), ..Default::default() };
+/// let movie = Movie { id: 7, title: "Heat, Part 1".into(), year: 1995, torrents: vec![torrent], ..Default::default() };
+/// let mut out = Vec::new();
+/// write_movies(&CsvFormatter, &[&movie], &mut out).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "id,title,year,imdb_code,quality,torrent_type,size_bytes,hash,magnet_url\n\
+///      7,\"Heat, Part 1\",1995,,1080p,,0,ABC,\n"
+/// );
+/// ```
+pub struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn write_header(&self, w: &mut dyn Write) -> Result<()> {
+        let mut csv_writer = csv::Writer::from_writer(w);
+        csv_writer.write_record(EXPORT_COLUMNS)?;
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    fn write_movie(&self, movie: &Movie, w: &mut dyn Write) -> Result<()> {
+        let mut csv_writer = csv::WriterBuilder::new().has_headers(false).from_writer(w);
+        if movie.torrents.is_empty() {
+            csv_writer.serialize(ExportRow::new(movie, None))?;
+        }
+        for torrent in &movie.torrents {
+            csv_writer.serialize(ExportRow::new(movie, Some(torrent)))?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+}
+
+/// One compact JSON movie object per line, as written by [`write_ndjson`](crate::write_ndjson).
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{write_movies, Movie, NdjsonFormatter};
+///
+/// let movie = Movie { id: 7, title: "Heat".into(), ..Default::default() };
+/// let mut out = Vec::new();
+/// write_movies(&NdjsonFormatter, &[&movie, &movie], &mut out).unwrap();
+///
+/// let out = String::from_utf8(out).unwrap();
+/// assert_eq!(out.lines().count(), 2);
+/// assert!(out.starts_with("{\"id\":7,\"title\":\"Heat\","));
+/// ```
+pub struct NdjsonFormatter;
+
+impl Formatter for NdjsonFormatter {
+    fn write_movie(&self, movie: &Movie, w: &mut dyn Write) -> Result<()> {
+        serde_json::to_writer(&mut *w, movie)?;
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl OutputFormat {
+    /// The formatter for this format. `shown` and `total` are only used by
+    /// the table header.
+    pub fn formatter(self, shown: usize, total: usize) -> Box<dyn Formatter> {
+        match self {
+            OutputFormat::Table => Box::new(TableFormatter { shown, total, colors: ColorScheme::None }),
+            OutputFormat::Json => Box::new(JsonFormatter::default()),
+            OutputFormat::Csv => Box::new(CsvFormatter),
+            OutputFormat::Ndjson => Box::new(NdjsonFormatter),
+        }
+    }
+}
+
+/// A column `list --fields` can print.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListField {
+    Id,
+    Title,
+    Year,
+    Imdb,
+    Rating,
+    Quality,
+    /// Torrent size in bytes.
+    Size,
+    Hash,
+    Magnet,
+}
+
+impl ListField {
+    pub const ALL: [ListField; 9] = [
+        ListField::Id,
+        ListField::Title,
+        ListField::Year,
+        ListField::Imdb,
+        ListField::Rating,
+        ListField::Quality,
+        ListField::Size,
+        ListField::Hash,
+        ListField::Magnet,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ListField::Id => "id",
+            ListField::Title => "title",
+            ListField::Year => "year",
+            ListField::Imdb => "imdb",
+            ListField::Rating => "rating",
+            ListField::Quality => "quality",
+            ListField::Size => "size",
+            ListField::Hash => "hash",
+            ListField::Magnet => "magnet",
+        }
+    }
+
+    /// Whether the value differs per torrent rather than per movie.
+    fn is_torrent_field(self) -> bool {
+        matches!(self, ListField::Quality | ListField::Size | ListField::Hash | ListField::Magnet)
+    }
+
+    /// `torrent` is `None` for a movie without torrents, leaving torrent
+    /// fields empty.
+    fn value(self, movie: &Movie, torrent: Option<&Torrent>) -> String {
+        match self {
+            ListField::Id => movie.id.to_string(),
+            ListField::Title => movie.title.clone(),
+            ListField::Year => movie.year.to_string(),
+            ListField::Imdb => movie.imdb_code.clone(),
+            ListField::Rating => format!("{:.1}", movie.rating),
+            ListField::Quality => torrent.map(|t| t.label()).unwrap_or_default(),
+            ListField::Size => torrent.map(|t| t.size_bytes.to_string()).unwrap_or_default(),
+            ListField::Hash => torrent.map(|t| t.hash.clone()).unwrap_or_default(),
+            ListField::Magnet => torrent.map(|t| t.magnet_url.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+impl std::str::FromStr for ListField {
+    type Err = YtsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_ascii_lowercase();
+        ListField::ALL
+            .into_iter()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| YtsError::UnknownField(s.to_string()))
+    }
+}
+
+/// Lines `list --fields` prints for `movie`: one with the movie fields, or
+/// one per torrent if any torrent field (quality, size, hash, magnet) is
+/// requested.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{format_fields, ListField, Movie, Torrent};
+///
+/// let torrent = |quality: &str, hash: &str| Torrent {
+///     quality: quality.into(),
+///     torrent_type: "web".into(),
+///     hash: hash.into(),
+///     size_bytes: 1000,
+///     ..Default::default()
+/// };
+/// let movie = Movie {
+///     id: 7,
+///     title: "Heat".into(),
+///     year: 1995,
+///     rating: 8.3,
+///     torrents: vec![torrent("720p", "AAA"), torrent("1080p", "BBB")],
+///     ..Default::default()
+/// };
+///
+/// let fields: Vec<ListField> = "id,title,year".split(',').map(|f| f.parse().unwrap()).collect();
+/// assert_eq!(format_fields(&movie, &fields, "\t"), ["7\tHeat\t1995"]);
+/// assert_eq!(format_fields(&movie, &fields, " | "), ["7 | Heat | 1995"]);
+///
+/// let fields = [ListField::Title, ListField::Quality, ListField::Hash];
+/// assert_eq!(format_fields(&movie, &fields, ","), ["Heat,720p-web,AAA", "Heat,1080p-web,BBB"]);
+///
+/// let bare = Movie { title: "Lost".into(), ..Default::default() };
+/// assert_eq!(format_fields(&bare, &fields, ","), ["Lost,,"]);
+///
+/// assert_eq!("RATING".parse::<ListField>().unwrap(), ListField::Rating);
+/// let err = "genre".parse::<ListField>().unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "unknown field 'genre', expected one of: id, title, year, imdb, rating, quality, size, hash, magnet"
+/// );
+/// ```
+pub fn format_fields(movie: &Movie, fields: &[ListField], separator: &str) -> Vec<String> {
+    let line = |torrent: Option<&Torrent>| {
+        fields.iter().map(|f| f.value(movie, torrent)).collect::<Vec<_>>().join(separator)
+    };
+
+    if !fields.iter().any(|f| f.is_torrent_field()) || movie.torrents.is_empty() {
+        return vec![line(None)];
+    }
+    movie.torrents.iter().map(|t| line(Some(t))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::movie;
+
+    fn table_row(title: &str) -> String {
+        let movie = movie(7, title, 1995);
+        let mut out = Vec::new();
+        TableFormatter { shown: 1, total: 1, colors: ColorScheme::None }.write_movie(&movie, &mut out).unwrap();
+        String::from_utf8(out).unwrap().lines().next().unwrap().to_string()
+    }
+
+    #[test]
+    fn long_titles_are_cut_at_47_characters() {
+        let row = table_row(&"x".repeat(60));
+        assert!(row.contains(&format!(" {}... ", "x".repeat(47))), "{}", row);
+        assert!(table_row(&"y".repeat(47)).contains(&format!(" {} ", "y".repeat(47))));
+    }
+
+    #[test]
+    fn non_ascii_titles_are_cut_between_characters() {
+        // "é" takes two bytes, so byte 47 falls inside a character.
+        let title = format!("{}é{}", "a".repeat(46), "ü".repeat(10));
+        let row = table_row(&title);
+        assert!(row.contains(&format!(" {}é... ", "a".repeat(46))), "{}", row);
+
+        let short = "Amélie: Le Fabuleux Destin";
+        assert!(table_row(short).contains(short));
+    }
+}
@@ -0,0 +1,222 @@
+//! Static HTML pages behind `report` and `cover-grid`.
+//!
+//! The pages are rendered from the Tera templates `report.html` and
+//! `cover_grid.html`, which are compiled into the binary. They need no server:
+//! the report's table filter is plain JavaScript and its charts load Chart.js
+//! from a CDN; the cover grid is plain HTML and CSS.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{compute_stats, format_size, largest_torrent_size, CoverGridOpts, Movie, Result, Storage};
+
+const TEMPLATE: &str = include_str!("report.html");
+const COVER_GRID_TEMPLATE: &str = include_str!("cover_grid.html");
+
+#[derive(Serialize)]
+struct ReportStats {
+    movies: usize,
+    torrents: usize,
+    total_size: String,
+    first_year: u32,
+    last_year: u32,
+}
+
+#[derive(Serialize)]
+struct ReportMovie<'a> {
+    id: u32,
+    title: &'a str,
+    year: u32,
+    rating: String,
+    genres: String,
+    qualities: String,
+    size: String,
+    imdb_code: &'a str,
+}
+
+#[derive(Serialize)]
+struct Poster<'a> {
+    title: &'a str,
+    year: u32,
+    /// Already escaped for an attribute value; Tera's own escaping would
+    /// also turn every `/` of the URL into an entity.
+    cover: String,
+    imdb_code: &'a str,
+}
+
+#[derive(Serialize)]
+struct Chart {
+    labels: Vec<String>,
+    counts: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Charts {
+    years: Chart,
+    genres: Chart,
+}
+
+/// Renders the HTML report for `movies`. Titles and other text are
+/// HTML-escaped by the template.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use yts_movie_scraper::report::render_report;
+/// use yts_movie_scraper::Movie;
+///
+/// let movies = vec![
+///     Movie { id: 2, title: "Heat".into(), year: 1995, genres: vec!["Crime".into()], ..Default::default() },
+///     Movie { id: 1, title: "Tom & Jerry".into(), year: 2021, ..Default::default() },
+/// ];
+/// let html = render_report(&movies, Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()).unwrap();
+///
+/// assert!(html.contains("<td>Heat</td>"));
+/// assert!(html.contains("<td>Tom &amp; Jerry</td>"));
+/// assert!(html.contains("2024-05-01 12:00 UTC"));
+/// assert!(html.contains(r#""labels":["1995","2021"]"#));
+/// ```
+pub fn render_report(movies: &[Movie], generated_at: DateTime<Utc>) -> Result<String> {
+    let stats = compute_stats(movies);
+
+    let rows: Vec<ReportMovie> = movies
+        .iter()
+        .map(|m| ReportMovie {
+            id: m.id,
+            title: &m.title,
+            year: m.year,
+            rating: format!("{:.1}", m.rating),
+            genres: m.genres.join(", "),
+            qualities: m.torrents.iter().map(|t| t.label()).collect::<Vec<_>>().join(", "),
+            size: format_size(largest_torrent_size(m)),
+            imdb_code: &m.imdb_code,
+        })
+        .collect();
+
+    let charts = Charts {
+        years: Chart {
+            labels: stats.per_year.keys().map(u32::to_string).collect(),
+            counts: stats.per_year.values().map(|y| y.count).collect(),
+        },
+        genres: Chart {
+            labels: stats.per_genre.keys().cloned().collect(),
+            counts: stats.per_genre.values().copied().collect(),
+        },
+    };
+    // Inlined into a <script> block unescaped, so no value may close it.
+    let charts = serde_json::to_string(&charts)?.replace('<', "\\u003c");
+
+    let mut context = tera::Context::new();
+    context.insert("generated_at", &generated_at.format("%Y-%m-%d %H:%M UTC").to_string());
+    context.insert(
+        "stats",
+        &ReportStats {
+            movies: stats.total_movies,
+            torrents: stats.total_torrents,
+            total_size: format_size(stats.total_size),
+            first_year: stats.year_range.0,
+            last_year: stats.year_range.1,
+        },
+    );
+    context.insert("movies", &rows);
+    context.insert("charts", &charts);
+
+    Ok(tera::Tera::one_off(TEMPLATE, &context, true)?)
+}
+
+pub fn report_command(storage: &dyn Storage, output: &Path) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    fs::write(output, render_report(&movies, Utc::now())?)?;
+    say!("✅ Wrote a report of {} movies to {}", movies.len(), output.display());
+
+    Ok(())
+}
+
+/// Renders a grid of the cover images of `movies`, each linking to the
+/// movie's IMDb page.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::report::render_cover_grid;
+/// use yts_movie_scraper::Movie;
+///
+/// let movies = [
+///     Movie {
+///         title: "Heat".into(),
+///         year: 1995,
+///         imdb_code: "tt0113277".into(),
+///         large_cover_image: "https://img.test/heat.jpg".into(),
+///         ..Default::default()
+///     },
+///     Movie {
+///         title: "Dune".into(),
+///         year: 2021,
+///         large_cover_image: "https://img.test/dune.jpg".into(),
+///         ..Default::default()
+///     },
+/// ];
+/// let html = render_cover_grid(&movies.iter().collect::<Vec<_>>()).unwrap();
+///
+/// assert!(html.contains(r#"<img src="https://img.test/heat.jpg" alt="Heat (1995)" loading="lazy">"#));
+/// assert!(html.contains(r#"<img src="https://img.test/dune.jpg" alt="Dune (2021)" loading="lazy">"#));
+/// assert!(html.contains(r#"<a href="https://www.imdb.com/title/tt0113277/">"#));
+/// ```
+pub fn render_cover_grid(movies: &[&Movie]) -> Result<String> {
+    let posters: Vec<Poster> = movies
+        .iter()
+        .map(|m| Poster {
+            title: &m.title,
+            year: m.year,
+            cover: escape_attribute(&m.large_cover_image),
+            imdb_code: &m.imdb_code,
+        })
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("posters", &posters);
+
+    Ok(tera::Tera::one_off(COVER_GRID_TEMPLATE, &context, true)?)
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn cover_grid_command(storage: &dyn Storage, opts: &CoverGridOpts) -> Result<()> {
+    let movies = storage.load()?;
+
+    if movies.is_empty() {
+        say!("❌ No movies found in database. Run 'fetch' first.");
+        return Ok(());
+    }
+
+    let mut matches: Vec<&Movie> = movies.iter().filter(|m| opts.matches(m)).collect();
+    if matches.is_empty() {
+        say!("🔍 No movies with a cover image match the given filters.");
+        return Ok(());
+    }
+    if opts.limit > 0 {
+        matches.truncate(opts.limit);
+    }
+
+    fs::write(&opts.path, render_cover_grid(&matches)?)?;
+    say!("✅ Wrote {} posters to {}", matches.len(), opts.path.display());
+
+    Ok(())
+}
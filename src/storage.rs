@@ -0,0 +1,592 @@
+//! Reading and writing the local database, and the metadata file kept next to it.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{split_quality_type, Movie, OmdbUsage, Result, Torrent, YtsError};
+
+pub const OUTPUT_FILE: &str = "yts_movies.json";
+pub const SQLITE_OUTPUT_FILE: &str = "yts_movies.db";
+
+/// Sync metadata stored next to the database file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FetchMeta {
+    pub last_fetched_at: DateTime<Utc>,
+    pub movie_count: u32,
+    /// Total movie count reported by the API at the last sync.
+    #[serde(default)]
+    pub total_count: Option<u32>,
+    #[serde(default)]
+    pub omdb_usage: Option<OmdbUsage>,
+    /// `--min-api-rating` the database was fetched with.
+    #[serde(default)]
+    pub minimum_rating: Option<u8>,
+    /// `--genre` filters the database was fetched with; empty for every genre.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    /// API movie count at the last `watch-new` poll since this sync.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_count: Option<u32>,
+}
+
+/// Older databases stored the torrent type inside `quality`; move it into
+/// `torrent_type` so both formats load the same way.
+fn migrate_torrent_types(movies: &mut [Movie]) {
+    for torrent in movies.iter_mut().flat_map(|m| m.torrents.iter_mut()) {
+        if torrent.torrent_type.is_empty() && torrent.quality.contains(['-', '.']) {
+            (torrent.quality, torrent.torrent_type) = split_quality_type(&torrent.quality);
+        }
+    }
+}
+
+/// Reads a JSON database, returning an empty list if the file does not exist.
+pub fn load_existing_movies(path: &Path) -> Result<Vec<Movie>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let mut movies: Vec<Movie> = serde_json::from_str(&content)?;
+    migrate_torrent_types(&mut movies);
+    Ok(movies)
+}
+
+/// Writes movies to a JSON database, one movie object per line.
+///
+/// Movies are serialized one at a time into a buffered writer, so memory use
+/// does not grow with the size of the database.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{load_existing_movies, save_movies, Movie, Torrent};
+///
+/// let movies: Vec<Movie> = (1..=10_000)
+///     .map(|id| Movie {
+///         id,
+///         title: format!("Movie \"{}\"", id),
+///         genres: vec!["Drama".into()],
+///         torrents: vec![Torrent { quality: "1080p".into(), size_bytes: id as u64, ..Default::default() }],
+///         ..Default::default()
+///     })
+///     .collect();
+/// let path = std::env::temp_dir().join(format!("yts-save-movies-{}.json", std::process::id()));
+///
+/// save_movies(&path, &movies).unwrap();
+/// let loaded = load_existing_movies(&path).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+///
+/// assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&movies).unwrap());
+/// ```
+pub fn save_movies(path: &Path, movies: &[Movie]) -> Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+
+    writer.write_all(b"[\n")?;
+    for (i, movie) in movies.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",\n")?;
+        }
+        serde_json::to_writer(&mut writer, movie)?;
+    }
+    writer.write_all(b"\n]\n")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StorageBackend {
+    Json,
+    Sqlite,
+}
+
+impl StorageBackend {
+    pub fn default_path(self) -> &'static str {
+        match self {
+            StorageBackend::Json => OUTPUT_FILE,
+            StorageBackend::Sqlite => SQLITE_OUTPUT_FILE,
+        }
+    }
+
+    pub fn open(self, path: &Path) -> Box<dyn Storage> {
+        let path = path.to_path_buf();
+        match self {
+            StorageBackend::Json => Box::new(JsonStorage { path }),
+            StorageBackend::Sqlite => Box::new(SqliteStorage { path }),
+        }
+    }
+}
+
+pub trait Storage {
+    fn load(&self) -> Result<Vec<Movie>>;
+    fn save(&self, movies: &[Movie]) -> Result<()>;
+
+    /// Location of the database on disk, also used to place sidecar files.
+    fn path(&self) -> &Path;
+}
+
+pub struct JsonStorage {
+    pub path: PathBuf,
+}
+
+impl Storage for JsonStorage {
+    fn load(&self) -> Result<Vec<Movie>> {
+        load_existing_movies(&self.path)
+    }
+
+    fn save(&self, movies: &[Movie]) -> Result<()> {
+        save_movies(&self.path, movies)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+pub struct SqliteStorage {
+    pub path: PathBuf,
+}
+
+impl SqliteStorage {
+    fn connect(&self) -> Result<rusqlite::Connection> {
+        let conn = rusqlite::Connection::open(&self.path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS movies (
+                id INTEGER PRIMARY KEY,
+                title TEXT NOT NULL,
+                year INTEGER NOT NULL,
+                imdb_code TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS torrents (
+                movie_id INTEGER NOT NULL REFERENCES movies(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL,
+                quality TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                magnet_url TEXT NOT NULL,
+                PRIMARY KEY (movie_id, position)
+            );",
+        )?;
+
+        // Columns added after the initial schema
+        ensure_column(&conn, "movies", "rating", "REAL NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "movies", "mpa_rating", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "large_cover_image", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "medium_cover_image", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "small_cover_image", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "genres", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "date_uploaded_unix", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "torrents", "torrent_type", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "torrents", "url", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "plot", "TEXT")?;
+        ensure_column(&conn, "movies", "director", "TEXT")?;
+        ensure_column(&conn, "movies", "awards", "TEXT")?;
+        ensure_column(&conn, "movies", "watchlisted", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "movies", "runtime_minutes", "INTEGER")?;
+        ensure_column(&conn, "movies", "backdrop_url", "TEXT")?;
+        ensure_column(&conn, "movies", "spoken_languages", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "tags", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "movies", "yts_id", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "torrents", "seeds", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "torrents", "peers", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "torrents", "dht_peers", "INTEGER")?;
+
+        Ok(conn)
+    }
+}
+
+/// Genres, spoken languages and tags are stored in SQLite as a single
+/// comma-separated column.
+fn split_genres(joined: &str) -> Vec<String> {
+    joined.split(',').filter(|g| !g.is_empty()).map(String::from).collect()
+}
+
+/// Adds `column` to `table` when a database created by an older version lacks it.
+fn ensure_column(conn: &rusqlite::Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<Vec<Movie>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.connect()?;
+
+        let mut torrents: HashMap<u32, Vec<Torrent>> = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT movie_id, quality, hash, size_bytes, magnet_url, torrent_type, url, seeds, peers, dht_peers
+             FROM torrents ORDER BY movie_id, position",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                Torrent {
+                    quality: row.get(1)?,
+                    torrent_type: row.get(5)?,
+                    hash: row.get(2)?,
+                    size_bytes: row.get::<_, i64>(3)? as u64,
+                    magnet_url: row.get(4)?,
+                    url: row.get(6)?,
+                    seeds: row.get(7)?,
+                    peers: row.get(8)?,
+                    dht_peers: row.get(9)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (movie_id, torrent) = row?;
+            torrents.entry(movie_id).or_default().push(torrent);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT id, title, year, imdb_code, rating, mpa_rating, large_cover_image, medium_cover_image,
+                    small_cover_image, genres, date_uploaded_unix, plot, director, awards,
+                    watchlisted, runtime_minutes, backdrop_url, spoken_languages, tags, yts_id
+             FROM movies ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Movie {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                year: row.get(2)?,
+                imdb_code: row.get(3)?,
+                rating: row.get(4)?,
+                mpa_rating: row.get(5)?,
+                large_cover_image: row.get(6)?,
+                medium_cover_image: row.get(7)?,
+                small_cover_image: row.get(8)?,
+                genres: split_genres(&row.get::<_, String>(9)?),
+                date_uploaded_unix: row.get(10)?,
+                torrents: Vec::new(),
+                plot: row.get(11)?,
+                director: row.get(12)?,
+                awards: row.get(13)?,
+                watchlisted: row.get(14)?,
+                runtime_minutes: row.get(15)?,
+                backdrop_url: row.get(16)?,
+                spoken_languages: split_genres(&row.get::<_, String>(17)?),
+                tags: split_genres(&row.get::<_, String>(18)?),
+                yts_id: row.get(19)?,
+            })
+        })?;
+
+        let mut movies = Vec::new();
+        for row in rows {
+            let mut movie = row?;
+            movie.torrents = torrents.remove(&movie.id).unwrap_or_default();
+            movies.push(movie);
+        }
+
+        migrate_torrent_types(&mut movies);
+        Ok(movies)
+    }
+
+    /// Fails with [`YtsError::DuplicateId`] if two movies share an ID, as
+    /// the `movies` table holds one row per ID.
+    fn save(&self, movies: &[Movie]) -> Result<()> {
+        let mut seen = HashSet::new();
+        if let Some(movie) = movies.iter().find(|m| !seen.insert(m.id)) {
+            return Err(YtsError::DuplicateId(movie.id));
+        }
+
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM torrents", [])?;
+        tx.execute("DELETE FROM movies", [])?;
+
+        {
+            let mut insert_movie = tx.prepare(
+                "INSERT INTO movies (id, title, year, imdb_code, rating, mpa_rating, large_cover_image, medium_cover_image,
+                                     small_cover_image, genres, date_uploaded_unix, plot, director, awards,
+                                     watchlisted, runtime_minutes, backdrop_url, spoken_languages, tags, yts_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+            )?;
+            let mut insert_torrent = tx.prepare(
+                "INSERT INTO torrents (movie_id, position, quality, hash, size_bytes, magnet_url, torrent_type, url, seeds,
+                                       peers, dht_peers)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+
+            for movie in movies {
+                insert_movie.execute(rusqlite::params![
+                    movie.id,
+                    movie.title,
+                    movie.year,
+                    movie.imdb_code,
+                    movie.rating,
+                    movie.mpa_rating,
+                    movie.large_cover_image,
+                    movie.medium_cover_image,
+                    movie.small_cover_image,
+                    movie.genres.join(","),
+                    movie.date_uploaded_unix,
+                    movie.plot,
+                    movie.director,
+                    movie.awards,
+                    movie.watchlisted,
+                    movie.runtime_minutes,
+                    movie.backdrop_url,
+                    movie.spoken_languages.join(","),
+                    movie.tags.join(","),
+                    movie.yts_id
+                ])?;
+
+                for (position, torrent) in movie.torrents.iter().enumerate() {
+                    insert_torrent.execute(rusqlite::params![
+                        movie.id,
+                        position,
+                        torrent.quality,
+                        torrent.hash,
+                        torrent.size_bytes as i64,
+                        torrent.magnet_url,
+                        torrent.torrent_type,
+                        torrent.url,
+                        torrent.seeds,
+                        torrent.peers,
+                        torrent.dht_peers
+                    ])?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn meta_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("meta.json")
+}
+
+pub fn load_meta(db_path: &Path) -> Result<Option<FetchMeta>> {
+    let path = meta_path(db_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+pub(crate) fn save_meta(
+    db_path: &Path,
+    movie_count: u32,
+    total_count: u32,
+    omdb_usage: Option<OmdbUsage>,
+    minimum_rating: Option<u8>,
+    genres: &[String],
+) -> Result<()> {
+    let meta = FetchMeta {
+        last_fetched_at: Utc::now(),
+        movie_count,
+        total_count: Some(total_count),
+        omdb_usage,
+        minimum_rating,
+        genres: genres.to_vec(),
+        last_seen_count: None,
+    };
+    write_meta(db_path, &meta)
+}
+
+pub(crate) fn write_meta(db_path: &Path, meta: &FetchMeta) -> Result<()> {
+    let json = serde_json::to_string_pretty(meta)?;
+    fs::write(meta_path(db_path), json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{movie, torrent};
+
+    #[test]
+    fn meta_round_trips_next_to_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("action.json");
+        assert!(load_meta(&db).unwrap().is_none());
+
+        let before = Utc::now();
+        save_meta(&db, 120, 4_000, None, Some(7), &["action".to_string()]).unwrap();
+        assert!(dir.path().join("action.meta.json").exists());
+
+        let meta = load_meta(&db).unwrap().unwrap();
+        assert_eq!(meta.movie_count, 120);
+        assert_eq!(meta.total_count, Some(4_000));
+        assert_eq!(meta.minimum_rating, Some(7));
+        assert_eq!(meta.genres, ["action"]);
+        assert!(meta.last_fetched_at >= before && meta.last_fetched_at <= Utc::now());
+    }
+
+    #[test]
+    fn meta_without_the_later_fields_still_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("yts_movies.json");
+        fs::write(meta_path(&db), r#"{"last_fetched_at":"2024-05-01T12:00:00Z","movie_count":42}"#).unwrap();
+
+        let meta = load_meta(&db).unwrap().unwrap();
+        assert_eq!(meta.movie_count, 42);
+        assert_eq!(meta.last_fetched_at.to_rfc3339(), "2024-05-01T12:00:00+00:00");
+        assert_eq!(meta.total_count, None);
+        assert!(meta.genres.is_empty());
+    }
+
+    #[test]
+    fn unreadable_meta_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("yts_movies.json");
+        fs::write(meta_path(&db), "{").unwrap();
+
+        assert!(matches!(load_meta(&db), Err(YtsError::ParseError(_))));
+    }
+
+    /// Every field set, so a backend that drops one fails the round trip.
+    fn full_movie(id: u32) -> Movie {
+        let mut movie = movie(id, &format!("Movie {}", id), 2001);
+        movie.rating = 7.5;
+        movie.mpa_rating = "R".to_string();
+        movie.genres = vec!["Action".to_string(), "Crime".to_string()];
+        movie.large_cover_image = format!("https://img.example/{}/large.jpg", id);
+        movie.medium_cover_image = format!("https://img.example/{}/medium.jpg", id);
+        movie.small_cover_image = format!("https://img.example/{}/small.jpg", id);
+        movie.date_uploaded_unix = 1_700_000_000 + id as i64;
+        movie.plot = Some("A heist goes wrong.".to_string());
+        movie.director = Some("Someone".to_string());
+        movie.awards = None;
+        movie.watchlisted = id % 2 == 1;
+        movie.runtime_minutes = Some(118);
+        movie.backdrop_url = Some(format!("https://img.example/{}/backdrop.jpg", id));
+        movie.spoken_languages = vec!["English".to_string(), "French".to_string()];
+        movie.tags = vec!["heist".to_string()];
+        movie.yts_id = id + 1000;
+        let mut uhd = torrent("2160p", 5_000_000_000 + id as u64);
+        uhd.torrent_type = "bluray".to_string();
+        uhd.url = format!("https://yts.example/torrent/{}", id);
+        uhd.seeds = 40;
+        uhd.peers = 3;
+        uhd.dht_peers = Some(12);
+        movie.torrents[0].torrent_type = "web".to_string();
+        movie.torrents.push(uhd);
+        movie
+    }
+
+    fn as_json(movies: &[Movie]) -> serde_json::Value {
+        serde_json::to_value(movies).unwrap()
+    }
+
+    /// The checks every [`Storage`] implementation has to pass.
+    fn roundtrip(storage: impl Storage) {
+        assert!(storage.load().unwrap().is_empty());
+
+        let movies = vec![full_movie(3), full_movie(2), Movie { torrents: Vec::new(), ..movie(1, "Bare", 1999) }];
+        storage.save(&movies).unwrap();
+        assert_eq!(as_json(&storage.load().unwrap()), as_json(&movies));
+
+        // Saving replaces the previous contents.
+        storage.save(&movies[1..]).unwrap();
+        assert_eq!(as_json(&storage.load().unwrap()), as_json(&movies[1..]));
+
+        storage.save(&[]).unwrap();
+        assert!(storage.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        roundtrip(JsonStorage { path: dir.path().join("yts_movies.json") });
+    }
+
+    #[test]
+    fn sqlite_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        roundtrip(SqliteStorage { path: dir.path().join("yts_movies.db") });
+    }
+
+    #[test]
+    fn sqlite_rejects_duplicate_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage { path: dir.path().join("yts_movies.db") };
+        storage.save(&[full_movie(1)]).unwrap();
+
+        let duplicates = [full_movie(2), movie(1, "First", 2000), movie(1, "Second", 2000)];
+        assert!(matches!(storage.save(&duplicates), Err(YtsError::DuplicateId(1))));
+
+        // The failed save leaves the database as it was.
+        assert_eq!(as_json(&storage.load().unwrap()), as_json(&[full_movie(1)]));
+    }
+
+    /// A movie as the first version of the scraper stored it.
+    const BASELINE_MOVIE: &str = r#"[{"id":7,"title":"Heat","year":1995,"imdb_code":"tt0113277","torrents":[
+        {"quality":"1080p","hash":"ABC","size_bytes":5,"magnet_url":"magnet:?xt=urn:btih:ABC"}
+    ]}]"#;
+
+    #[test]
+    fn baseline_databases_load_with_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("yts_movies.json");
+        fs::write(&path, BASELINE_MOVIE).unwrap();
+
+        let movies = JsonStorage { path }.load().unwrap();
+        let heat = &movies[0];
+        assert_eq!((heat.id, heat.title.as_str(), heat.year), (7, "Heat", 1995));
+        assert_eq!(heat.rating, 0.0);
+        assert_eq!(heat.mpa_rating, "");
+        assert!(heat.genres.is_empty());
+        let torrent = &heat.torrents[0];
+        assert_eq!((torrent.seeds, torrent.peers), (0, 0));
+        assert_eq!((torrent.quality.as_str(), torrent.torrent_type.as_str()), ("1080p", ""));
+    }
+
+    const COMBINED_QUALITIES: &str = r#"[{"id":7,"title":"Heat","year":1995,"imdb_code":"tt0113277","torrents":[
+        {"quality":"1080p-bluray","hash":"A","size_bytes":5,"magnet_url":""},
+        {"quality":"720p.web","hash":"B","size_bytes":3,"magnet_url":""},
+        {"quality":"2160p","hash":"C","size_bytes":9,"magnet_url":""},
+        {"quality":"1080p","torrent_type":"web","hash":"D","size_bytes":4,"magnet_url":""}
+    ]}]"#;
+
+    fn labels(movies: &[Movie]) -> Vec<(String, String)> {
+        movies[0].torrents.iter().map(|t| (t.quality.clone(), t.torrent_type.clone())).collect()
+    }
+
+    fn split(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(q, t)| (q.to_string(), t.to_string())).collect()
+    }
+
+    #[test]
+    fn combined_qualities_are_split_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("yts_movies.json");
+        fs::write(&path, COMBINED_QUALITIES).unwrap();
+        let storage = JsonStorage { path };
+
+        let expected = split(&[("1080p", "bluray"), ("720p", "web"), ("2160p", ""), ("1080p", "web")]);
+        assert_eq!(labels(&storage.load().unwrap()), expected);
+
+        // Saving writes the split fields, so the next load needs no migration.
+        storage.save(&storage.load().unwrap()).unwrap();
+        assert!(!fs::read_to_string(&storage.path).unwrap().contains("1080p-bluray"));
+        assert_eq!(labels(&storage.load().unwrap()), expected);
+    }
+}
@@ -0,0 +1,196 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::{ApiVersion, JsonStorage, Movie, OmdbApi, Storage, Torrent, YtsApi};
+
+/// Path the mock servers serve `list_movies` on.
+pub(crate) const LIST_MOVIES_PATH: &str = "/api/v2/list_movies.json";
+
+/// A movie with a single 1080p torrent.
+pub(crate) fn movie(id: u32, title: &str, year: u32) -> Movie {
+    Movie {
+        id,
+        title: title.to_string(),
+        year,
+        imdb_code: format!("tt{:07}", id),
+        torrents: vec![torrent("1080p", 1_000_000_000 + id as u64)],
+        ..Default::default()
+    }
+}
+
+/// A torrent whose hash is derived from its size, so distinct sizes give
+/// distinct valid info hashes.
+pub(crate) fn torrent(quality: &str, size_bytes: u64) -> Torrent {
+    let hash = format!("{:040X}", size_bytes);
+    Torrent {
+        quality: quality.to_string(),
+        magnet_url: format!("magnet:?xt=urn:btih:{}", hash),
+        hash,
+        size_bytes,
+        ..Default::default()
+    }
+}
+
+/// A JSON database at `dir/yts_movies.json` holding `movies`.
+pub(crate) fn json_storage(dir: &Path, movies: &[Movie]) -> JsonStorage {
+    let storage = JsonStorage { path: dir.join("yts_movies.json") };
+    storage.save(movies).unwrap();
+    storage
+}
+
+/// Genres of the mock movie `id`: even ids are action movies, odd ones dramas.
+pub(crate) fn mock_genres(id: u32) -> Vec<&'static str> {
+    if id.is_multiple_of(2) {
+        vec!["Action", "Thriller"]
+    } else {
+        vec!["Drama"]
+    }
+}
+
+/// The movie `id` as the YTS API lists it. Its IMDb rating is `id % 10`.
+pub(crate) fn api_movie(id: u32) -> Value {
+    json!({
+        "id": id,
+        "title": format!("Movie {}", id),
+        "year": 2000 + id % 20,
+        "imdb_code": format!("tt{:07}", id),
+        "rating": (id % 10) as f32,
+        "genres": mock_genres(id),
+        "date_uploaded_unix": 1_600_000_000 + id as i64 * 86_400,
+        "torrents": [{
+            "quality": "1080p",
+            "type": "web",
+            "hash": format!("{:040X}", id),
+            "size_bytes": 1_000_000_000 + id as u64,
+            "seeds": id % 100,
+            "peers": id % 7,
+        }],
+    })
+}
+
+/// A `list_movies` response body. YTS leaves out `movies` past the last page.
+pub(crate) fn list_response(ids: &[u32], movie_count: u32) -> Value {
+    let mut data = json!({ "movie_count": movie_count });
+    if !ids.is_empty() {
+        data["movies"] = ids.iter().map(|&id| api_movie(id)).collect();
+    }
+    json!({ "status": "ok", "data": data })
+}
+
+/// A client for `server` with `page_size` movies per page and no retries.
+pub(crate) fn yts_api(server: &MockServer, page_size: u32) -> YtsApi {
+    YtsApi {
+        client: reqwest::Client::new(),
+        base_url: format!("{}{}", server.uri(), LIST_MOVIES_PATH).parse().unwrap(),
+        version: ApiVersion::V2,
+        max_retries: 0,
+        page_size,
+        api_key: None,
+        page_cache: None,
+    }
+}
+
+/// An OMDb client for `server`, which answers on `/omdb/` for the key "key".
+pub(crate) fn omdb_api(server: &MockServer) -> OmdbApi {
+    OmdbApi {
+        client: reqwest::Client::new(),
+        base_url: format!("{}/omdb/", server.uri()).parse().unwrap(),
+        api_key: "key".to_string(),
+    }
+}
+
+/// A mock `list_movies` endpoint listing the movies `ids` newest first. It
+/// honours `limit`, `page`, `genre` and `minimum_rating` like YTS, and records
+/// the page and arrival time of every request.
+#[derive(Clone)]
+pub(crate) struct Catalogue {
+    ids: Arc<Mutex<Vec<u32>>>,
+    delay: fn(u32) -> Duration,
+    requests: Arc<Mutex<Vec<(u32, Instant)>>>,
+}
+
+impl Catalogue {
+    /// The movies `1..=count`.
+    pub(crate) fn new(count: u32) -> Self {
+        Catalogue::with_ids((1..=count).collect())
+    }
+
+    pub(crate) fn with_ids(ids: Vec<u32>) -> Self {
+        let catalogue = Catalogue { ids: Arc::default(), delay: |_| Duration::ZERO, requests: Arc::default() };
+        catalogue.add(ids);
+        catalogue
+    }
+
+    /// Lists `ids` as well, also on a running server.
+    pub(crate) fn add(&self, ids: impl IntoIterator<Item = u32>) {
+        let mut listed = self.ids.lock().unwrap();
+        listed.extend(ids);
+        listed.sort_unstable_by(|a, b| b.cmp(a));
+    }
+
+    /// Answers each page after `delay(page)`.
+    pub(crate) fn delayed(mut self, delay: fn(u32) -> Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Starts a server answering with this catalogue.
+    pub(crate) async fn serve(&self) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path(LIST_MOVIES_PATH)).respond_with(self.clone()).mount(&server).await;
+        server
+    }
+
+    /// Pages requested so far, in the order the requests arrived.
+    pub(crate) fn pages(&self) -> Vec<u32> {
+        self.requests.lock().unwrap().iter().map(|(page, _)| *page).collect()
+    }
+
+    /// The most requests the server was answering at once. A request counts
+    /// as in flight from its arrival until its delay is over.
+    pub(crate) fn max_in_flight(&self) -> usize {
+        let requests = self.requests.lock().unwrap();
+        requests
+            .iter()
+            .map(|(_, arrived)| {
+                requests
+                    .iter()
+                    .filter(|(page, other)| other <= arrived && *arrived < *other + (self.delay)(*page))
+                    .count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Respond for Catalogue {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let param = |name: &str| request.url.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.into_owned());
+        let page: u32 = param("page").map_or(1, |p| p.parse().unwrap());
+        let limit: usize = param("limit").map_or(20, |l| l.parse().unwrap());
+        let genre = param("genre");
+        let minimum_rating: u32 = param("minimum_rating").map_or(0, |r| r.parse().unwrap());
+        self.requests.lock().unwrap().push((page, Instant::now()));
+
+        let ids: Vec<u32> = self
+            .ids
+            .lock()
+            .unwrap()
+            .iter()
+            .copied()
+            .filter(|id| genre.as_ref().is_none_or(|g| mock_genres(*id).iter().any(|m| m.eq_ignore_ascii_case(g))))
+            .filter(|id| id % 10 >= minimum_rating)
+            .collect();
+        let start = (page as usize - 1) * limit;
+        let listed = ids.get(start.min(ids.len())..(start + limit).min(ids.len())).unwrap_or_default();
+
+        ResponseTemplate::new(200).set_body_json(list_response(listed, ids.len() as u32)).set_delay((self.delay)(page))
+    }
+}
@@ -0,0 +1,413 @@
+//! Magnet links, info hashes and `.torrent` files.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    download_with_progress, format_size, quality_matches, sanitize_filename, Movie, Result, Storage, Torrent, YtsApi,
+    YtsError,
+};
+
+pub const DEFAULT_TRACKERS: &[&str] = &[
+    "udp://open.demonii.com:1337/announce",
+    "udp://tracker.openbittorrent.com:80",
+    "udp://tracker.coppersurfer.tk:6969",
+    "udp://glotorrents.pw:6969/announce",
+    "udp://tracker.opentrackr.org:1337/announce",
+    "udp://torrent.gresille.org:80/announce",
+    "udp://p4p.arenabg.com:1337",
+    "udp://tracker.leechers-paradise.org:6969",
+];
+/// Hosts of trackers that are known to be offline, checked by `lint`.
+pub const DEAD_TRACKERS: &[&str] = &[
+    "tracker.coppersurfer.tk",
+    "tracker.leechers-paradise.org",
+    "glotorrents.pw",
+    "torrent.gresille.org",
+    "tracker.openbittorrent.com",
+];
+
+/// Builds a magnet link for a torrent hash, announcing to every tracker given.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::create_magnet_url;
+///
+/// let trackers = vec!["udp://tracker.opentrackr.org:1337/announce".to_string()];
+/// let magnet = create_magnet_url("ABC123", "The Matrix", &trackers);
+///
+/// assert_eq!(
+///     magnet,
+///     "magnet:?xt=urn:btih:ABC123&dn=The+Matrix&tr=udp://tracker.opentrackr.org:1337/announce"
+/// );
+/// ```
+pub fn create_magnet_url(hash: &str, title: &str, trackers: &[String]) -> String {
+    let encoded_title = title.replace(' ', "+");
+    let mut magnet = format!("magnet:?xt=urn:btih:{}&dn={}", hash, encoded_title);
+    for tracker in trackers {
+        magnet.push_str("&tr=");
+        magnet.push_str(tracker);
+    }
+    magnet
+}
+
+/// Whether `hash` is a BitTorrent v1 info hash written as exactly 40
+/// lowercase hexadecimal characters.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::is_valid_sha1_hash;
+///
+/// assert!(is_valid_sha1_hash("c0edf0f169275d7d889dee3c073122b26fdfaca0"));
+/// assert!(is_valid_sha1_hash(&"0".repeat(40)));
+/// assert!(!is_valid_sha1_hash("C0EDF0F169275D7D889DEE3C073122B26FDFACA0"));
+/// assert!(!is_valid_sha1_hash("c0edf0f169275d7d889dee3c073122b26fdfaca"));
+/// assert!(!is_valid_sha1_hash("c0edf0f169275d7d889dee3c073122b26fdfaca00"));
+/// assert!(!is_valid_sha1_hash("g0edf0f169275d7d889dee3c073122b26fdfaca0"));
+/// assert!(!is_valid_sha1_hash("c0edf0f169275d7d889dee3c073122b2 6fdfaca"));
+/// assert!(!is_valid_sha1_hash(""));
+/// ```
+pub fn is_valid_sha1_hash(hash: &str) -> bool {
+    hash.len() == 40 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// The movie and torrent with info hash `hash`, ignoring case. Only
+/// 40-character (BitTorrent v1, SHA-1) and 64-character (v2, SHA-256) hex
+/// hashes can match.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{find_by_hash, Movie, Torrent};
+///
+/// let v1 = "C0EDF0F169275D7D889DEE3C073122B26FDFACA0";
+/// let v2 = "a".repeat(64);
+/// let torrent = |quality: &str, hash: &str| Torrent { quality: quality.into(), hash: hash.into(), ..Default::default() };
+/// let movie = |id, title: &str, torrents| Movie { id, title: title.into(), torrents, ..Default::default() };
+/// let movies = vec![
+///     movie(1, "Heat", vec![torrent("720p", &"b".repeat(40)), torrent("1080p", v1)]),
+///     movie(2, "Dune", vec![torrent("2160p", &v2)]),
+/// ];
+///
+/// let (movie, torrent) = find_by_hash(&movies, "c0edf0f169275d7d889dee3c073122b26fdfaca0").unwrap();
+/// assert_eq!((movie.id, torrent.quality.as_str()), (1, "1080p"));
+/// assert_eq!(find_by_hash(&movies, &v2.to_uppercase()).unwrap().0.title, "Dune");
+///
+/// assert!(find_by_hash(&movies, &"c".repeat(40)).is_none());
+/// assert!(find_by_hash(&movies, "c0edf0f1").is_none());
+/// ```
+pub fn find_by_hash<'a>(movies: &'a [Movie], hash: &str) -> Option<(&'a Movie, &'a Torrent)> {
+    let hash = hash.trim().to_ascii_lowercase();
+    if !is_info_hash(&hash) {
+        return None;
+    }
+
+    movies
+        .iter()
+        .flat_map(|m| m.torrents.iter().map(move |t| (m, t)))
+        .find(|(_, t)| t.hash.eq_ignore_ascii_case(&hash))
+}
+
+/// 40 or 64 lowercase hex characters.
+pub(crate) fn is_info_hash(hash: &str) -> bool {
+    matches!(hash.len(), 40 | 64) && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[derive(Debug, Deserialize)]
+struct RawTorrent {
+    #[serde(default)]
+    announce: Option<String>,
+    #[serde(default, rename = "announce-list")]
+    announce_list: Vec<Vec<String>>,
+    #[serde(default, rename = "creation date")]
+    creation_date: Option<i64>,
+    info: RawTorrentInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawTorrentInfo {
+    name: String,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    /// Set for single-file torrents.
+    #[serde(default)]
+    length: Option<u64>,
+    /// Set for multi-file torrents.
+    #[serde(default)]
+    files: Vec<RawTorrentFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawTorrentFile {
+    length: u64,
+    path: Vec<String>,
+}
+
+/// A file inside a multi-file torrent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorrentMetaFile {
+    /// Path below the torrent's top-level directory, joined with `/`.
+    pub path: String,
+    pub length: u64,
+}
+
+/// The parts of a .torrent file worth showing to a user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorrentMeta {
+    /// File name, or the directory name for multi-file torrents.
+    pub name: String,
+    /// Total size of all files in bytes.
+    pub length: u64,
+    /// Empty for single-file torrents.
+    pub files: Vec<TorrentMetaFile>,
+    pub piece_length: u64,
+    /// Trackers from `announce-list`, or `announce` if there is no list,
+    /// without duplicates.
+    pub trackers: Vec<String>,
+    pub creation_date: Option<DateTime<Utc>>,
+}
+
+/// Decodes the metadata of a bencoded .torrent file.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::decode_torrent_info;
+///
+/// let single = b"d8:announce21:udp://tracker.test:8013:creation datei1700000000e\
+///     4:infod6:lengthi1048576e4:name8:test.mkv12:piece lengthi262144e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+/// let meta = decode_torrent_info(single).unwrap();
+/// assert_eq!(meta.name, "test.mkv");
+/// assert_eq!(meta.length, 1048576);
+/// assert_eq!(meta.piece_length, 262144);
+/// assert!(meta.files.is_empty());
+/// assert_eq!(meta.trackers, ["udp://tracker.test:80"]);
+/// assert_eq!(meta.creation_date.unwrap().to_rfc3339(), "2023-11-14T22:13:20+00:00");
+///
+/// let multi = b"d13:announce-listll5:udp:ael5:udp:b5:udp:aee\
+///     4:infod5:filesld6:lengthi10e4:pathl4:Subs5:a.srteed6:lengthi20e4:pathl5:b.mkveee\
+///     4:name3:dir12:piece lengthi16384e6:pieces0:ee";
+/// let meta = decode_torrent_info(multi).unwrap();
+/// assert_eq!(meta.length, 30);
+/// assert_eq!(meta.files[0].path, "Subs/a.srt");
+/// assert_eq!(meta.files[1].length, 20);
+/// assert_eq!(meta.trackers, ["udp:a", "udp:b"]);
+/// assert_eq!(meta.creation_date, None);
+///
+/// assert!(decode_torrent_info(b"not bencode").is_err());
+/// assert!(decode_torrent_info(b"d8:announce5:udp:ae").is_err());
+/// ```
+pub fn decode_torrent_info(data: &[u8]) -> Result<TorrentMeta> {
+    let raw: RawTorrent = bt_bencode::from_slice(data)?;
+
+    let files: Vec<TorrentMetaFile> = raw
+        .info
+        .files
+        .into_iter()
+        .map(|f| TorrentMetaFile {
+            path: f.path.join("/"),
+            length: f.length,
+        })
+        .collect();
+    let length = raw.info.length.unwrap_or_else(|| files.iter().map(|f| f.length).sum());
+
+    let mut trackers: Vec<String> = raw.announce_list.into_iter().flatten().collect();
+    if trackers.is_empty() {
+        trackers.extend(raw.announce);
+    }
+    let mut seen = HashSet::new();
+    trackers.retain(|t| seen.insert(t.clone()));
+
+    Ok(TorrentMeta {
+        name: raw.info.name,
+        length,
+        files,
+        piece_length: raw.info.piece_length,
+        trackers,
+        creation_date: raw.creation_date.and_then(|t| DateTime::from_timestamp(t, 0)),
+    })
+}
+
+pub fn torrent_info_command(path: &Path) -> Result<()> {
+    let meta = decode_torrent_info(&fs::read(path)?)?;
+
+    say!("📦 {}", meta.name);
+    say!("   💾 Size: {}", format_size(meta.length));
+    say!("   🧩 Piece length: {}", format_size(meta.piece_length));
+    match meta.creation_date {
+        Some(date) => say!("   📅 Created: {}", date.format("%Y-%m-%d %H:%M:%S UTC")),
+        None => say!("   📅 Created: unknown"),
+    }
+
+    if !meta.files.is_empty() {
+        say!("\n📁 Files ({}):", meta.files.len());
+        for file in &meta.files {
+            say!("   {} ({})", file.path, format_size(file.length));
+        }
+    }
+
+    if meta.trackers.is_empty() {
+        say!("\n📡 No trackers (DHT only)");
+    } else {
+        say!("\n📡 Trackers ({}):", meta.trackers.len());
+        for tracker in &meta.trackers {
+            say!("   {}", tracker);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn download_torrent_file(
+    storage: &dyn Storage,
+    api: &YtsApi,
+    id: u32,
+    quality: &str,
+    output_dir: &Path,
+) -> Result<()> {
+    let movies = storage.load()?;
+
+    let Some(movie) = movies.iter().find(|m| m.id == id) else {
+        return Err(YtsError::NotFound(id));
+    };
+
+    let Some(torrent) = movie.torrents.iter().find(|t| quality_matches(&t.label(), quality)) else {
+        let available: Vec<String> = movie.torrents.iter().map(|t| t.label()).collect();
+        say_err!(
+            "❌ No {} torrent for {} ({}). Available: {}",
+            quality,
+            movie.title,
+            movie.year,
+            available.join(", ")
+        );
+        return Ok(());
+    };
+
+    if torrent.url.is_empty() {
+        say_err!("❌ No .torrent URL stored for {}. Re-fetch the database to pick up torrent URLs.", movie.title);
+        return Ok(());
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let dest = output_dir.join(format!("{}_{}.torrent", sanitize_filename(&movie.title), torrent.label()));
+
+    say!("⬇️  Downloading {} {} torrent", movie.title, torrent.label());
+    let bytes = download_with_progress(&api.client, &torrent.url, &dest).await?;
+
+    say!("✅ Saved {} ({})", dest.display(), format_size(bytes));
+
+    Ok(())
+}
+
+/// Rebuilds every torrent's magnet URL from its hash and the movie title.
+/// Returns the number of torrents updated.
+pub fn regenerate_magnets(movies: &mut [Movie], trackers: &[String]) -> usize {
+    let mut updated = 0;
+
+    for movie in movies.iter_mut() {
+        for torrent in &mut movie.torrents {
+            torrent.magnet_url = create_magnet_url(&torrent.hash, &movie.title, trackers);
+            updated += 1;
+        }
+    }
+
+    updated
+}
+
+pub(crate) fn is_dead_tracker(tracker: &str, blocklist: &[&str]) -> bool {
+    Url::parse(tracker)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| blocklist.iter().any(|dead| host.eq_ignore_ascii_case(dead))))
+        .unwrap_or(false)
+}
+
+/// Returns the `tr=` trackers of `magnet` whose host is in `blocklist`.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{find_dead_trackers, DEAD_TRACKERS};
+///
+/// let magnet = "magnet:?xt=urn:btih:ABC&dn=Heat\
+///     &tr=udp://tracker.coppersurfer.tk:6969\
+///     &tr=udp://tracker.opentrackr.org:1337/announce";
+///
+/// assert_eq!(find_dead_trackers(magnet, DEAD_TRACKERS), vec!["udp://tracker.coppersurfer.tk:6969"]);
+/// assert!(find_dead_trackers("magnet:?xt=urn:btih:ABC", DEAD_TRACKERS).is_empty());
+/// ```
+pub fn find_dead_trackers(magnet: &str, blocklist: &[&str]) -> Vec<String> {
+    let Some((_, query)) = magnet.split_once('?') else {
+        return Vec::new();
+    };
+
+    url::form_urlencoded::parse(query.as_bytes())
+        .filter(|(key, _)| key == "tr")
+        .map(|(_, tracker)| tracker.into_owned())
+        .filter(|tracker| is_dead_tracker(tracker, blocklist))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::test_support::{json_storage, movie, torrent, yts_api};
+
+    #[test]
+    fn regenerate_magnets_counts_torrents_not_movies() {
+        let mut heat = movie(2, "Heat", 1995);
+        heat.torrents.push(torrent("720p", 7));
+        let mut lost = movie(1, "Lost", 2004);
+        lost.torrents.clear();
+        let mut movies = [heat, lost];
+
+        let trackers = ["udp://a.test:1337".to_string()];
+        assert_eq!(regenerate_magnets(&mut movies, &trackers), 2);
+        assert_eq!(
+            movies[0].torrents[1].magnet_url,
+            format!("magnet:?xt=urn:btih:{:040X}&dn=Heat&tr=udp://a.test:1337", 7)
+        );
+    }
+
+    #[tokio::test]
+    async fn torrent_files_are_saved_under_the_title_and_quality() {
+        let server = MockServer::start().await;
+        let contents: Vec<u8> = (0..=255).cycle().take(70_000).collect();
+        Mock::given(method("GET"))
+            .and(path("/torrent/download/ABC"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(contents.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let mut heat = movie(2, "Heat: Director's Cut", 1995);
+        heat.torrents[0].torrent_type = "bluray".to_string();
+        heat.torrents[0].url = format!("{}/torrent/download/ABC", server.uri());
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[heat]);
+        let output_dir = dir.path().join("torrents");
+
+        download_torrent_file(&storage, &yts_api(&server, 20), 2, "1080p", &output_dir).await.unwrap();
+        let saved = fs::read(output_dir.join("Heat__Director_s_Cut_1080p-bluray.torrent")).unwrap();
+        assert_eq!(saved, contents);
+    }
+
+    #[tokio::test]
+    async fn torrent_files_of_unknown_movies_are_an_error() {
+        let server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(2, "Heat", 1995)]);
+
+        let err = download_torrent_file(&storage, &yts_api(&server, 20), 9, "1080p", dir.path()).await.unwrap_err();
+        assert!(matches!(err, YtsError::NotFound(9)));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+//! Interactive terminal browser behind `list --interactive`.
+//!
+//! The search logic ([`search_movies`]) is kept apart from the rendering so it
+//! can be used and checked without a terminal.
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::{format_movie_card, Movie, Result};
+
+/// Rows moved by PageUp / PageDown.
+const PAGE_ROWS: usize = 20;
+
+/// Indices of the movies matching every whitespace-separated word of `query`.
+/// A word matches a movie when it appears, ignoring case, in the title, the
+/// IMDb code, a genre or the release year. An empty query matches everything.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::tui::search_movies;
+/// use yts_movie_scraper::Movie;
+///
+/// let movie = |title: &str, year, genre: &str| Movie {
+///     title: title.into(),
+///     year,
+///     genres: vec![genre.into()],
+///     ..Default::default()
+/// };
+/// let movies = vec![
+///     movie("The Matrix", 1999, "Action"),
+///     movie("Matrix Reloaded", 2003, "Action"),
+///     movie("Amélie", 2001, "Romance"),
+/// ];
+///
+/// assert_eq!(search_movies(&movies, ""), [0, 1, 2]);
+/// assert_eq!(search_movies(&movies, "MATRIX"), [0, 1]);
+/// assert_eq!(search_movies(&movies, "matrix 2003"), [1]);
+/// assert_eq!(search_movies(&movies, "romance"), [2]);
+/// assert!(search_movies(&movies, "matrix romance").is_empty());
+/// ```
+pub fn search_movies(movies: &[Movie], query: &str) -> Vec<usize> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+
+    movies
+        .iter()
+        .enumerate()
+        .filter(|(_, movie)| words.iter().all(|word| movie_matches_word(movie, word)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// `word` must already be lowercase.
+fn movie_matches_word(movie: &Movie, word: &str) -> bool {
+    movie.title.to_lowercase().contains(word)
+        || movie.imdb_code.to_lowercase() == *word
+        || movie.year.to_string() == *word
+        || movie.genres.iter().any(|g| g.to_lowercase().contains(word))
+}
+
+enum Mode {
+    Browse,
+    /// Typing a search query; the table filters on every keystroke.
+    Search,
+    /// Full `info` card of the selected movie, scrolled by this many lines.
+    Detail(u16),
+}
+
+struct App {
+    movies: Vec<Movie>,
+    /// Indices into `movies` matching `query`, in display order.
+    visible: Vec<usize>,
+    query: String,
+    table: TableState,
+    mode: Mode,
+}
+
+impl App {
+    fn new(movies: Vec<Movie>) -> Self {
+        let visible = (0..movies.len()).collect();
+        let mut app = App {
+            movies,
+            visible,
+            query: String::new(),
+            table: TableState::default(),
+            mode: Mode::Browse,
+        };
+        app.select(0);
+        app
+    }
+
+    fn selected_movie(&self) -> Option<&Movie> {
+        self.table.selected().and_then(|row| self.visible.get(row)).map(|&i| &self.movies[i])
+    }
+
+    /// Selects `row`, clamped to the visible rows.
+    fn select(&mut self, row: usize) {
+        let row = (!self.visible.is_empty()).then(|| row.min(self.visible.len() - 1));
+        self.table.select(row);
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let current = self.table.selected().unwrap_or(0);
+        self.select(current.saturating_add_signed(delta));
+    }
+
+    fn refilter(&mut self) {
+        self.visible = search_movies(&self.movies, &self.query);
+        self.select(0);
+    }
+
+    /// Handles one key press; returns false when the app should quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        match self.mode {
+            Mode::Browse => match key.code {
+                KeyCode::Esc if !self.query.is_empty() => {
+                    self.query.clear();
+                    self.refilter();
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return false,
+                KeyCode::Char('j') | KeyCode::Down => self.move_by(1),
+                KeyCode::Char('k') | KeyCode::Up => self.move_by(-1),
+                KeyCode::PageDown | KeyCode::Char('d') => self.move_by(PAGE_ROWS as isize),
+                KeyCode::PageUp | KeyCode::Char('u') => self.move_by(-(PAGE_ROWS as isize)),
+                KeyCode::Char('g') | KeyCode::Home => self.select(0),
+                KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+                KeyCode::Char('/') => self.mode = Mode::Search,
+                KeyCode::Enter if self.selected_movie().is_some() => self.mode = Mode::Detail(0),
+                _ => {}
+            },
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.refilter();
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.refilter();
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.refilter();
+                }
+                _ => {}
+            },
+            Mode::Detail(scroll) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Char('j') | KeyCode::Down => self.mode = Mode::Detail(scroll.saturating_add(1)),
+                KeyCode::Char('k') | KeyCode::Up => self.mode = Mode::Detail(scroll.saturating_sub(1)),
+                _ => {}
+            },
+        }
+
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+        if let (Mode::Detail(scroll), Some(movie)) = (&self.mode, self.selected_movie()) {
+            let card = Paragraph::new(format_movie_card(movie))
+                .block(Block::bordered().title(format!(" {} ", movie.title)))
+                .wrap(Wrap { trim: false })
+                .scroll((*scroll, 0));
+            frame.render_widget(card, main);
+        } else {
+            let rows = self.visible.iter().map(|&i| {
+                let movie = &self.movies[i];
+                let qualities: Vec<String> = movie.torrents.iter().map(|t| t.label()).collect();
+                Row::new([
+                    movie.id.to_string(),
+                    movie.title.clone(),
+                    movie.year.to_string(),
+                    format!("{:.1}", movie.rating),
+                    qualities.join(", "),
+                ])
+            });
+            let widths = [
+                Constraint::Length(8),
+                Constraint::Fill(3),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Fill(2),
+            ];
+            let title = format!(" {} of {} movies ", self.visible.len(), self.movies.len());
+            let table = Table::new(rows, widths)
+                .header(Row::new(["ID", "Title", "Year", "Rating", "Qualities"]).bold())
+                .block(Block::bordered().title(title))
+                .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                .highlight_symbol("> ");
+            frame.render_stateful_widget(table, main, &mut self.table);
+        }
+
+        let help = match self.mode {
+            Mode::Browse if self.query.is_empty() => "j/k move  / search  Enter details  q quit".to_string(),
+            Mode::Browse => format!("filter: {}  / edit  Esc clear  Enter details  q quit", self.query),
+            Mode::Search => format!("/{}█  (Enter keep, Esc clear)", self.query),
+            Mode::Detail(_) => "j/k scroll  q back".to_string(),
+        };
+        frame.render_widget(Line::from(help).dim(), status);
+    }
+}
+
+fn run_app(terminal: &mut DefaultTerminal, app: &mut App) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Shows `movies` in a full-screen browser until the user quits. The terminal
+/// is restored even if drawing fails.
+pub fn run(movies: Vec<Movie>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, &mut App::new(movies));
+    ratatui::restore();
+
+    Ok(result?)
+}
@@ -0,0 +1,531 @@
+//! Polling YTS for new movies: `watch`, `watch-new` and notifications.
+
+use std::ffi::OsString;
+use std::future::Future;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::{Args, ValueEnum};
+
+use crate::{
+    check_new_movies, compute_total_pages, fetch_movies, fetch_page, format_movie_card, load_meta, movie_from_api,
+    write_meta, ApiMovie, FetchOptions, FetchParams, NewMovies, Result, Storage, YtsApi, YtsError,
+};
+
+/// How `watch-new` prints each new movie.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum WatchFormat {
+    /// The full card shown by `info`
+    Info,
+    /// One JSON movie object per line
+    Ndjson,
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Titles listed in a notification before the rest are summed up.
+const NOTIFY_MAX_TITLES: usize = 5;
+
+/// Summary and body of the notification announcing `new_movies`.
+///
+/// # Examples
+///
+/// ```
+/// use yts_movie_scraper::{notification_message, NewMovies};
+///
+/// let titles = ["Heat", "Ronin", "Collateral", "Thief", "Drive", "Sicario"];
+/// let new_movies = NewMovies { count: 6, titles: titles.iter().map(|t| t.to_string()).collect() };
+/// let (summary, body) = notification_message(&new_movies);
+/// assert_eq!(summary, "6 new movies on YTS");
+/// assert_eq!(body, "Heat\nRonin\nCollateral\nThief\nDrive\n…and 1 more");
+///
+/// let (summary, body) = notification_message(&NewMovies { count: 1, titles: vec!["Heat".into()] });
+/// assert_eq!((summary.as_str(), body.as_str()), ("1 new movie on YTS", "Heat"));
+///
+/// let (_, body) = notification_message(&NewMovies { count: 73025, titles: Vec::new() });
+/// assert_eq!(body, "");
+/// ```
+pub fn notification_message(new_movies: &NewMovies) -> (String, String) {
+    let plural = if new_movies.count == 1 { "" } else { "s" };
+    let summary = format!("{} new movie{} on YTS", new_movies.count, plural);
+
+    let mut lines: Vec<String> = new_movies.titles.iter().take(NOTIFY_MAX_TITLES).cloned().collect();
+    let rest = new_movies.titles.len().saturating_sub(NOTIFY_MAX_TITLES);
+    if rest > 0 {
+        lines.push(format!("…and {} more", rest));
+    }
+
+    (summary, lines.join("\n"))
+}
+
+/// Where `check --notify` and `watch --notify` announce new movies.
+pub trait Notifier {
+    fn notify(&self, new_movies: &NewMovies) -> Result<()>;
+}
+
+/// Shows a notification with a summary and a body.
+pub type ShowNotification = Box<dyn Fn(&str, &str) -> Result<()> + Send + Sync>;
+
+/// A desktop notification through the system notification service.
+pub struct DesktopNotifier {
+    show: ShowNotification,
+    env_var: fn(&str) -> Option<OsString>,
+}
+
+impl Default for DesktopNotifier {
+    fn default() -> Self {
+        DesktopNotifier::new(Box::new(show_desktop_notification), |var| std::env::var_os(var))
+    }
+}
+
+impl DesktopNotifier {
+    /// A notifier showing notifications with `show`, finding the display
+    /// server through `env_var`.
+    pub fn new(show: ShowNotification, env_var: fn(&str) -> Option<OsString>) -> Self {
+        DesktopNotifier { show, env_var }
+    }
+
+    /// Whether there is a desktop to show notifications on. Only checked on
+    /// Linux and the BSDs, where headless machines have no display server.
+    pub fn is_available(&self) -> bool {
+        if cfg!(any(target_os = "macos", target_os = "windows")) {
+            return true;
+        }
+        ["DISPLAY", "WAYLAND_DISPLAY"]
+            .iter()
+            .any(|var| (self.env_var)(var).is_some_and(|v| !v.is_empty()))
+    }
+}
+
+fn show_desktop_notification(summary: &str, body: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname("yts-movie-scraper")
+        .summary(summary)
+        .body(body)
+        .show()
+        .map_err(|e| YtsError::IoError(io::Error::other(e)))?;
+    Ok(())
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, new_movies: &NewMovies) -> Result<()> {
+        if !self.is_available() {
+            tracing::warn!("no display found, skipping the desktop notification");
+            return Ok(());
+        }
+
+        let (summary, body) = notification_message(new_movies);
+        (self.show)(&summary, &body)
+    }
+}
+
+/// Runs a shell command, with `{count}` replaced by the number of new movies.
+/// Titles are passed in the `YTS_NEW_TITLES` environment variable, one per
+/// line, rather than spliced into the command where they could inject shell
+/// syntax.
+pub struct CommandNotifier {
+    pub command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&self, new_movies: &NewMovies) -> Result<()> {
+        let command = self.command.replace("{count}", &new_movies.count.to_string());
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+
+        let status = std::process::Command::new(shell)
+            .arg(flag)
+            .arg(&command)
+            .env("YTS_NEW_TITLES", new_movies.titles.join("\n"))
+            .status()?;
+        if !status.success() {
+            return Err(YtsError::IoError(io::Error::other(format!("notify command failed with {}", status))));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug, Default)]
+pub struct NotifyOpts {
+    /// Show a desktop notification when new movies are found
+    #[arg(long)]
+    pub notify: bool,
+
+    /// Run this shell command instead of a desktop notification; "{count}" is
+    /// replaced by the number of new movies and their titles are in $YTS_NEW_TITLES
+    #[arg(long, value_name = "CMD")]
+    pub notify_command: Option<String>,
+}
+
+impl NotifyOpts {
+    /// The configured notifier, or `None` if notifications are off.
+    pub fn notifier(&self) -> Option<Box<dyn Notifier>> {
+        match &self.notify_command {
+            Some(command) => Some(Box::new(CommandNotifier { command: command.clone() })),
+            None if self.notify => Some(Box::<DesktopNotifier>::default()),
+            None => None,
+        }
+    }
+}
+
+/// Sends the notification if there is anything new. A failed notification is
+/// reported but does not fail the check.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+/// use yts_movie_scraper::{notification_message, notify_new_movies, NewMovies, Notifier, Result};
+///
+/// #[derive(Default)]
+/// struct Recorder(RefCell<Vec<(String, String)>>);
+///
+/// impl Notifier for Recorder {
+///     fn notify(&self, new_movies: &NewMovies) -> Result<()> {
+///         self.0.borrow_mut().push(notification_message(new_movies));
+///         Ok(())
+///     }
+/// }
+///
+/// let recorder = Recorder::default();
+/// notify_new_movies(Some(&recorder), &NewMovies::default());
+/// assert!(recorder.0.borrow().is_empty());
+///
+/// let new_movies = NewMovies { count: 2, titles: vec!["Heat".into(), "Ronin".into()] };
+/// notify_new_movies(Some(&recorder), &new_movies);
+/// assert_eq!(*recorder.0.borrow(), [("2 new movies on YTS".to_string(), "Heat\nRonin".to_string())]);
+/// ```
+pub fn notify_new_movies(notifier: Option<&dyn Notifier>, new_movies: &NewMovies) {
+    let Some(notifier) = notifier.filter(|_| new_movies.count > 0) else {
+        return;
+    };
+    if let Err(e) = notifier.notify(new_movies) {
+        say_err!("⚠️  Could not send notification: {}", e);
+    }
+}
+
+/// Settings of the `watch` loop.
+pub struct WatchOptions<'a> {
+    /// Time to wait between polls.
+    pub interval: Duration,
+    /// Fetch new movies as soon as they are found.
+    pub auto_fetch: bool,
+    /// Number of pages requested in parallel when fetching.
+    pub concurrency: u32,
+    pub trackers: &'a [String],
+    pub notifier: Option<&'a dyn Notifier>,
+}
+
+/// Polls YTS every `opts.interval` until Ctrl-C or SIGTERM.
+pub async fn watch_movies(storage: &dyn Storage, api: &YtsApi, opts: &WatchOptions<'_>) -> Result<()> {
+    watch_until(storage, api, opts, shutdown_signal()).await
+}
+
+/// The `watch` loop, stopping once `shutdown` resolves.
+async fn watch_until(
+    storage: &dyn Storage,
+    api: &YtsApi,
+    opts: &WatchOptions<'_>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    // Listen for signals in the background so a poll or fetch that is already
+    // running gets to finish (and save) before we exit.
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown.await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    say!("👀 Watching for new movies every {} minutes (Ctrl-C to stop)", opts.interval.as_secs() / 60);
+
+    loop {
+        say!("\n🕒 [{}] Polling YTS", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+
+        match check_new_movies(storage, api, None).await {
+            Ok(new_movies) => {
+                notify_new_movies(opts.notifier, &new_movies);
+                if new_movies.count > 0 && opts.auto_fetch {
+                    let fetch_opts = FetchOptions::new(opts.concurrency, opts.trackers);
+                    if let Err(e) = fetch_movies(storage, api, &fetch_opts).await {
+                        say_err!("❌ Fetch failed: {}", e);
+                    }
+                }
+            }
+            Err(e) => say_err!("❌ Check failed: {}", e),
+        }
+
+        if *shutdown_rx.borrow() {
+            break;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(opts.interval) => {}
+            _ = shutdown_rx.changed() => break,
+        }
+    }
+
+    say!("\n👋 Stopping watch");
+    Ok(())
+}
+
+/// Asks YTS for its movie count and returns it with the movies added since
+/// it reported `last_seen`, newest first. Removals on YTS offset additions,
+/// so a count that did not grow yields no movies.
+pub async fn poll_new_movies(api: &YtsApi, last_seen: u32) -> Result<(u32, Vec<ApiMovie>)> {
+    let params = FetchParams::default();
+    let first = fetch_page(api, &params, 1).await?;
+    let count = first.data.movie_count;
+    let wanted = count.saturating_sub(last_seen) as usize;
+    let last_page = compute_total_pages(count, api.page_size);
+
+    let mut movies = first.data.movies.unwrap_or_default();
+    let mut page = 1;
+    while movies.len() < wanted && page < last_page {
+        page += 1;
+        movies.extend(fetch_page(api, &params, page).await?.data.movies.unwrap_or_default());
+    }
+    movies.truncate(wanted);
+
+    Ok((count, movies))
+}
+
+/// Prints movies as they appear on YTS, polling every `interval_secs` until
+/// Ctrl-C. Status lines go to stderr so stdout only carries the movies.
+pub async fn watch_new_command(
+    storage: &dyn Storage,
+    api: &YtsApi,
+    interval_secs: u64,
+    format: WatchFormat,
+    trackers: &[String],
+) -> Result<()> {
+    let mut last_seen = load_meta(storage.path())?.and_then(|m| m.last_seen_count.or(m.total_count));
+    if last_seen.is_none() {
+        say_err!("ℹ️  No earlier movie count in the meta file; showing movies added from now on");
+    }
+    say_err!("👀 Polling YTS for new movies every {} seconds (Ctrl-C to stop)", interval_secs);
+
+    let mut ticks = tokio::time::interval(Duration::from_secs(interval_secs));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = &mut shutdown => break,
+        }
+
+        let (count, new_movies) = match poll_new_movies(api, last_seen.unwrap_or(u32::MAX)).await {
+            Ok(polled) => polled,
+            Err(e) => {
+                say_err!("❌ Poll failed: {}", e);
+                continue;
+            }
+        };
+
+        let mut out = io::stdout().lock();
+        for api_movie in &new_movies {
+            let movie = movie_from_api(api_movie, Some(trackers));
+            match format {
+                WatchFormat::Info => writeln!(out, "{}\n", format_movie_card(&movie))?,
+                WatchFormat::Ndjson => {
+                    serde_json::to_writer(&mut out, &movie)?;
+                    writeln!(out)?;
+                }
+            }
+        }
+        out.flush()?;
+
+        last_seen = Some(count);
+        if let Some(mut meta) = load_meta(storage.path())? {
+            meta.last_seen_count = Some(count);
+            write_meta(storage.path(), &meta)?;
+        }
+    }
+
+    say_err!("\n👋 Stopping watch");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test_support::{json_storage, movie, yts_api, Catalogue};
+
+    fn ids(movies: &[ApiMovie]) -> Vec<u32> {
+        movies.iter().map(|m| m.id).collect()
+    }
+
+    #[tokio::test]
+    async fn polls_return_the_movies_added_since_the_last_count() {
+        let catalogue = Catalogue::new(2);
+        let server = catalogue.serve().await;
+        let api = yts_api(&server, 50);
+
+        let (count, movies) = poll_new_movies(&api, 2).await.unwrap();
+        assert_eq!((count, movies.len()), (2, 0));
+
+        catalogue.add(3..=4);
+        let (count, movies) = poll_new_movies(&api, count).await.unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(ids(&movies), [4, 3]);
+
+        let (_, movies) = poll_new_movies(&api, count).await.unwrap();
+        assert!(movies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn polls_read_further_pages_until_every_new_movie_is_found() {
+        let catalogue = Catalogue::new(3);
+        let server = catalogue.serve().await;
+        let api = yts_api(&server, 2);
+
+        catalogue.add(4..=8);
+        let (count, movies) = poll_new_movies(&api, 3).await.unwrap();
+
+        assert_eq!(count, 8);
+        assert_eq!(ids(&movies), [8, 7, 6, 5, 4]);
+        assert_eq!(catalogue.pages(), [1, 2, 3]);
+    }
+
+    fn watching(interval: Duration) -> WatchOptions<'static> {
+        WatchOptions { interval, auto_fetch: false, concurrency: 1, trackers: &[], notifier: None }
+    }
+
+    /// Resolves once `catalogue` has answered `requests` requests.
+    async fn after_requests(catalogue: Catalogue, requests: usize) {
+        while catalogue.pages().len() < requests {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn watch_polls_until_shut_down() {
+        let catalogue = Catalogue::new(3);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[movie(3, "C", 2003), movie(2, "B", 2002), movie(1, "A", 2001)]);
+
+        let api = yts_api(&server, 20);
+        check_new_movies(&storage, &api, None).await.unwrap();
+        let per_poll = catalogue.pages().len();
+
+        // Shut down as soon as the second poll starts. It still finishes, but
+        // no third one starts.
+        let shutdown = after_requests(catalogue.clone(), 2 * per_poll + 1);
+        watch_until(&storage, &api, &watching(Duration::from_millis(100)), shutdown).await.unwrap();
+        assert_eq!(catalogue.pages().len(), 3 * per_poll);
+    }
+
+    #[tokio::test]
+    async fn watch_stops_while_waiting_for_the_next_poll() {
+        let catalogue = Catalogue::new(3);
+        let server = catalogue.serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = json_storage(dir.path(), &[]);
+
+        let shutdown = after_requests(catalogue.clone(), 1);
+        let opts = watching(Duration::from_secs(3600));
+        tokio::time::timeout(Duration::from_secs(10), watch_until(&storage, &yts_api(&server, 20), &opts, shutdown))
+            .await
+            .expect("watch did not stop")
+            .unwrap();
+    }
+
+    fn new_movies(titles: &[&str]) -> NewMovies {
+        NewMovies { count: titles.len() as u32, titles: titles.iter().map(|t| t.to_string()).collect() }
+    }
+
+    #[test]
+    fn notifications_list_the_first_five_titles() {
+        let titles = ["A", "B", "C", "D", "E", "F", "G"];
+
+        assert_eq!(notification_message(&new_movies(&titles[..1])), ("1 new movie on YTS".into(), "A".into()));
+        assert_eq!(notification_message(&new_movies(&titles[..5])).1, "A\nB\nC\nD\nE");
+        assert_eq!(
+            notification_message(&new_movies(&titles)),
+            ("7 new movies on YTS".into(), "A\nB\nC\nD\nE\n…and 2 more".into())
+        );
+    }
+
+    /// A desktop notifier recording what it shows, reading environment
+    /// variables with `env_var`.
+    fn recording_notifier(env_var: fn(&str) -> Option<OsString>) -> (DesktopNotifier, Arc<Mutex<Vec<String>>>) {
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&shown);
+        let show: ShowNotification = Box::new(move |summary: &str, body: &str| {
+            recorded.lock().unwrap().push(format!("{}: {}", summary, body));
+            Ok(())
+        });
+        (DesktopNotifier::new(show, env_var), shown)
+    }
+
+    #[test]
+    fn desktop_notifications_show_the_message() {
+        let (notifier, shown) = recording_notifier(|var| (var == "WAYLAND_DISPLAY").then(|| "wayland-0".into()));
+        notifier.notify(&new_movies(&["Heat", "Ronin"])).unwrap();
+        assert_eq!(*shown.lock().unwrap(), ["2 new movies on YTS: Heat\nRonin"]);
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[test]
+    fn desktop_notifications_are_skipped_without_a_display() {
+        let (headless, shown) = recording_notifier(|_| None);
+        assert!(!headless.is_available());
+        headless.notify(&new_movies(&["Heat"])).unwrap();
+
+        let (empty, _) = recording_notifier(|_| Some(OsString::new()));
+        assert!(!empty.is_available());
+        let (x11, _) = recording_notifier(|var| (var == "DISPLAY").then(|| ":0".into()));
+        assert!(x11.is_available());
+        assert!(shown.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn nothing_is_sent_without_new_movies() {
+        let (notifier, shown) = recording_notifier(|_| Some(":0".into()));
+        notify_new_movies(Some(&notifier), &NewMovies::default());
+        assert!(shown.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn notify_commands_get_the_count_and_titles() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("notified");
+        let notifier = CommandNotifier {
+            command: format!("printf '%s|%s' {{count}} \"$YTS_NEW_TITLES\" > '{}'", out.display()),
+        };
+
+        notifier.notify(&new_movies(&["Heat", "Ronin; rm -rf /"])).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "2|Heat\nRonin; rm -rf /");
+
+        let failing = CommandNotifier { command: "exit 3".to_string() };
+        assert!(failing.notify(&new_movies(&["Heat"])).is_err());
+    }
+}
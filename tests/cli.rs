@@ -0,0 +1,157 @@
+//! End-to-end runs of the binary in a throwaway directory.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use yts_movie_scraper::{load_existing_movies, save_movies, Movie, Torrent};
+
+/// The binary run inside `dir`, with no config file and no `YTS_*`
+/// variables from the environment of the test run.
+fn cli(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_yts-movie-scraper"));
+    command.current_dir(dir).env("XDG_CONFIG_HOME", dir).env("HOME", dir).env("NO_COLOR", "1");
+    for (name, _) in std::env::vars() {
+        if name.starts_with("YTS_") {
+            command.env_remove(name);
+        }
+    }
+    command
+}
+
+/// Runs `command` and returns its stdout, panicking with its stderr if it
+/// fails.
+fn run(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn movie(id: u32, title: &str, year: u32) -> Movie {
+    let hash = format!("{:040X}", id);
+    Movie {
+        id,
+        title: title.to_string(),
+        year,
+        imdb_code: format!("tt{:07}", id),
+        torrents: vec![Torrent {
+            quality: "1080p".to_string(),
+            magnet_url: format!("magnet:?xt=urn:btih:{}", hash),
+            hash,
+            size_bytes: 1_000_000_000,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn output_flag_selects_the_database_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("genres")).unwrap();
+    let db = dir.path().join("genres/action.json");
+    save_movies(&db, &[movie(2, "Heat", 1995), movie(1, "Ronin", 1998)]).unwrap();
+
+    run(cli(dir.path()).args(["-o", "genres/action.json", "watchlist", "add", "--id", "2"]));
+    let movies = load_existing_movies(&db).unwrap();
+    assert!(movies.iter().find(|m| m.id == 2).unwrap().watchlisted);
+    assert!(!dir.path().join("yts_movies.json").exists());
+
+    let listed = run(cli(dir.path()).args(["--output", "genres/action.json", "list"]));
+    assert!(listed.contains("Heat") && listed.contains("Ronin"), "{}", listed);
+
+    let default = run(cli(dir.path()).arg("list"));
+    assert!(default.contains("No movies found in database"), "{}", default);
+}
+
+/// A YTS mirror that finds one movie, "Mirror Heat", for any query.
+async fn mirror() -> MockServer {
+    let server = MockServer::start().await;
+    let heat = json!({
+        "id": 7,
+        "title": "Mirror Heat",
+        "year": 1995,
+        "imdb_code": "tt0113277",
+        "torrents": [{ "quality": "1080p", "type": "web", "hash": "0".repeat(40), "size_bytes": 1 }],
+    });
+    Mock::given(method("GET"))
+        .and(path("/api/v2/list_movies.json"))
+        .and(query_param("query_term", "heat"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "data": { "movie_count": 1, "movies": [heat] } })),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+fn list_movies_url(server: &MockServer) -> String {
+    format!("{}/api/v2/list_movies.json", server.uri())
+}
+
+async fn requests(server: &MockServer) -> usize {
+    server.received_requests().await.unwrap().len()
+}
+
+/// A throwaway directory whose config file points `api_url` at `server`.
+fn configured_for(server: &MockServer) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let config_dir = dir.path().join(".config/yts-scraper");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(config_dir.join("config.toml"), format!("api_url = \"{}\"\n", list_movies_url(server))).unwrap();
+    dir
+}
+
+fn remote_search(dir: &Path) -> Command {
+    let mut command = cli(dir);
+    command.args(["remote-search", "--query", "heat"]);
+    command
+}
+
+#[tokio::test]
+async fn api_url_flag_overrides_the_config() {
+    let (flag, config) = (mirror().await, mirror().await);
+    let dir = configured_for(&config);
+
+    let found = run(remote_search(dir.path()).args(["--api-url", &list_movies_url(&flag)]));
+    assert!(found.contains("Mirror Heat"), "{}", found);
+    assert_eq!((requests(&flag).await, requests(&config).await), (1, 0));
+
+    run(&mut remote_search(dir.path()));
+    assert_eq!((requests(&flag).await, requests(&config).await), (1, 1));
+}
+
+#[test]
+fn api_url_flag_rejects_invalid_urls() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = remote_search(dir.path()).args(["--api-url", "not a url"]).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'not a url' is not a valid URL"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn api_url_environment_variable_sits_between_the_flag_and_the_config() {
+    let (flag, env, config) = (mirror().await, mirror().await, mirror().await);
+    let dir = configured_for(&config);
+    let counts = || async { (requests(&flag).await, requests(&env).await, requests(&config).await) };
+
+    let found = run(remote_search(dir.path()).env("YTS_API_URL", list_movies_url(&env)));
+    assert!(found.contains("Mirror Heat"), "{}", found);
+    assert_eq!(counts().await, (0, 1, 0));
+
+    let flag_url = list_movies_url(&flag);
+    run(remote_search(dir.path()).env("YTS_API_URL", list_movies_url(&env)).args(["--api-url", &flag_url]));
+    assert_eq!(counts().await, (1, 1, 0));
+}
+
+#[test]
+fn completions_cover_the_subcommands() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let script = run(cli(dir.path()).args(["completions", "bash"]));
+    assert!(!script.is_empty());
+    assert!(script.contains("remote-search"), "{}", script);
+}